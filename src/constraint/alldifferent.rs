@@ -1,9 +1,10 @@
 //! All different implementation.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap,HashSet};
 use std::rc::Rc;
 
 use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+use super::ConsistencyLevel;
 
 pub struct AllDifferent {
     vars: Vec<VarToken>,
@@ -21,10 +22,24 @@ impl AllDifferent {
     ///
     /// puzzle_solver::constraint::AllDifferent::new(&vars);
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same variable appears more than once, which
+    /// would be unsatisfiable by construction.
     pub fn new<'a, I>(vars: I) -> Self
             where I: IntoIterator<Item=&'a VarToken> {
+        let vars: Vec<VarToken> = vars.into_iter().cloned().collect();
+
+        let mut seen = HashSet::new();
+        for &var in vars.iter() {
+            if !seen.insert(var) {
+                panic!("all_different: variable {:?} appears twice", var);
+            }
+        }
+
         AllDifferent {
-            vars: vars.into_iter().cloned().collect(),
+            vars: vars,
         }
     }
 }
@@ -46,7 +61,7 @@ impl Constraint for AllDifferent {
     fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
         // Build a table of which values can be assigned to which variables.
         let mut num_unassigned = 0;
-        let mut all_candidates = HashMap::new();
+        let mut all_candidates = BTreeMap::new();
 
         for &var in self.vars.iter().filter(|&var| !search.is_assigned(*var)) {
             num_unassigned = num_unassigned + 1;
@@ -75,6 +90,46 @@ impl Constraint for AllDifferent {
         Ok(())
     }
 
+    fn check_consistency(&self, search: &PuzzleSearch) -> ConsistencyLevel {
+        // Mirror on_assigned and on_updated without modifying the
+        // search state: report NotConsistent if either would still
+        // prune a candidate.
+        let mut num_unassigned = 0;
+        let mut all_candidates = BTreeMap::new();
+
+        for &var in self.vars.iter() {
+            if let Some(val) = search.get_assigned(var) {
+                for &var2 in self.vars.iter().filter(|&v| *v != var) {
+                    if !search.is_assigned(var2)
+                            && search.get_unassigned(var2)
+                                .any(|cand| cand == val) {
+                        return ConsistencyLevel::NotConsistent;
+                    }
+                }
+            } else {
+                num_unassigned = num_unassigned + 1;
+
+                for val in search.get_unassigned(var) {
+                    if all_candidates.contains_key(&val) {
+                        all_candidates.insert(val, None);
+                    } else {
+                        all_candidates.insert(val, Some(var));
+                    }
+                }
+            }
+        }
+
+        if num_unassigned > all_candidates.len() {
+            return ConsistencyLevel::NotConsistent;
+        } else if num_unassigned == all_candidates.len() {
+            if all_candidates.values().any(|opt| opt.is_some()) {
+                return ConsistencyLevel::NotConsistent;
+            }
+        }
+
+        ConsistencyLevel::ArcConsistent
+    }
+
     fn substitute(&self, from: VarToken, to: VarToken)
             -> PsResult<Rc<Constraint>> {
         if let Some(idx) = self.vars.iter().position(|&var| var == from) {
@@ -121,6 +176,30 @@ mod tests {
         assert_eq!(search.get_unassigned(v2).collect::<Vec<Val>>(), &[2,3]);
     }
 
+    #[test]
+    fn test_check_consistency() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        puzzle.all_different(&[v0,v1,v2]);
+
+        // Propagation reaches fixpoint after constrain.
+        let search = puzzle.step().expect("contradiction");
+        assert!(search.is_arc_consistent());
+    }
+
+    #[test]
+    #[should_panic(expected = "appears twice")]
+    fn test_duplicate_variable() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2]);
+
+        puzzle.all_different(&[v0,v1,v0]);
+    }
+
     #[test]
     fn test_contradiction_by_length() {
         let mut puzzle = Puzzle::new();
@@ -0,0 +1,170 @@
+//! All different groups implementation.
+
+use std::collections::{BTreeMap,HashSet};
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct AllDifferentGroups {
+    vars: Vec<VarToken>,
+    groups: Vec<Vec<VarToken>>,
+}
+
+impl AllDifferentGroups {
+    /// Allocate a new All Different Groups constraint.  The variables
+    /// are all different within each group, e.g. the rows and columns
+    /// of a grid puzzle, sharing a single constraint object.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_2d(2, 2, &[1,2]);
+    ///
+    /// puzzle_solver::constraint::AllDifferentGroups::new(vec![
+    ///         vars[0].clone(), vars[1].clone(),
+    ///         vec![ vars[0][0], vars[1][0] ],
+    ///         vec![ vars[0][1], vars[1][1] ] ]);
+    /// ```
+    pub fn new(groups: Vec<Vec<VarToken>>) -> Self {
+        let mut seen = HashSet::new();
+        let mut vars = Vec::new();
+        for group in groups.iter() {
+            for &var in group.iter() {
+                if seen.insert(var) {
+                    vars.push(var);
+                }
+            }
+        }
+
+        AllDifferentGroups {
+            vars: vars,
+            groups: groups,
+        }
+    }
+
+    /// Require the variables in one group to be all different.
+    fn constrain_group(&self, search: &mut PuzzleSearch, group: &[VarToken])
+            -> PsResult<()> {
+        // Build a table of which values can be assigned to which variables.
+        let mut num_unassigned = 0;
+        let mut all_candidates = BTreeMap::new();
+
+        for &var in group.iter().filter(|&var| !search.is_assigned(*var)) {
+            num_unassigned = num_unassigned + 1;
+
+            for val in search.get_unassigned(var) {
+                if all_candidates.contains_key(&val) {
+                    all_candidates.insert(val, None);
+                } else {
+                    all_candidates.insert(val, Some(var));
+                }
+            }
+        }
+
+        if num_unassigned > all_candidates.len() {
+            // More unassigned variables than candidates, contradiction.
+            return Err(());
+        } else if num_unassigned == all_candidates.len() {
+            // As many as variables as candidates.
+            for (&val, &opt) in all_candidates.iter() {
+                if let Some(var) = opt {
+                    try!(search.set_candidate(var, val));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Constraint for AllDifferentGroups {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        for group in self.groups.iter().filter(|group| group.contains(&var)) {
+            for &var2 in group.iter().filter(|&v| *v != var) {
+                try!(search.remove_candidate(var2, val));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        for group in self.groups.iter() {
+            try!(self.constrain_group(search, group));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        if self.groups.iter()
+                .any(|group| group.contains(&from) && group.contains(&to)) {
+            // Both in the same group, contradiction.
+            return Err(());
+        }
+
+        let subst = |&var| if var == from { to } else { var };
+        let groups = self.groups.iter()
+            .map(|group| group.iter().map(&subst).collect())
+            .collect();
+        Ok(Rc::new(AllDifferentGroups::new(groups)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::AllDifferentGroups;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_2d(2, 2, &[1,2,3]);
+        puzzle.set_value(vars[0][0], 1);
+        puzzle.add_constraint(AllDifferentGroups::new(vec![
+                vars[0].clone(), vars[1].clone(),
+                vec![ vars[0][0], vars[1][0] ],
+                vec![ vars[0][1], vars[1][1] ] ]));
+
+        let search = puzzle.step().expect("contradiction");
+
+        // The rest of the row and column lose the candidate.
+        assert_eq!(search.get_unassigned(vars[0][1]).collect::<Vec<Val>>(),
+                &[2,3]);
+        assert_eq!(search.get_unassigned(vars[1][0]).collect::<Vec<Val>>(),
+                &[2,3]);
+        assert_eq!(search.get_unassigned(vars[1][1]).collect::<Vec<Val>>(),
+                &[1,2,3]);
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_2d(2, 2, &[1,2]);
+        puzzle.add_constraint(AllDifferentGroups::new(vec![
+                vars[0].clone(), vars[1].clone(),
+                vec![ vars[0][0], vars[1][0] ],
+                vec![ vars[0][1], vars[1][1] ] ]));
+
+        // The 2x2 Latin squares.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 2);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2]);
+        puzzle.add_constraint(AllDifferentGroups::new(vec![vars]));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
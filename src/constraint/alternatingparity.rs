@@ -0,0 +1,148 @@
+//! Alternating parity implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct AlternatingParity {
+    vars: Vec<VarToken>,
+}
+
+impl AlternatingParity {
+    /// Allocate a new Alternating Parity constraint.  Consecutive
+    /// variables must have opposite parity (odd/even).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2,3,4]);
+    ///
+    /// puzzle_solver::constraint::AlternatingParity::new(&vars);
+    /// ```
+    pub fn new<'a, I>(vars: I) -> Self
+            where I: IntoIterator<Item=&'a VarToken> {
+        AlternatingParity {
+            vars: vars.into_iter().cloned().collect(),
+        }
+    }
+
+    /// Remove all candidates of the given parity from a variable.
+    fn remove_parity(search: &mut PuzzleSearch, var: VarToken, parity: Val)
+            -> PsResult<()> {
+        if !search.is_assigned(var) {
+            let remove: Vec<Val> = search.get_unassigned(var)
+                .filter(|&val| (val & 1) == parity)
+                .collect();
+
+            for val in remove.into_iter() {
+                try!(search.remove_candidate(var, val));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the parity shared by all of a variable's candidates, or
+    /// None if the candidates have mixed parity.
+    fn uniform_parity(search: &PuzzleSearch, var: VarToken) -> Option<Val> {
+        if let Some(val) = search.get_assigned(var) {
+            return Some(val & 1);
+        }
+
+        let mut parity = None;
+        for val in search.get_unassigned(var) {
+            match parity {
+                None => parity = Some(val & 1),
+                Some(p) => if p != (val & 1) {
+                    return None;
+                },
+            }
+        }
+
+        parity
+    }
+}
+
+impl Constraint for AlternatingParity {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        for idx in 0..self.vars.len() {
+            if self.vars[idx] != var {
+                continue;
+            }
+
+            if idx > 0 {
+                try!(Self::remove_parity(search, self.vars[idx - 1], val & 1));
+            }
+
+            if idx + 1 < self.vars.len() {
+                try!(Self::remove_parity(search, self.vars[idx + 1], val & 1));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        for idx in 0..self.vars.len() {
+            if let Some(parity) = Self::uniform_parity(search, self.vars[idx]) {
+                if idx > 0 {
+                    try!(Self::remove_parity(search, self.vars[idx - 1], parity));
+                }
+
+                if idx + 1 < self.vars.len() {
+                    try!(Self::remove_parity(search, self.vars[idx + 1], parity));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let new_vars = self.vars.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(AlternatingParity{ vars: new_vars }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+
+    #[test]
+    fn test_forced_alternation() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[2]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3,4]);
+        let v3 = puzzle.new_var_with_candidates(&[1,2,3,4]);
+
+        puzzle.alternating_parity(&[v0,v1,v2,v3]);
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v0], 2);
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[1,3]);
+        assert_eq!(search.get_unassigned(v2).collect::<Vec<Val>>(), &[2,4]);
+        assert_eq!(search.get_unassigned(v3).collect::<Vec<Val>>(), &[1,3]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[3,5]);
+
+        puzzle.alternating_parity(&[v0,v1]);
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
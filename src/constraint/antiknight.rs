@@ -0,0 +1,130 @@
+//! Anti-knight implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct AntiKnight {
+    width: usize,
+    height: usize,
+    vars: Vec<VarToken>,
+}
+
+impl AntiKnight {
+    /// Allocate a new Anti-Knight constraint.  No two cells a chess
+    /// knight's move apart may share a value.
+    ///
+    /// The variables are a flat grid in row-major order, i.e. the
+    /// cell (x, y) is `vars[y * width + x]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(9, &[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::AntiKnight::new(3, 3, vars);
+    /// ```
+    pub fn new(width: usize, height: usize, vars: Vec<VarToken>) -> Self {
+        assert_eq!(vars.len(), width * height);
+        AntiKnight {
+            width: width,
+            height: height,
+            vars: vars,
+        }
+    }
+
+    /// Get the indices of the cells a knight's move away from the
+    /// given cell.
+    fn neighbours(&self, idx: usize) -> Vec<usize> {
+        let x = (idx % self.width) as isize;
+        let y = (idx / self.width) as isize;
+        let mut neighbours = Vec::with_capacity(8);
+
+        for &(dx, dy) in [ (-2,-1), (-1,-2), (1,-2), (2,-1),
+                           (2,1), (1,2), (-1,2), (-2,1) ].iter() {
+            let (x2, y2) = (x + dx, y + dy);
+            if 0 <= x2 && x2 < (self.width as isize)
+                    && 0 <= y2 && y2 < (self.height as isize) {
+                neighbours.push((y2 as usize) * self.width + (x2 as usize));
+            }
+        }
+
+        neighbours
+    }
+}
+
+impl Constraint for AntiKnight {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        for idx in 0..self.vars.len() {
+            if self.vars[idx] != var {
+                continue;
+            }
+
+            for idx2 in self.neighbours(idx).into_iter() {
+                try!(search.remove_candidate(self.vars[idx2], val));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let new_vars = self.vars.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(AntiKnight{
+            width: self.width,
+            height: self.height,
+            vars: new_vars,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::AntiKnight;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let mut vars = Vec::new();
+        for _ in 0..(3 * 3) {
+            vars.push(puzzle.new_var_with_candidates(&[1,2,3]));
+        }
+
+        puzzle.set_value(vars[0], 1);
+        puzzle.add_constraint(AntiKnight::new(3, 3, vars.clone()));
+
+        let search = puzzle.step().expect("contradiction");
+
+        // (0,0)'s knight neighbours are (2,1) = index 5 and (1,2) = 7.
+        assert_eq!(search.get_unassigned(vars[5]).collect::<Vec<Val>>(),
+                &[2,3]);
+        assert_eq!(search.get_unassigned(vars[7]).collect::<Vec<Val>>(),
+                &[2,3]);
+        assert_eq!(search.get_unassigned(vars[1]).collect::<Vec<Val>>(),
+                &[1,2,3]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let mut vars = Vec::new();
+        for _ in 0..(3 * 3) {
+            vars.push(puzzle.new_var_with_candidates(&[1]));
+        }
+
+        puzzle.add_constraint(AntiKnight::new(3, 3, vars.clone()));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
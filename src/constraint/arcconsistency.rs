@@ -0,0 +1,133 @@
+//! Arc consistency implementation.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct ArcConsistency {
+    var1: VarToken,
+    var2: VarToken,
+    relation: Arc<dyn Fn(Val, Val) -> bool + Send + Sync>,
+}
+
+impl ArcConsistency {
+    /// Allocate a new Arc Consistency constraint over an arbitrary
+    /// binary relation.  Candidates of either variable without a
+    /// supporting value in the other variable are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let v1 = puzzle.new_var_with_candidates(&[1,2,3]);
+    /// let v2 = puzzle.new_var_with_candidates(&[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::ArcConsistency::new(v1, v2,
+    ///         std::sync::Arc::new(|a, b| a < b));
+    /// ```
+    pub fn new(var1: VarToken, var2: VarToken,
+            relation: Arc<dyn Fn(Val, Val) -> bool + Send + Sync>) -> Self {
+        ArcConsistency {
+            var1: var1,
+            var2: var2,
+            relation: relation,
+        }
+    }
+
+    /// Remove the candidates of "var" without a supporting value,
+    /// where supported(val) holds if some candidate of the other
+    /// variable relates to val.
+    fn revise<F>(&self, search: &mut PuzzleSearch, var: VarToken,
+            supported: F) -> PsResult<()>
+            where F: Fn(&PuzzleSearch, Val) -> bool {
+        if let Some(val) = search.get_assigned(var) {
+            if !supported(search, val) {
+                return Err(());
+            }
+        } else {
+            let remove: Vec<Val> = search.get_unassigned(var)
+                .filter(|&val| !supported(search, val))
+                .collect();
+
+            for val in remove.into_iter() {
+                try!(search.remove_candidate(var, val));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Constraint for ArcConsistency {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new([&self.var1, &self.var2].to_vec().into_iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let (var1, var2) = (self.var1, self.var2);
+        let relation = &self.relation;
+
+        try!(self.revise(search, var1, |search, val1|
+                search.get_assigned(var2).into_iter()
+                    .chain(search.get_unassigned(var2))
+                    .any(|val2| relation(val1, val2))));
+        try!(self.revise(search, var2, |search, val2|
+                search.get_assigned(var1).into_iter()
+                    .chain(search.get_unassigned(var1))
+                    .any(|val1| relation(val1, val2))));
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |var| if var == from { to } else { var };
+        Ok(Rc::new(ArcConsistency::new(subst(self.var1), subst(self.var2),
+                Arc::clone(&self.relation))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use ::{Puzzle,Val};
+    use super::ArcConsistency;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3]);
+        puzzle.add_constraint(ArcConsistency::new(v1, v2,
+                Arc::new(|a, b| a < b)));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[1,2]);
+        assert_eq!(search.get_unassigned(v2).collect::<Vec<Val>>(), &[2,3]);
+    }
+
+    #[test]
+    fn test_assigned_support() {
+        let mut puzzle = Puzzle::new();
+        let v1 = puzzle.new_var_with_candidates(&[2]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3]);
+        puzzle.add_constraint(ArcConsistency::new(v1, v2,
+                Arc::new(|a, b| a < b)));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v2], 3);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v1 = puzzle.new_var_with_candidates(&[1,2]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2]);
+        puzzle.add_constraint(ArcConsistency::new(v1, v2,
+                Arc::new(|_, _| false)));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
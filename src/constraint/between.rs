@@ -0,0 +1,110 @@
+//! Between implementation.
+
+use std::cmp;
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Between {
+    a: VarToken,
+    b: VarToken,
+    c: VarToken,
+}
+
+impl Between {
+    /// Allocate a new Between constraint.  The middle variable is
+    /// strictly between the two outer variables, i.e. a < b < c or
+    /// c < b < a.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::Between::new(vars[0], vars[1], vars[2]);
+    /// ```
+    pub fn new(a: VarToken, b: VarToken, c: VarToken) -> Self {
+        Between {
+            a: a,
+            b: b,
+            c: c,
+        }
+    }
+}
+
+impl Constraint for Between {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(iter::once(&self.a)
+                .chain(iter::once(&self.b))
+                .chain(iter::once(&self.c)))
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        // The middle variable cannot equal either outer variable.
+        if var == self.b {
+            try!(search.remove_candidate(self.a, val));
+            try!(search.remove_candidate(self.c, val));
+        } else if var == self.a || var == self.c {
+            try!(search.remove_candidate(self.b, val));
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let (a_min, a_max) = try!(search.get_min_max(self.a));
+        let (c_min, c_max) = try!(search.get_min_max(self.c));
+
+        // The direction is unknown, so the middle variable is
+        // bounded by the union interval of the outer variables,
+        // exclusive of the end points.
+        let min = cmp::min(a_min, c_min) + 1;
+        let max = cmp::max(a_max, c_max) - 1;
+        try!(search.bound_candidate_range(self.b, min, max));
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let a = if self.a == from { to } else { self.a };
+        let b = if self.b == from { to } else { self.b };
+        let c = if self.c == from { to } else { self.c };
+        Ok(Rc::new(Between{ a: a, b: b, c: c }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+
+    #[test]
+    fn test_prune_middle() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[2]);
+        let b = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        let c = puzzle.new_var_with_candidates(&[7]);
+
+        puzzle.between(a, b, c);
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(b).collect::<Vec<Val>>(),
+                &[3,4,5,6]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[4]);
+        let b = puzzle.new_var_with_candidates(&[1,9]);
+        let c = puzzle.new_var_with_candidates(&[6]);
+
+        puzzle.between(a, b, c);
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
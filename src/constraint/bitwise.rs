@@ -0,0 +1,282 @@
+//! Bitwise AND, OR and XOR constraint implementations.
+
+use std::collections::BTreeSet;
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+/// Cap on the pair enumeration used for support-based pruning.
+const MAX_PAIRS: usize = 4096;
+
+#[derive(Clone,Copy)]
+enum BitOp {
+    And,
+    Or,
+    Xor,
+}
+
+impl BitOp {
+    fn apply(self, a: Val, b: Val) -> Val {
+        match self {
+            BitOp::And => a & b,
+            BitOp::Or => a | b,
+            BitOp::Xor => a ^ b,
+        }
+    }
+}
+
+/// Collect the variable's remaining values (a single value if it has
+/// been assigned).
+fn candidates(search: &PuzzleSearch, var: VarToken) -> Vec<Val> {
+    match search.get_assigned(var) {
+        Some(val) => vec![val],
+        None => search.get_unassigned(var).collect(),
+    }
+}
+
+/// Remove the variable's candidates that are not in the support set.
+fn prune(search: &mut PuzzleSearch, var: VarToken, support: &BTreeSet<Val>)
+        -> PsResult<()> {
+    for val in candidates(search, var) {
+        if !support.contains(&val) {
+            try!(search.remove_candidate(var, val));
+        }
+    }
+
+    Ok(())
+}
+
+/// Propagate z = x op y by enumerating the supported values.
+///
+/// A candidate of z is supported only if some pair of candidates of
+/// x and y produces it, and vice versa.  If the number of pairs
+/// exceeds MAX_PAIRS, fall back to propagating assigned operands
+/// only.
+fn propagate(search: &mut PuzzleSearch, op: BitOp,
+        z: VarToken, x: VarToken, y: VarToken) -> PsResult<()> {
+    let xs = candidates(search, x);
+    let ys = candidates(search, y);
+    let zs: BTreeSet<Val> = candidates(search, z).into_iter().collect();
+
+    if xs.len() * ys.len() > MAX_PAIRS {
+        if xs.len() == 1 && ys.len() == 1 {
+            try!(search.set_candidate(z, op.apply(xs[0], ys[0])));
+        }
+
+        return Ok(());
+    }
+
+    let mut z_support = BTreeSet::new();
+    let mut x_support = BTreeSet::new();
+    let mut y_support = BTreeSet::new();
+    for &a in xs.iter() {
+        for &b in ys.iter() {
+            let val = op.apply(a, b);
+            if zs.contains(&val) {
+                z_support.insert(val);
+                x_support.insert(a);
+                y_support.insert(b);
+            }
+        }
+    }
+
+    try!(prune(search, z, &z_support));
+    try!(prune(search, x, &x_support));
+    try!(prune(search, y, &y_support));
+    Ok(())
+}
+
+pub struct BitAnd {
+    z: VarToken,
+    x: VarToken,
+    y: VarToken,
+}
+
+pub struct BitOr {
+    z: VarToken,
+    x: VarToken,
+    y: VarToken,
+}
+
+pub struct BitXor {
+    z: VarToken,
+    x: VarToken,
+    y: VarToken,
+}
+
+impl BitAnd {
+    /// Allocate a new BitAnd constraint: z = x & y, bitwise on
+    /// non-negative values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1,2,3]);
+    ///
+    /// puzzle_solver::constraint::BitAnd::new(vars[0], vars[1], vars[2]);
+    /// ```
+    pub fn new(z: VarToken, x: VarToken, y: VarToken) -> Self {
+        BitAnd {
+            z: z,
+            x: x,
+            y: y,
+        }
+    }
+}
+
+impl BitOr {
+    /// Allocate a new BitOr constraint: z = x | y, bitwise on
+    /// non-negative values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1,2,3]);
+    ///
+    /// puzzle_solver::constraint::BitOr::new(vars[0], vars[1], vars[2]);
+    /// ```
+    pub fn new(z: VarToken, x: VarToken, y: VarToken) -> Self {
+        BitOr {
+            z: z,
+            x: x,
+            y: y,
+        }
+    }
+}
+
+impl BitXor {
+    /// Allocate a new BitXor constraint: z = x ^ y, bitwise on
+    /// non-negative values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1,2,3]);
+    ///
+    /// puzzle_solver::constraint::BitXor::new(vars[0], vars[1], vars[2]);
+    /// ```
+    pub fn new(z: VarToken, x: VarToken, y: VarToken) -> Self {
+        BitXor {
+            z: z,
+            x: x,
+            y: y,
+        }
+    }
+}
+
+macro_rules! impl_bitwise_constraint {
+    ($name:ident, $op:expr) => {
+        impl Constraint for $name {
+            fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+                Box::new(iter::once(&self.z)
+                        .chain(iter::once(&self.x))
+                        .chain(iter::once(&self.y)))
+            }
+
+            fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+                propagate(search, $op, self.z, self.x, self.y)
+            }
+
+            fn substitute(&self, from: VarToken, to: VarToken)
+                    -> PsResult<Rc<Constraint>> {
+                let z = if self.z == from { to } else { self.z };
+                let x = if self.x == from { to } else { self.x };
+                let y = if self.y == from { to } else { self.y };
+                Ok(Rc::new($name::new(z, x, y)))
+            }
+        }
+    };
+}
+
+impl_bitwise_constraint!(BitAnd, BitOp::And);
+impl_bitwise_constraint!(BitOr, BitOp::Or);
+impl_bitwise_constraint!(BitXor, BitOp::Xor);
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val,VarToken};
+    use super::{BitAnd,BitOr,BitXor};
+
+    /// Compare the constraint's solutions against brute force over
+    /// the full 4-bit domains.
+    fn check_4bit(op: fn(Val, Val) -> Val,
+            solutions: Vec<(Val, Val, Val)>) {
+        let mut expected = Vec::new();
+        for x in 0..16 {
+            for y in 0..16 {
+                expected.push((op(x, y), x, y));
+            }
+        }
+
+        expected.sort();
+        assert_eq!(solutions, expected);
+    }
+
+    fn solve_4bit<F>(add: F) -> Vec<(Val, Val, Val)>
+            where F: Fn(&mut Puzzle, VarToken, VarToken, VarToken) {
+        let mut puzzle = Puzzle::new();
+        let candidates: Vec<Val> = (0..16).collect();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &candidates);
+        add(&mut puzzle, vars[0], vars[1], vars[2]);
+
+        let mut rows: Vec<(Val, Val, Val)> = puzzle.solve_all().iter()
+            .map(|dict| (dict[vars[0]], dict[vars[1]], dict[vars[2]]))
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_bitand_4bit() {
+        let rows = solve_4bit(|puzzle, z, x, y| {
+            puzzle.add_constraint(BitAnd::new(z, x, y));
+        });
+        check_4bit(|x, y| x & y, rows);
+    }
+
+    #[test]
+    fn test_bitor_4bit() {
+        let rows = solve_4bit(|puzzle, z, x, y| {
+            puzzle.add_constraint(BitOr::new(z, x, y));
+        });
+        check_4bit(|x, y| x | y, rows);
+    }
+
+    #[test]
+    fn test_bitxor_4bit() {
+        let rows = solve_4bit(|puzzle, z, x, y| {
+            puzzle.add_constraint(BitXor::new(z, x, y));
+        });
+        check_4bit(|x, y| x ^ y, rows);
+    }
+
+    #[test]
+    fn test_propagation() {
+        let mut puzzle = Puzzle::new();
+        let z = puzzle.new_var_with_candidates(&[0,1,2,3]);
+        let x = puzzle.new_var_with_candidates(&[1,3]);
+        let y = puzzle.new_var_with_candidates(&[2]);
+
+        // 1 & 2 == 0 and 3 & 2 == 2.
+        puzzle.add_constraint(BitAnd::new(z, x, y));
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(z).collect::<Vec<Val>>(), &[0,2]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let z = puzzle.new_var_with_candidates(&[3]);
+        let x = puzzle.new_var_with_candidates(&[0,1]);
+        let y = puzzle.new_var_with_candidates(&[0,1]);
+
+        // x | y <= 1 can never be 3.
+        puzzle.add_constraint(BitOr::new(z, x, y));
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
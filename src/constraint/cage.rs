@@ -0,0 +1,177 @@
+//! Cage (killer sudoku) implementation.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Cage {
+    total: Val,
+    cells: Vec<VarToken>,
+}
+
+impl Cage {
+    /// Allocate a new Cage constraint.  The cells take distinct
+    /// values summing to the total, as in killer sudokus.
+    ///
+    /// Propagation enumerates the feasible value combinations and
+    /// prunes each cell to the values appearing in at least one
+    /// combination, which is strong and cheap for small cages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle_solver::constraint::Cage::new(17, vars);
+    /// ```
+    pub fn new(total: Val, cells: Vec<VarToken>) -> Self {
+        Cage {
+            total: total,
+            cells: cells,
+        }
+    }
+}
+
+/// Enumerate the combinations of distinct values summing to the
+/// remaining total, recording the values used in any feasible
+/// combination.  Returns true if any combination was found.
+fn feasible(domains: &[Vec<Val>], remaining: Val, idx: usize,
+        chosen: &mut Vec<Val>, allowed: &mut [BTreeSet<Val>]) -> bool {
+    if idx >= domains.len() {
+        if remaining == 0 {
+            for (cell, &val) in chosen.iter().enumerate() {
+                allowed[cell].insert(val);
+            }
+            return true;
+        }
+
+        return false;
+    }
+
+    let mut found = false;
+    for &val in domains[idx].iter() {
+        if chosen.contains(&val) {
+            continue;
+        }
+
+        chosen.push(val);
+        found = feasible(domains, remaining - val, idx + 1, chosen, allowed)
+                || found;
+        chosen.pop();
+    }
+
+    found
+}
+
+impl Constraint for Cage {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        // The cells are distinct.
+        for &var2 in self.cells.iter() {
+            if var2 != var {
+                try!(search.remove_candidate(var2, val));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let domains: Vec<Vec<Val>> = self.cells.iter().map(|&var|
+                if let Some(val) = search.get_assigned(var) {
+                    vec![val]
+                } else {
+                    search.get_unassigned(var).collect()
+                })
+            .collect();
+
+        let mut allowed = vec![BTreeSet::new(); self.cells.len()];
+        let mut chosen = Vec::with_capacity(self.cells.len());
+        if !feasible(&domains, self.total, 0, &mut chosen, &mut allowed) {
+            return Err(());
+        }
+
+        for (cell, &var) in self.cells.iter().enumerate() {
+            if !search.is_assigned(var) {
+                for &val in domains[cell].iter() {
+                    if !allowed[cell].contains(&val) {
+                        try!(search.remove_candidate(var, val));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        if let Some(idx) = self.cells.iter().position(|&var| var == from) {
+            if !self.cells.contains(&to) {
+                let mut new_cells = self.cells.clone();
+                new_cells[idx] = to;
+                return Ok(Rc::new(Cage{
+                    total: self.total,
+                    cells: new_cells,
+                }));
+            }
+        }
+
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+
+    #[test]
+    fn test_two_cell_seventeen() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2,
+                &[1,2,3,4,5,6,7,8,9]);
+
+        puzzle.killer_cage(17, &vars);
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(vars[0]).collect::<Vec<Val>>(),
+                &[8,9]);
+        assert_eq!(search.get_unassigned(vars[1]).collect::<Vec<Val>>(),
+                &[8,9]);
+    }
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3,
+                &[1,2,3,4,5,6,7,8,9]);
+
+        puzzle.killer_cage(7, &vars);
+
+        // 7 = 1 + 2 + 4 over three distinct digits.
+        let search = puzzle.step().expect("contradiction");
+        for &var in vars.iter() {
+            assert_eq!(search.get_unassigned(var).collect::<Vec<Val>>(),
+                    &[1,2,4]);
+        }
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3]);
+
+        puzzle.killer_cage(6, &vars);
+
+        // 6 = 3 + 3 requires a repeat.
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
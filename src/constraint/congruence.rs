@@ -0,0 +1,149 @@
+//! Congruence implementation.
+
+use std::collections::BTreeSet;
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Congruence {
+    var1: VarToken,
+    var2: VarToken,
+    modulus: Val,
+}
+
+impl Congruence {
+    /// Allocate a new Congruence constraint, enforcing
+    /// var1 = var2 (mod modulus).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let hour = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9,10,11,12]);
+    /// let bell = puzzle.new_var_with_candidates(&[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::Congruence::new(hour, bell, 12);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the modulus is not positive.
+    pub fn new(var1: VarToken, var2: VarToken, modulus: Val) -> Self {
+        assert!(modulus > 0);
+        Congruence {
+            var1: var1,
+            var2: var2,
+            modulus: modulus,
+        }
+    }
+
+    /// Get the set of residues achievable by a variable.
+    fn residues(&self, search: &PuzzleSearch, var: VarToken) -> BTreeSet<Val> {
+        if let Some(val) = search.get_assigned(var) {
+            iter::once(val.rem_euclid(self.modulus)).collect()
+        } else {
+            search.get_unassigned(var)
+                .map(|val| val.rem_euclid(self.modulus))
+                .collect()
+        }
+    }
+
+    /// Remove all candidates whose residue is not in the given set.
+    fn keep_residues(&self, search: &mut PuzzleSearch, var: VarToken,
+            residues: &BTreeSet<Val>) -> PsResult<()> {
+        if !search.is_assigned(var) {
+            let remove: Vec<Val> = search.get_unassigned(var)
+                .filter(|&val| !residues.contains(&val.rem_euclid(self.modulus)))
+                .collect();
+
+            for val in remove.into_iter() {
+                try!(search.remove_candidate(var, val));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Constraint for Congruence {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(iter::once(&self.var1).chain(iter::once(&self.var2)))
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        let other = if var == self.var1 { self.var2 } else { self.var1 };
+        let residues = iter::once(val.rem_euclid(self.modulus)).collect();
+        self.keep_residues(search, other, &residues)
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let residues1 = self.residues(search, self.var1);
+        let residues2 = self.residues(search, self.var2);
+        let common: BTreeSet<Val> = residues1.intersection(&residues2)
+            .cloned().collect();
+
+        if common.is_empty() {
+            return Err(());
+        }
+
+        try!(self.keep_residues(search, self.var1, &common));
+        try!(self.keep_residues(search, self.var2, &common));
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let var1 = if self.var1 == from { to } else { self.var1 };
+        let var2 = if self.var2 == from { to } else { self.var2 };
+        Ok(Rc::new(Congruence{
+            var1: var1,
+            var2: var2,
+            modulus: self.modulus,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::Congruence;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[5]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8]);
+
+        puzzle.add_constraint(Congruence::new(v0, v1, 3));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[2,5,8]);
+    }
+
+    #[test]
+    fn test_residue_intersection() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2]);
+        let v1 = puzzle.new_var_with_candidates(&[4,6,8]);
+
+        puzzle.add_constraint(Congruence::new(v0, v1, 4));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v0], 2);
+        assert_eq!(search[v1], 6);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,3]);
+        let v1 = puzzle.new_var_with_candidates(&[2,4]);
+
+        puzzle.add_constraint(Congruence::new(v0, v1, 2));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
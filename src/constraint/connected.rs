@@ -0,0 +1,179 @@
+//! Connected implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Connected {
+    // The 0/1 selection variables and their grid positions.
+    cells: Vec<(VarToken, usize, usize)>,
+    width: usize,
+    height: usize,
+}
+
+impl Connected {
+    /// Allocate a new Connected constraint.  The cells set to 1 form
+    /// a single orthogonally-connected component of the grid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1]);
+    /// let cells = (0..3).map(|x| (vars[x], x, 0)).collect();
+    ///
+    /// puzzle_solver::constraint::Connected::new(cells, 3, 1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a cell position lies outside the grid.
+    pub fn new(cells: Vec<(VarToken, usize, usize)>, width: usize,
+            height: usize) -> Self {
+        for &(_, x, y) in cells.iter() {
+            assert!(x < width && y < height);
+        }
+
+        Connected {
+            cells: cells,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Check that every committed on-cell can reach the first one
+    /// through cells that are not off, optionally with one extra
+    /// cell blocked.
+    fn connected_without(&self, states: &[Option<Val>],
+            blocked: Option<usize>) -> bool {
+        let mut grid = vec![None; self.width * self.height];
+        for (i, &(_, x, y)) in self.cells.iter().enumerate() {
+            if states[i] != Some(0) && blocked != Some(i) {
+                grid[y * self.width + x] = Some(i);
+            }
+        }
+
+        let start = match states.iter().position(|&s| s == Some(1)) {
+            Some(i) => i,
+            None => return true,
+        };
+
+        let mut seen = vec![false; self.cells.len()];
+        let mut queue = vec![start];
+        seen[start] = true;
+        while let Some(i) = queue.pop() {
+            let (_, x, y) = self.cells[i];
+            let mut neighbours = Vec::with_capacity(4);
+            if x > 0 { neighbours.push((x - 1, y)); }
+            if y > 0 { neighbours.push((x, y - 1)); }
+            if x + 1 < self.width { neighbours.push((x + 1, y)); }
+            if y + 1 < self.height { neighbours.push((x, y + 1)); }
+
+            for (nx, ny) in neighbours.into_iter() {
+                if let Some(j) = grid[ny * self.width + nx] {
+                    if !seen[j] {
+                        seen[j] = true;
+                        queue.push(j);
+                    }
+                }
+            }
+        }
+
+        states.iter().enumerate()
+            .all(|(i, &s)| s != Some(1) || seen[i])
+    }
+}
+
+impl Constraint for Connected {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter().map(|cell| &cell.0))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let states: Vec<Option<Val>> = self.cells.iter()
+            .map(|&(var, _, _)| search.get_assigned(var))
+            .collect();
+
+        if !states.iter().any(|&s| s == Some(1)) {
+            return Ok(());
+        }
+
+        if !self.connected_without(&states, None) {
+            return Err(());
+        }
+
+        // An undecided cell whose removal disconnects the committed
+        // on-cells is a cut vertex: it must be on.
+        for i in 0..self.cells.len() {
+            if states[i].is_none()
+                    && !self.connected_without(&states, Some(i)) {
+                try!(search.set_candidate(self.cells[i].0, 1));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let cells = self.cells.iter()
+            .map(|&(var, x, y)|
+                (if var == from { to } else { var }, x, y))
+            .collect();
+        Ok(Rc::new(Connected::new(cells, self.width, self.height)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+
+    #[test]
+    fn test_forced_bridge() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1]);
+        puzzle.set_value(vars[0], 1);
+        puzzle.set_value(vars[2], 1);
+
+        let cells = vars.iter().enumerate()
+            .map(|(x, &var)| (var, x, 0))
+            .collect();
+        puzzle.connected(cells, 3, 1);
+
+        // The middle cell bridges the two committed cells.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_assigned(vars[1]), Some(1));
+    }
+
+    #[test]
+    fn test_count_connected() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_2d(2, 2, &[0,1]);
+
+        let cells = (0..2).flat_map(|y| (0..2).map(move |x| (x, y)))
+            .map(|(x, y)| (vars[y][x], x, y))
+            .collect();
+        puzzle.connected(cells, 2, 2);
+
+        // The empty region, 4 single cells, 4 dominoes, 4 triominoes
+        // and the full square; the 2 diagonal pairs are disconnected.
+        assert_eq!(puzzle.solve_all().len(), 14);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1]);
+        puzzle.set_value(vars[0], 1);
+        puzzle.set_value(vars[1], 0);
+        puzzle.set_value(vars[2], 1);
+
+        let cells = vars.iter().enumerate()
+            .map(|(x, &var)| (var, x, 0))
+            .collect();
+        puzzle.connected(cells, 3, 1);
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
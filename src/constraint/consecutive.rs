@@ -0,0 +1,112 @@
+//! Consecutive implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Consecutive {
+    vars: Vec<VarToken>,
+}
+
+impl Consecutive {
+    /// Allocate a new Consecutive constraint.  Every two consecutive
+    /// variables differ by exactly one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::Consecutive::new(vars);
+    /// ```
+    pub fn new(vars: Vec<VarToken>) -> Self {
+        Consecutive {
+            vars: vars,
+        }
+    }
+}
+
+impl Constraint for Consecutive {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        for idx in 0..self.vars.len() {
+            if self.vars[idx] != var {
+                continue;
+            }
+
+            // The neighbours must take either val - 1 or val + 1.
+            for idx2 in [idx.wrapping_sub(1), idx + 1].iter()
+                    .filter(|&&idx2| idx2 < self.vars.len()) {
+                let var2 = self.vars[*idx2];
+                if let Some(val2) = search.get_assigned(var2) {
+                    if (val2 - val).abs() != 1 {
+                        return Err(());
+                    }
+                } else {
+                    let gone: Vec<Val> = search.get_unassigned(var2)
+                        .filter(|&val2| (val2 - val).abs() != 1)
+                        .collect();
+                    for val2 in gone.into_iter() {
+                        try!(search.remove_candidate(var2, val2));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let vars = self.vars.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(Consecutive{ vars: vars }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::Consecutive;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[3]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4,5]);
+
+        puzzle.add_constraint(Consecutive::new(vec![v0,v1]));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[2,4]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[1,3,5]);
+
+        puzzle.add_constraint(Consecutive::new(vec![v0,v1]));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+        puzzle.add_constraint(Consecutive::new(vars));
+
+        // The walks of length three on the path 1 - 2 - 3.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 6);
+    }
+}
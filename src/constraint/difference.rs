@@ -0,0 +1,120 @@
+//! Difference implementation.
+
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,VarToken};
+
+pub struct Difference {
+    result: VarToken,
+    a: VarToken,
+    b: VarToken,
+}
+
+impl Difference {
+    /// Allocate a new Difference constraint: result = a - b.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::Difference::new(vars[0], vars[1], vars[2]);
+    /// ```
+    pub fn new(result: VarToken, a: VarToken, b: VarToken) -> Self {
+        Difference {
+            result: result,
+            a: a,
+            b: b,
+        }
+    }
+}
+
+impl Constraint for Difference {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(iter::once(&self.result)
+                .chain(iter::once(&self.a))
+                .chain(iter::once(&self.b)))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        // Propagate the interval bounds in all three directions.
+        let (a_min, a_max) = try!(search.get_min_max(self.a));
+        let (b_min, b_max) = try!(search.get_min_max(self.b));
+        try!(search.bound_candidate_range(self.result,
+                a_min - b_max, a_max - b_min));
+
+        let (r_min, r_max) = try!(search.get_min_max(self.result));
+        try!(search.bound_candidate_range(self.a,
+                r_min + b_min, r_max + b_max));
+
+        let (a_min, a_max) = try!(search.get_min_max(self.a));
+        try!(search.bound_candidate_range(self.b,
+                a_min - r_max, a_max - r_min));
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let result = if self.result == from { to } else { self.result };
+        let a = if self.a == from { to } else { self.a };
+        let b = if self.b == from { to } else { self.b };
+        Ok(Rc::new(Difference{ result: result, a: a, b: b }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+
+    #[test]
+    fn test_three_way_propagation() {
+        let mut puzzle = Puzzle::new();
+        let result = puzzle.new_var_with_candidates(&[0,1,2,3,4,5,6,7,8,9]);
+        let a = puzzle.new_var_with_candidates(&[7]);
+        let b = puzzle.new_var_with_candidates(&[2,3]);
+
+        puzzle.difference(result, a, b);
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(result).collect::<Vec<Val>>(),
+                &[4,5]);
+    }
+
+    #[test]
+    fn test_matches_equals() {
+        let candidates: Vec<Val> = (-2..=2).collect();
+
+        let mut puzzle1 = Puzzle::new();
+        let vars1 = puzzle1.new_vars_with_candidates_1d(3, &candidates);
+        puzzle1.difference(vars1[0], vars1[1], vars1[2]);
+
+        let mut puzzle2 = Puzzle::new();
+        let vars2 = puzzle2.new_vars_with_candidates_1d(3, &candidates);
+        puzzle2.equals(vars2[0], vars2[1] - vars2[2]);
+
+        let rows1: Vec<Vec<Val>> = puzzle1.solve_all().iter()
+            .map(|dict| vars1.iter().map(|&var| dict[var]).collect())
+            .collect();
+        let rows2: Vec<Vec<Val>> = puzzle2.solve_all().iter()
+            .map(|dict| vars2.iter().map(|&var| dict[var]).collect())
+            .collect();
+
+        assert_eq!(rows1, rows2);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let result = puzzle.new_var_with_candidates(&[5]);
+        let a = puzzle.new_var_with_candidates(&[1,2]);
+        let b = puzzle.new_var_with_candidates(&[1,2]);
+
+        puzzle.difference(result, a, b);
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
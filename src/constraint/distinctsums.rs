@@ -0,0 +1,140 @@
+//! Distinct sums implementation.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,VarToken};
+
+pub struct DistinctSums {
+    vars: Vec<VarToken>,
+}
+
+impl DistinctSums {
+    /// Allocate a new Distinct Sums constraint.  The pairwise sums
+    /// vars[i] + vars[j], for every pair i < j, must all take
+    /// distinct values, as in Sidon sets and B2 sequences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2,3,5,8]);
+    ///
+    /// puzzle_solver::constraint::DistinctSums::new(vars);
+    /// ```
+    pub fn new(vars: Vec<VarToken>) -> Self {
+        DistinctSums {
+            vars: vars,
+        }
+    }
+}
+
+impl Constraint for DistinctSums {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let mut bounds = Vec::with_capacity(self.vars.len());
+        for &var in self.vars.iter() {
+            bounds.push(try!(search.get_min_max(var)));
+        }
+
+        // The pairs whose sum range has collapsed to a single value.
+        let mut forced = Vec::new();
+        for i in 0..self.vars.len() {
+            for j in (i + 1)..self.vars.len() {
+                let sum_min = bounds[i].0 + bounds[j].0;
+                let sum_max = bounds[i].1 + bounds[j].1;
+                if sum_min == sum_max {
+                    forced.push((i, j, sum_min));
+                }
+            }
+        }
+
+        {
+            let mut seen = BTreeSet::new();
+            for &(_, _, sum) in forced.iter() {
+                if !seen.insert(sum) {
+                    return Err(());
+                }
+            }
+        }
+
+        // In the pairs with exactly one undetermined variable, remove
+        // the candidates that would repeat a forced sum.
+        for i in 0..self.vars.len() {
+            for j in (i + 1)..self.vars.len() {
+                let (val, other) = match (search.get_assigned(self.vars[i]),
+                                          search.get_assigned(self.vars[j])) {
+                    (Some(val), None) => (val, self.vars[j]),
+                    (None, Some(val)) => (val, self.vars[i]),
+                    _ => continue,
+                };
+
+                for &(fi, fj, sum) in forced.iter() {
+                    if (fi, fj) != (i, j) {
+                        try!(search.remove_candidate(other, sum - val));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let vars = self.vars.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(DistinctSums{ vars: vars }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+    use super::DistinctSums;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[2]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        puzzle.add_constraint(DistinctSums::new(vec![v0,v1,v2]));
+
+        // v0 + v2 != 3 and v1 + v2 != 3.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v2], 3);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[2]);
+        let v2 = puzzle.new_var_with_candidates(&[3]);
+        let v3 = puzzle.new_var_with_candidates(&[4]);
+
+        // v0 + v3 == v1 + v2.
+        puzzle.add_constraint(DistinctSums::new(vec![v0,v1,v2,v3]));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+        puzzle.add_constraint(DistinctSums::new(vars));
+
+        // On three variables, distinct pairwise sums are equivalent
+        // to all different.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 6);
+    }
+}
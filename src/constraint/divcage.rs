@@ -0,0 +1,157 @@
+//! Division cage (kenken) implementation.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct DivCage {
+    result: Val,
+    cells: [VarToken; 2],
+}
+
+impl DivCage {
+    /// Allocate a new DivCage constraint: the larger cell divided by
+    /// the smaller equals the result, with no remainder, as in
+    /// kenken division cages (the clue does not say which cell is
+    /// larger).  The cell values must be positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+    ///
+    /// puzzle_solver::constraint::DivCage::new(3, vars[0], vars[1]);
+    /// ```
+    pub fn new(result: Val, a: VarToken, b: VarToken) -> Self {
+        DivCage {
+            result: result,
+            cells: [a, b],
+        }
+    }
+}
+
+/// Check if the pair satisfies max / min == result exactly.
+fn feasible(a: Val, b: Val, result: Val) -> bool {
+    let (hi, lo) = if a < b { (b, a) } else { (a, b) };
+    lo > 0 && hi % lo == 0 && hi / lo == result
+}
+
+impl Constraint for DivCage {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let domains: Vec<Vec<Val>> = self.cells.iter().map(|&var|
+                if let Some(val) = search.get_assigned(var) {
+                    vec![val]
+                } else {
+                    search.get_unassigned(var).collect()
+                })
+            .collect();
+
+        // Prune each cell to the values supported by some pair.
+        let mut allowed = vec![BTreeSet::new(); 2];
+        for &a in domains[0].iter() {
+            for &b in domains[1].iter() {
+                if feasible(a, b, self.result) {
+                    allowed[0].insert(a);
+                    allowed[1].insert(b);
+                }
+            }
+        }
+
+        if allowed[0].is_empty() {
+            return Err(());
+        }
+
+        for (cell, &var) in self.cells.iter().enumerate() {
+            if !search.is_assigned(var) {
+                for &val in domains[cell].iter() {
+                    if !allowed[cell].contains(&val) {
+                        try!(search.remove_candidate(var, val));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        // Keep a duplicated token if the cells are merged: the pair
+        // enumeration rejects a / a = result itself unless the
+        // result is one.
+        let subst = |var| if var == from { to } else { var };
+        Ok(Rc::new(DivCage{
+            result: self.result,
+            cells: [subst(self.cells[0]), subst(self.cells[1])],
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+
+    #[test]
+    fn test_two_cell_three() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        puzzle.div_cage(3, vars[0], vars[1]);
+
+        // 3 = 3 / 1 = 6 / 2.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(vars[0]).collect::<Vec<Val>>(),
+                &[1,2,3,6]);
+        assert_eq!(search.get_unassigned(vars[1]).collect::<Vec<Val>>(),
+                &[1,2,3,6]);
+    }
+
+    #[test]
+    fn test_assigned_cell() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        puzzle.div_cage(3, vars[0], vars[1]);
+        puzzle.set_value(vars[0], 2);
+
+        // 2 / b is never 3, so b / 2 = 3 forces b = 6.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[vars[1]], 6);
+    }
+
+    #[test]
+    fn test_unify_merged_cells() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        // a / a = 1 holds for every candidate.
+        puzzle.div_cage(1, vars[0], vars[1]);
+        puzzle.unify(vars[0], vars[1]);
+        assert_eq!(puzzle.solve_all().len(), 6);
+
+        // ... but any other result is a contradiction.
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+        puzzle.div_cage(2, vars[0], vars[1]);
+        puzzle.unify(vars[0], vars[1]);
+        assert_eq!(puzzle.solve_all().len(), 0);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[2,3,4,5,6]);
+
+        // 4 * 2 is already out of range.
+        puzzle.div_cage(4, vars[0], vars[1]);
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,184 @@
+//! Entropy implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Entropy {
+    cells: Vec<VarToken>,
+}
+
+/// The band of a value: low {1,2,3}, mid {4,5,6}, high {7,8,9}.
+/// Values outside 1..=9 belong to no band and are never supported.
+fn band(val: Val) -> Option<usize> {
+    if 1 <= val && val <= 9 {
+        Some(((val - 1) / 3) as usize)
+    } else {
+        None
+    }
+}
+
+/// The bands of a variable's candidates.
+fn band_set(search: &PuzzleSearch, var: VarToken) -> [bool; 3] {
+    let mut bands = [false; 3];
+
+    if let Some(val) = search.get_assigned(var) {
+        if let Some(b) = band(val) {
+            bands[b] = true;
+        }
+    } else {
+        for val in search.get_unassigned(var) {
+            if let Some(b) = band(val) {
+                bands[b] = true;
+            }
+        }
+    }
+
+    bands
+}
+
+impl Entropy {
+    /// Allocate a new Entropy constraint.  Every run of 3 consecutive
+    /// cells along the line contains one value from each of the bands
+    /// low {1,2,3}, mid {4,5,6} and high {7,8,9}.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle_solver::constraint::Entropy::new(vars);
+    /// ```
+    pub fn new(cells: Vec<VarToken>) -> Self {
+        Entropy {
+            cells: cells,
+        }
+    }
+
+    /// Keep only the candidates of each cell in the triple that can
+    /// be completed to three distinct bands.
+    fn constrain_triple(&self, search: &mut PuzzleSearch,
+            triple: &[VarToken]) -> PsResult<()> {
+        for i in 0..3 {
+            let others: Vec<[bool; 3]> = (0..3).filter(|&j| j != i)
+                .map(|j| band_set(search, triple[j]))
+                .collect();
+
+            let supported = |val: Val| {
+                let b0 = match band(val) {
+                    Some(b0) => b0,
+                    None => return false,
+                };
+                (0..3).any(|b1| others[0][b1]
+                        && (0..3).any(|b2| others[1][b2]
+                            && b0 != b1 && b0 != b2 && b1 != b2))
+            };
+
+            let var = triple[i];
+            if let Some(val) = search.get_assigned(var) {
+                if !supported(val) {
+                    return Err(());
+                }
+            } else {
+                let remove: Vec<Val> = search.get_unassigned(var)
+                    .filter(|&val| !supported(val))
+                    .collect();
+
+                for val in remove.into_iter() {
+                    try!(search.remove_candidate(var, val));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Constraint for Entropy {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        for triple in self.cells.windows(3) {
+            try!(self.constrain_triple(search, triple));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let cells = self.cells.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(Entropy::new(cells)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::Entropy;
+
+    #[test]
+    fn test_forced_band() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3,
+                &[1,2,3,4,5,6,7,8,9]);
+        puzzle.set_value(vars[0], 1);
+        puzzle.set_value(vars[1], 5);
+        puzzle.add_constraint(Entropy::new(vars.clone()));
+
+        // Low and mid are taken; the third cell must be high.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(vars[2]).collect::<Vec<Val>>(),
+                &[7,8,9]);
+    }
+
+    #[test]
+    fn test_propagates_along_line() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(4,
+                &[1,2,3,4,5,6,7,8,9]);
+        puzzle.set_value(vars[0], 2);
+        puzzle.set_value(vars[1], 4);
+        puzzle.set_value(vars[2], 9);
+        puzzle.add_constraint(Entropy::new(vars.clone()));
+
+        // The second triple (4, 9, ?) must be completed by a low.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(vars[3]).collect::<Vec<Val>>(),
+                &[1,2,3]);
+    }
+
+    #[test]
+    fn test_out_of_band_pruned() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3,
+                &[0,1,5,9,10]);
+        puzzle.add_constraint(Entropy::new(vars.clone()));
+
+        // Values outside 1..=9 belong to no band.
+        let search = puzzle.step().expect("contradiction");
+        for &var in vars.iter() {
+            assert_eq!(search.get_unassigned(var).collect::<Vec<Val>>(),
+                    &[1,5,9]);
+        }
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3,
+                &[1,2,3,4,5,6,7,8,9]);
+        puzzle.set_value(vars[0], 1);
+        puzzle.set_value(vars[1], 2);
+        puzzle.add_constraint(Entropy::new(vars));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -4,7 +4,17 @@ use std::rc::Rc;
 use num_rational::Ratio;
 use num_traits::Zero;
 
-use ::{Constraint,LinExpr,PsResult,PuzzleSearch,Val,VarToken};
+use ::{Coef,Constraint,LinExpr,PsResult,PuzzleSearch,Val,VarToken};
+
+// The bound arithmetic multiplies coefficients by candidate values
+// and sums across terms, e.g. 10-letter alphametics have coefficients
+// of 10^9, so evaluate with i64 intermediates to avoid overflowing
+// the i32 numerators.
+type WideRatio = Ratio<i64>;
+
+fn widen(ratio: Coef) -> WideRatio {
+    Ratio::new(*ratio.numer() as i64, *ratio.denom() as i64)
+}
 
 pub struct Equality {
     // The equation: 0 = constant + coef1 * var1 + coef2 * var2 + ...
@@ -38,12 +48,12 @@ impl Constraint for Equality {
 
     fn on_assigned(&self, search: &mut PuzzleSearch, _: VarToken, _: Val)
             -> PsResult<()> {
-        let mut sum = self.eqn.constant;
+        let mut sum = widen(self.eqn.constant);
         let mut unassigned_var = None;
 
         for (&var, &coef) in self.eqn.coef.iter() {
             if let Some(val) = search.get_assigned(var) {
-                sum = sum + coef * Ratio::from_integer(val);
+                sum = sum + widen(coef) * Ratio::from_integer(val as i64);
             } else {
                 // If we find more than one unassigned variable,
                 // cannot assign any other variables.
@@ -58,9 +68,13 @@ impl Constraint for Equality {
         // If we have exactly one unassigned variable, assign it.
         if let Some((var, coef)) = unassigned_var {
             // sum + coef * var = 0.
-            let val = -sum / coef;
+            let val = -sum / widen(coef);
             if val.is_integer() {
-                try!(search.set_candidate(var, val.to_integer()));
+                let val = val.to_integer();
+                if val < Val::min_value() as i64 || (Val::max_value() as i64) < val {
+                    return Err(());
+                }
+                try!(search.set_candidate(var, val as Val));
             } else {
                 return Err(());
             }
@@ -74,17 +88,18 @@ impl Constraint for Equality {
     }
 
     fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
-        let mut sum_min = self.eqn.constant;
-        let mut sum_max = self.eqn.constant;
+        let mut sum_min = widen(self.eqn.constant);
+        let mut sum_max = widen(self.eqn.constant);
 
         for (&var, &coef) in self.eqn.coef.iter() {
+            let coef = widen(coef);
             let (min_val, max_val) = try!(search.get_min_max(var));
             if coef > Ratio::zero() {
-                sum_min = sum_min + coef * Ratio::from_integer(min_val);
-                sum_max = sum_max + coef * Ratio::from_integer(max_val);
+                sum_min = sum_min + coef * Ratio::from_integer(min_val as i64);
+                sum_max = sum_max + coef * Ratio::from_integer(max_val as i64);
             } else {
-                sum_min = sum_min + coef * Ratio::from_integer(max_val);
-                sum_max = sum_max + coef * Ratio::from_integer(min_val);
+                sum_min = sum_min + coef * Ratio::from_integer(max_val as i64);
+                sum_max = sum_max + coef * Ratio::from_integer(min_val as i64);
             }
         }
 
@@ -104,27 +119,41 @@ impl Constraint for Equality {
                 continue;
             }
 
+            let coef = widen(coef);
             let (min_val, max_val) = try!(search.get_min_max(var));
             let (min_bnd, max_bnd);
 
+            // coef * var = -(sum of the other terms); subtracting the
+            // variable's own contribution from sum_min/sum_max gives
+            // the bounds of the other terms.  Note that Ratio::floor
+            // rounds towards negative infinity (and ceil positive),
+            // so the same formulas hold for negative and fractional
+            // coefficients.
             if coef > Ratio::zero() {
-                min_bnd = ((coef * Ratio::from_integer(max_val) - sum_max) / coef).ceil().to_integer();
-                max_bnd = ((coef * Ratio::from_integer(min_val) - sum_min) / coef).floor().to_integer();
+                min_bnd = ((coef * Ratio::from_integer(max_val as i64) - sum_max) / coef).ceil().to_integer();
+                max_bnd = ((coef * Ratio::from_integer(min_val as i64) - sum_min) / coef).floor().to_integer();
             } else {
-                min_bnd = ((coef * Ratio::from_integer(max_val) - sum_min) / coef).ceil().to_integer();
-                max_bnd = ((coef * Ratio::from_integer(min_val) - sum_max) / coef).floor().to_integer();
+                min_bnd = ((coef * Ratio::from_integer(max_val as i64) - sum_min) / coef).ceil().to_integer();
+                max_bnd = ((coef * Ratio::from_integer(min_val as i64) - sum_max) / coef).floor().to_integer();
             }
 
-            if min_val < min_bnd || max_bnd < max_val {
+            if (min_val as i64) < min_bnd || max_bnd < (max_val as i64) {
+                if (Val::max_value() as i64) < min_bnd
+                        || max_bnd < (Val::min_value() as i64) {
+                    return Err(());
+                }
+
+                let min_bnd = ::std::cmp::max(min_bnd, Val::min_value() as i64) as Val;
+                let max_bnd = ::std::cmp::min(max_bnd, Val::max_value() as i64) as Val;
                 let (new_min, new_max)
                     = try!(search.bound_candidate_range(var, min_bnd, max_bnd));
 
                 if coef > Ratio::zero() {
-                    sum_min = sum_min + coef * Ratio::from_integer(new_min - min_val);
-                    sum_max = sum_max + coef * Ratio::from_integer(new_max - max_val);
+                    sum_min = sum_min + coef * Ratio::from_integer((new_min - min_val) as i64);
+                    sum_max = sum_max + coef * Ratio::from_integer((new_max - max_val) as i64);
                 } else {
-                    sum_min = sum_min + coef * Ratio::from_integer(new_max - max_val);
-                    sum_max = sum_max + coef * Ratio::from_integer(new_min - min_val);
+                    sum_min = sum_min + coef * Ratio::from_integer((new_max - max_val) as i64);
+                    sum_max = sum_max + coef * Ratio::from_integer((new_min - min_val) as i64);
                 }
 
                 iters = self.eqn.coef.len();
@@ -136,17 +165,22 @@ impl Constraint for Equality {
 
     fn substitute(&self, from: VarToken, to: VarToken)
             -> PsResult<Rc<Constraint>> {
-        let mut eqn = self.eqn.clone();
-        if let Some(coef) = eqn.coef.remove(&from) {
-            eqn = eqn + coef * to;
+        let eqn = self.eqn.substitute(from, &LinExpr::from(to));
+
+        if eqn.coef.is_empty() && !eqn.constant.is_zero() {
+            // All variable terms cancelled, leaving e.g. "0 == 3".
+            return Err(());
         }
 
+        // If the terms cancelled to "0 == 0", the constraint is
+        // always true; its vars() is empty so it never wakes again.
         Ok(Rc::new(Equality{ eqn: eqn }))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use num_rational::Ratio;
     use ::{Puzzle,Val};
 
     #[test]
@@ -161,6 +195,105 @@ mod tests {
         assert!(search.is_none());
     }
 
+    #[test]
+    fn test_substitute_degenerate_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[1,2,3]);
+        let b = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        // Substituting a with b degenerates the equation to 0 == 3.
+        puzzle.equals(a - b, 3);
+        puzzle.unify(a, b);
+
+        assert!(puzzle.solve_any().is_none());
+    }
+
+    #[test]
+    fn test_substitute_degenerate_always_true() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[1,2,3]);
+        let b = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        // Substituting a with b degenerates the equation to 0 == 0.
+        puzzle.equals(a - b, 0);
+        puzzle.unify(a, b);
+
+        assert_eq!(puzzle.solve_all().len(), 3);
+    }
+
+    #[test]
+    fn test_ten_letter_coefficients() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2,3]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        // The leading digit of a 10-letter word has coefficient 10^9;
+        // the bound arithmetic must not overflow.
+        puzzle.equals(1_000_000_000 * v0 + v1, 2_000_000_003);
+
+        let solution = puzzle.solve_any().expect("solution");
+        assert_eq!(solution[v0], 2);
+        assert_eq!(solution[v1], 3);
+    }
+
+    #[test]
+    fn test_bound_rounding_sweep() {
+        // Exhaustively check the ceil/floor choices in the bound
+        // arithmetic, which are easy to get wrong for negative and
+        // fractional coefficients: propagation must never remove a
+        // supported value, and the search must find exactly the
+        // brute-force solutions.
+        let coefs = [ Ratio::new(-2,1), Ratio::new(-1,1), Ratio::new(-1,2),
+                      Ratio::new(1,2), Ratio::new(1,1), Ratio::new(2,1) ];
+        let domain = [-2,-1,0,1,2];
+
+        for &a in coefs.iter() {
+            for &b in coefs.iter() {
+                for c in -3..4 {
+                    // Brute force a * x + b * y + c == 0.
+                    let mut supported_x = Vec::new();
+                    let mut supported_y = Vec::new();
+                    for &x in domain.iter() {
+                        for &y in domain.iter() {
+                            let sum = a * Ratio::from_integer(x)
+                                + b * Ratio::from_integer(y)
+                                + Ratio::from_integer(c);
+                            if sum == Ratio::from_integer(0) {
+                                supported_x.push(x);
+                                supported_y.push(y);
+                            }
+                        }
+                    }
+
+                    let mut puzzle = Puzzle::new();
+                    let x = puzzle.new_var_with_candidates(&domain);
+                    let y = puzzle.new_var_with_candidates(&domain);
+                    puzzle.equals(x * a + y * b + c, 0);
+
+                    if let Some(search) = puzzle.step() {
+                        for &(var, vals) in [(x, &supported_x),
+                                             (y, &supported_y)].iter() {
+                            for &val in vals.iter() {
+                                assert!(search.get_assigned(var) == Some(val)
+                                        || search.get_unassigned(var)
+                                            .any(|cand| cand == val),
+                                        "lost {} for {} x + {} y + {} == 0",
+                                        val, a, b, c);
+                            }
+                        }
+                    } else {
+                        assert!(supported_x.is_empty(),
+                                "false contradiction for {} x + {} y + {} == 0",
+                                a, b, c);
+                    }
+
+                    assert_eq!(puzzle.solve_all().len(), supported_x.len(),
+                            "solutions of {} x + {} y + {} == 0", a, b, c);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_assign() {
         let mut puzzle = Puzzle::new();
@@ -0,0 +1,109 @@
+//! Even odd implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct EvenOdd {
+    even_vars: Vec<VarToken>,
+    odd_vars: Vec<VarToken>,
+}
+
+/// Remove the candidates that do not have the given parity.
+fn keep_parity(search: &mut PuzzleSearch, var: VarToken, parity: Val)
+        -> PsResult<()> {
+    if let Some(val) = search.get_assigned(var) {
+        if val & 1 != parity {
+            return Err(());
+        }
+    } else {
+        let remove: Vec<Val> = search.get_unassigned(var)
+            .filter(|&val| val & 1 != parity)
+            .collect();
+
+        for val in remove.into_iter() {
+            try!(search.remove_candidate(var, val));
+        }
+    }
+
+    Ok(())
+}
+
+impl EvenOdd {
+    /// Allocate a new Even Odd constraint, forcing the variables in
+    /// the first group to even values and the variables in the
+    /// second group to odd values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2,3,4]);
+    ///
+    /// puzzle_solver::constraint::EvenOdd::new(
+    ///         vec![vars[0], vars[1]], vec![vars[2], vars[3]]);
+    /// ```
+    pub fn new(even_vars: Vec<VarToken>, odd_vars: Vec<VarToken>) -> Self {
+        EvenOdd {
+            even_vars: even_vars,
+            odd_vars: odd_vars,
+        }
+    }
+}
+
+impl Constraint for EvenOdd {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.even_vars.iter().chain(self.odd_vars.iter()))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        for &var in self.even_vars.iter() {
+            try!(keep_parity(search, var, 0));
+        }
+
+        for &var in self.odd_vars.iter() {
+            try!(keep_parity(search, var, 1));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |&var| if var == from { to } else { var };
+        Ok(Rc::new(EvenOdd{
+            even_vars: self.even_vars.iter().map(&subst).collect(),
+            odd_vars: self.odd_vars.iter().map(&subst).collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::EvenOdd;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2,3,4]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4]);
+
+        puzzle.add_constraint(EvenOdd::new(vec![v0], vec![v1]));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v0).collect::<Vec<Val>>(), &[2,4]);
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[1,3]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,3]);
+
+        puzzle.add_constraint(EvenOdd::new(vec![v0], vec![]));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
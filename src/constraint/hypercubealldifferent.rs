@@ -0,0 +1,189 @@
+//! Hypercube all different implementation.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct HypercubeAllDifferent {
+    vars: Vec<VarToken>,
+    lines: Vec<Vec<VarToken>>,
+}
+
+impl HypercubeAllDifferent {
+    /// Allocate a new Hypercube All Different constraint, treating
+    /// the variables as an n-dimensional array in row-major order.
+    /// The variables along every axis-aligned line are all different.
+    ///
+    /// A two dimensional grid gives the Latin square constraint
+    /// (rows and columns); three dimensions give a Latin cube.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2]);
+    ///
+    /// puzzle_solver::constraint::HypercubeAllDifferent::new(
+    ///         vec![2, 2], vars);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dimensions do not match the number of variables.
+    pub fn new(dims: Vec<usize>, vars: Vec<VarToken>) -> Self {
+        assert_eq!(dims.iter().product::<usize>(), vars.len());
+
+        // The stride along each axis, in row-major order.
+        let mut strides = vec![1; dims.len()];
+        for axis in (0..dims.len()).rev().skip(1) {
+            strides[axis] = strides[axis + 1] * dims[axis + 1];
+        }
+
+        let mut lines = Vec::new();
+        for axis in 0..dims.len() {
+            for base in 0..vars.len() {
+                // Lines start where the coordinate along the axis is zero.
+                if (base / strides[axis]) % dims[axis] == 0 {
+                    lines.push((0..dims[axis])
+                               .map(|j| vars[base + j * strides[axis]])
+                               .collect());
+                }
+            }
+        }
+
+        HypercubeAllDifferent {
+            vars: vars,
+            lines: lines,
+        }
+    }
+
+    /// Require the variables along one line to be all different.
+    fn constrain_line(&self, search: &mut PuzzleSearch, line: &[VarToken])
+            -> PsResult<()> {
+        // Build a table of which values can be assigned to which variables.
+        let mut num_unassigned = 0;
+        let mut all_candidates = BTreeMap::new();
+
+        for &var in line.iter().filter(|&var| !search.is_assigned(*var)) {
+            num_unassigned = num_unassigned + 1;
+
+            for val in search.get_unassigned(var) {
+                if all_candidates.contains_key(&val) {
+                    all_candidates.insert(val, None);
+                } else {
+                    all_candidates.insert(val, Some(var));
+                }
+            }
+        }
+
+        if num_unassigned > all_candidates.len() {
+            // More unassigned variables than candidates, contradiction.
+            return Err(());
+        } else if num_unassigned == all_candidates.len() {
+            // As many as variables as candidates.
+            for (&val, &opt) in all_candidates.iter() {
+                if let Some(var) = opt {
+                    try!(search.set_candidate(var, val));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Constraint for HypercubeAllDifferent {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        for line in self.lines.iter().filter(|line| line.contains(&var)) {
+            for &var2 in line.iter().filter(|&v| *v != var) {
+                try!(search.remove_candidate(var2, val));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        for line in self.lines.iter() {
+            try!(self.constrain_line(search, line));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        if self.vars.contains(&from) && !self.vars.contains(&to) {
+            let subst = |&var| if var == from { to } else { var };
+            let vars = self.vars.iter().map(&subst).collect();
+            let lines = self.lines.iter()
+                .map(|line| line.iter().map(&subst).collect())
+                .collect();
+            return Ok(Rc::new(HypercubeAllDifferent{
+                vars: vars,
+                lines: lines,
+            }));
+        }
+
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::HypercubeAllDifferent;
+
+    #[test]
+    fn test_latin_square() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2]);
+        puzzle.add_constraint(HypercubeAllDifferent::new(
+                vec![2, 2], vars.clone()));
+
+        // The 2x2 Latin squares: (1,2 / 2,1) and (2,1 / 1,2).
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 2);
+        assert_eq!(solutions[0][vars[0]], 1);
+        assert_eq!(solutions[0][vars[1]], 2);
+        assert_eq!(solutions[0][vars[2]], 2);
+        assert_eq!(solutions[0][vars[3]], 1);
+    }
+
+    #[test]
+    fn test_latin_cube() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(8, &[1,2]);
+        puzzle.add_constraint(HypercubeAllDifferent::new(
+                vec![2, 2, 2], vars));
+
+        // Fixing one cell determines the whole cube.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 2);
+    }
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(9, &[1,2,3]);
+        puzzle.set_value(vars[0], 1);
+        puzzle.add_constraint(HypercubeAllDifferent::new(
+                vec![3, 3], vars.clone()));
+
+        let search = puzzle.step().expect("contradiction");
+
+        // The rest of the row and column lose the candidate.
+        assert_eq!(search.get_unassigned(vars[1]).collect::<Vec<Val>>(),
+                &[2,3]);
+        assert_eq!(search.get_unassigned(vars[3]).collect::<Vec<Val>>(),
+                &[2,3]);
+        assert_eq!(search.get_unassigned(vars[4]).collect::<Vec<Val>>(),
+                &[1,2,3]);
+    }
+}
@@ -0,0 +1,134 @@
+//! Exact-value knapsack implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct KnapsackExact {
+    selected: Vec<VarToken>,
+    weights: Vec<Val>,
+    capacity: Val,
+}
+
+impl KnapsackExact {
+    /// Allocate a new Knapsack Exact constraint.  The selection
+    /// variables take the candidates 0 and 1, and the weights of the
+    /// selected items must sum to exactly the capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1]);
+    ///
+    /// puzzle_solver::constraint::KnapsackExact::new(vars, vec![2,3,4], 5);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of weights differs from the number of
+    /// selection variables, or if a weight is negative.
+    pub fn new(selected: Vec<VarToken>, weights: Vec<Val>, capacity: Val)
+            -> Self {
+        assert_eq!(selected.len(), weights.len());
+        assert!(weights.iter().all(|&w| w >= 0));
+        KnapsackExact {
+            selected: selected,
+            weights: weights,
+            capacity: capacity,
+        }
+    }
+}
+
+impl Constraint for KnapsackExact {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.selected.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        // The weight still needed, and the maximum weight that the
+        // undecided items can contribute.
+        let mut needed = self.capacity;
+        let mut available = 0;
+
+        for (&var, &weight) in self.selected.iter().zip(self.weights.iter()) {
+            match search.get_assigned(var) {
+                Some(0) => (),
+                Some(_) => needed = needed - weight,
+                None => available = available + weight,
+            }
+        }
+
+        if needed < 0 || needed > available {
+            return Err(());
+        }
+
+        for (&var, &weight) in self.selected.iter().zip(self.weights.iter()) {
+            if !search.is_assigned(var) {
+                if weight > needed {
+                    // Selecting this item would overshoot.
+                    try!(search.remove_candidate(var, 1));
+                } else if available - weight < needed {
+                    // The capacity cannot be reached without it.
+                    try!(search.remove_candidate(var, 0));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let selected = self.selected.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(KnapsackExact{
+            selected: selected,
+            weights: self.weights.clone(),
+            capacity: self.capacity,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+    use super::KnapsackExact;
+
+    #[test]
+    fn test_force_selection() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1]);
+
+        puzzle.add_constraint(KnapsackExact::new(vars.clone(), vec![5,3,2], 10));
+
+        let search = puzzle.step().expect("contradiction");
+        for &var in vars.iter() {
+            assert_eq!(search[var], 1);
+        }
+    }
+
+    #[test]
+    fn test_force_exclusion() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[0,1]);
+
+        puzzle.add_constraint(KnapsackExact::new(vars.clone(), vec![5,7], 5));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[vars[0]], 1);
+        assert_eq!(search[vars[1]], 0);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[0,1]);
+
+        puzzle.add_constraint(KnapsackExact::new(vars, vec![2,4], 7));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
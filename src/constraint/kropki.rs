@@ -0,0 +1,135 @@
+//! Kropki dot implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+/// The kind of a kropki dot.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum KropkiKind {
+    /// A white dot: the two cells differ by exactly one.
+    Consecutive,
+
+    /// A black dot: one cell is twice the other.
+    Double,
+}
+
+pub struct Kropki {
+    a: VarToken,
+    b: VarToken,
+    kind: KropkiKind,
+}
+
+impl Kropki {
+    /// Allocate a new Kropki dot constraint between two cells.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle_solver::constraint::Kropki::new(vars[0], vars[1],
+    ///         puzzle_solver::constraint::KropkiKind::Double);
+    /// ```
+    pub fn new(a: VarToken, b: VarToken, kind: KropkiKind) -> Self {
+        Kropki {
+            a: a,
+            b: b,
+            kind: kind,
+        }
+    }
+
+    /// Check if the two values satisfy the dot.
+    fn is_allowed(&self, a: Val, b: Val) -> bool {
+        match self.kind {
+            KropkiKind::Consecutive => (a - b).abs() == 1,
+            KropkiKind::Double => a == 2 * b || b == 2 * a,
+        }
+    }
+}
+
+impl Constraint for Kropki {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new([&self.a, &self.b].to_vec().into_iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        let other = if var == self.a {
+            self.b
+        } else {
+            self.a
+        };
+
+        if let Some(val2) = search.get_assigned(other) {
+            if self.is_allowed(val, val2) {
+                Ok(())
+            } else {
+                Err(())
+            }
+        } else {
+            let gone: Vec<Val> = search.get_unassigned(other)
+                .filter(|&val2| !self.is_allowed(val, val2))
+                .collect();
+            for val2 in gone.into_iter() {
+                try!(search.remove_candidate(other, val2));
+            }
+
+            Ok(())
+        }
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |var| if var == from { to } else { var };
+        Ok(Rc::new(Kropki{
+            a: subst(self.a),
+            b: subst(self.b),
+            kind: self.kind,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::{Kropki,KropkiKind};
+
+    #[test]
+    fn test_consecutive() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        puzzle.set_value(v0, 4);
+        puzzle.add_constraint(Kropki::new(v0, v1, KropkiKind::Consecutive));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[3,5]);
+    }
+
+    #[test]
+    fn test_double() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        puzzle.set_value(v0, 4);
+        puzzle.add_constraint(Kropki::new(v0, v1, KropkiKind::Double));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[2,8]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[5]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        puzzle.add_constraint(Kropki::new(v0, v1, KropkiKind::Double));
+
+        // Nothing is double or half of five in range.
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
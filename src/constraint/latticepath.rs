@@ -0,0 +1,161 @@
+//! Lattice path implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct LatticePath {
+    positions: Vec<VarToken>,
+    width: usize,
+}
+
+impl LatticePath {
+    /// Allocate a new Lattice Path constraint.  The variables are
+    /// positions on a grid, encoded as width * y + x, and each
+    /// consecutive pair of positions differs by exactly one step in a
+    /// cardinal direction, i.e. +-1 or +-width.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let positions = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[0,1,2,3,4,5,6,7,8]);
+    ///
+    /// puzzle_solver::constraint::LatticePath::new(positions, 3);
+    /// ```
+    pub fn new(positions: Vec<VarToken>, grid_width: usize) -> Self {
+        LatticePath {
+            positions: positions,
+            width: grid_width,
+        }
+    }
+
+    /// Check if the two positions differ by exactly one step in a
+    /// cardinal direction.
+    fn is_step(&self, a: Val, b: Val) -> bool {
+        let width = self.width as Val;
+        (a - b).abs() == width
+            || ((a - b).abs() == 1 && a / width == b / width)
+    }
+
+    /// Restrict the candidates of a variable to the positions
+    /// reachable in one step from the given set of positions.
+    fn constrain_neighbours(&self, search: &mut PuzzleSearch,
+            var: VarToken, from: &[Val]) -> PsResult<()> {
+        if search.is_assigned(var) {
+            return Ok(());
+        }
+
+        let discard = search.get_unassigned(var)
+            .filter(|&c| !from.iter().any(|&a| self.is_step(a, c)))
+            .collect::<Vec<_>>();
+
+        for c in discard.into_iter() {
+            try!(search.remove_candidate(var, c));
+        }
+
+        Ok(())
+    }
+
+    /// Get the candidates of a variable, assigned or not.
+    fn candidates(search: &PuzzleSearch, var: VarToken) -> Vec<Val> {
+        match search.get_assigned(var) {
+            Some(val) => vec![val],
+            None => search.get_unassigned(var).collect(),
+        }
+    }
+}
+
+impl Constraint for LatticePath {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.positions.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        for idx in 0..self.positions.len() {
+            if self.positions[idx] != var {
+                continue;
+            }
+
+            if idx > 0 {
+                try!(self.constrain_neighbours(
+                        search, self.positions[idx - 1], &[val]));
+            }
+
+            if idx + 1 < self.positions.len() {
+                try!(self.constrain_neighbours(
+                        search, self.positions[idx + 1], &[val]));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        for window in self.positions.windows(2) {
+            let cs0 = Self::candidates(search, window[0]);
+            let cs1 = Self::candidates(search, window[1]);
+            try!(self.constrain_neighbours(search, window[1], &cs0));
+            try!(self.constrain_neighbours(search, window[0], &cs1));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let positions = self.positions.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(LatticePath{
+            positions: positions,
+            width: self.width,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::LatticePath;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let positions = puzzle.new_vars_with_candidates_1d(2,
+                &[0,1,2,3,4,5,6,7,8]);
+        puzzle.set_value(positions[0], 4);
+        puzzle.add_constraint(LatticePath::new(positions.clone(), 3));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(positions[1]).collect::<Vec<Val>>(),
+                &[1,3,5,7]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let positions = puzzle.new_vars_with_candidates_1d(2,
+                &[0,1,2,3,4,5,6,7,8]);
+        puzzle.set_value(positions[0], 0);
+        puzzle.intersect_candidates(positions[1], &[8]);
+        puzzle.add_constraint(LatticePath::new(positions, 3));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let positions = puzzle.new_vars_with_candidates_1d(3, &[0,1,2,3]);
+        puzzle.add_constraint(LatticePath::new(positions, 2));
+
+        // Every cell of the 2x2 grid has two neighbours, and the path
+        // may revisit cells: 4 * 2 * 2 solutions.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 16);
+    }
+}
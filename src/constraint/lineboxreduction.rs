@@ -0,0 +1,147 @@
+//! Line-box reduction (pointing pairs) implementation.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct LineBoxReduction {
+    line: Vec<VarToken>,
+    box_cells: Vec<VarToken>,
+}
+
+/// Check if a variable can still take the given value.
+fn can_take(search: &PuzzleSearch, var: VarToken, val: Val) -> bool {
+    match search.get_assigned(var) {
+        Some(v) => v == val,
+        None => search.get_unassigned(var).any(|c| c == val),
+    }
+}
+
+/// If the value's possible cells in one house are confined to the
+/// intersection with the other house, remove the value from the rest
+/// of the other house.
+fn reduce(search: &mut PuzzleSearch, house: &[VarToken],
+        other: &[VarToken], val: Val) -> PsResult<()> {
+    let mut any = false;
+    for &var in house.iter() {
+        if can_take(search, var, val) {
+            if !other.contains(&var) {
+                return Ok(());
+            }
+
+            any = true;
+        }
+    }
+
+    if any {
+        for &var in other.iter() {
+            if !house.contains(&var) {
+                try!(search.remove_candidate(var, val));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl LineBoxReduction {
+    /// Allocate a new LineBoxReduction constraint, the classic
+    /// "pointing pairs" human technique.  Both cell lists are
+    /// assumed to be houses taking every value exactly once, e.g. a
+    /// sudoku row and an intersecting box.
+    ///
+    /// This is a redundant propagator: it removes candidates that
+    /// the houses' AllDifferent constraints would only discover by
+    /// guessing, but adds no new solutions or restrictions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let line = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    /// let boxc = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::LineBoxReduction::new(line, boxc);
+    /// ```
+    pub fn new(line: Vec<VarToken>, box_cells: Vec<VarToken>) -> Self {
+        LineBoxReduction {
+            line: line,
+            box_cells: box_cells,
+        }
+    }
+}
+
+impl Constraint for LineBoxReduction {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.line.iter().chain(self.box_cells.iter()))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let mut values = BTreeSet::new();
+        for &var in self.line.iter().chain(self.box_cells.iter()) {
+            if let Some(val) = search.get_assigned(var) {
+                values.insert(val);
+            } else {
+                values.extend(search.get_unassigned(var));
+            }
+        }
+
+        for &val in values.iter() {
+            try!(reduce(search, &self.line, &self.box_cells, val));
+            try!(reduce(search, &self.box_cells, &self.line, val));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |vars: &[VarToken]| vars.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(LineBoxReduction::new(subst(&self.line),
+                subst(&self.box_cells))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::LineBoxReduction;
+
+    #[test]
+    fn test_pointing_value() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[1,2]);
+        let b = puzzle.new_var_with_candidates(&[1,2]);
+        let c = puzzle.new_var_with_candidates(&[1,2,3]);
+        let d = puzzle.new_var_with_candidates(&[1,2,3]);
+        let e = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        // The 3 in the line [a,b,c] is confined to the shared cell c.
+        puzzle.add_constraint(LineBoxReduction::new(
+                vec![a, b, c], vec![c, d, e]));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(d).collect::<Vec<Val>>(), &[1,2]);
+        assert_eq!(search.get_unassigned(e).collect::<Vec<Val>>(), &[1,2]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[1,2]);
+        let b = puzzle.new_var_with_candidates(&[1,2]);
+        let c = puzzle.new_var_with_candidates(&[1,2,3]);
+        let d = puzzle.new_var_with_candidates(&[3]);
+        let e = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        // The box cell d takes the 3 that belongs to the line.
+        puzzle.add_constraint(LineBoxReduction::new(
+                vec![a, b, c], vec![c, d, e]));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
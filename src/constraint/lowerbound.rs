@@ -0,0 +1,75 @@
+//! Lower bound implementation.
+
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct LowerBound {
+    var: VarToken,
+    min: Val,
+}
+
+impl LowerBound {
+    /// Allocate a new Lower Bound constraint, removing all candidates
+    /// less than the minimum from the variable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2,3,4]);
+    ///
+    /// puzzle_solver::constraint::LowerBound::new(var, 3);
+    /// ```
+    pub fn new(var: VarToken, min: Val) -> Self {
+        LowerBound {
+            var: var,
+            min: min,
+        }
+    }
+}
+
+impl Constraint for LowerBound {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(iter::once(&self.var))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        try!(search.bound_candidate_range(self.var, self.min,
+                Val::max_value()));
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let var = if self.var == from { to } else { self.var };
+        Ok(Rc::new(LowerBound::new(var, self.min)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::LowerBound;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let var = puzzle.new_var_with_candidates(&[1,2,3,4]);
+        puzzle.add_constraint(LowerBound::new(var, 3));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(var).collect::<Vec<Val>>(), &[3,4]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let var = puzzle.new_var_with_candidates(&[1,2]);
+        puzzle.add_constraint(LowerBound::new(var, 3));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
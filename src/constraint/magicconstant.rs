@@ -0,0 +1,103 @@
+//! Magic constant implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,LinExpr,PsResult,PuzzleSearch,Val,VarToken};
+use super::Equality;
+
+pub struct MagicConstant {
+    vars: Vec<Vec<VarToken>>,
+    total: VarToken,
+    eq: Equality,
+}
+
+impl MagicConstant {
+    /// Allocate a new Magic Constant constraint.  The total variable
+    /// is the magic constant of the square, derived internally from
+    /// total * n = sum of all of the square's variables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut magic_square = puzzle_solver::Puzzle::new();
+    /// let vars = magic_square.new_vars_with_candidates_2d(3, 3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    /// let total = magic_square.new_var_with_candidates(&[15]);
+    ///
+    /// puzzle_solver::constraint::MagicConstant::new(vars, total);
+    /// ```
+    pub fn new(vars: Vec<Vec<VarToken>>, total: VarToken) -> Self {
+        let n = vars.len();
+        let sum = vars.iter()
+            .flat_map(|row| row.iter())
+            .fold(LinExpr::from(0), |sum, &var| sum + var);
+
+        MagicConstant {
+            eq: Equality::new(sum - (n as Val) * total),
+            vars: vars,
+            total: total,
+        }
+    }
+}
+
+impl Constraint for MagicConstant {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        self.eq.vars()
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        self.eq.on_assigned(search, var, val)
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        self.eq.on_updated(search)
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |&var| if var == from { to } else { var };
+        let vars = self.vars.iter()
+            .map(|row| row.iter().map(&subst).collect())
+            .collect();
+        Ok(Rc::new(MagicConstant::new(vars, subst(&self.total))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::MagicConstant;
+
+    #[test]
+    fn test_derive_total() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_2d(3, 3,
+                &[1,2,3,4,5,6,7,8,9]);
+        let total = puzzle.new_var_with_candidates(
+                &(1..30).collect::<Vec<Val>>());
+
+        let square = [ [2,7,6], [9,5,1], [4,3,8] ];
+        for y in 0..3 {
+            for x in 0..3 {
+                puzzle.set_value(vars[y][x], square[y][x]);
+            }
+        }
+
+        puzzle.add_constraint(MagicConstant::new(vars, total));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[total], 15);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_2d(1, 1, &[2]);
+        let total = puzzle.new_var_with_candidates(&[3]);
+        puzzle.add_constraint(MagicConstant::new(vars, total));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,121 @@
+//! Max cardinality implementation.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct MaxCardinality {
+    vars: Vec<VarToken>,
+    max_distinct: usize,
+}
+
+impl MaxCardinality {
+    /// Allocate a new Max Cardinality constraint.  At most
+    /// max_distinct distinct values may be used across the group of
+    /// variables, e.g. colouring a graph with a fixed palette size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::MaxCardinality::new(vars, 2);
+    /// ```
+    pub fn new(vars: Vec<VarToken>, max_distinct: usize) -> Self {
+        MaxCardinality {
+            vars: vars,
+            max_distinct: max_distinct,
+        }
+    }
+}
+
+impl Constraint for MaxCardinality {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let mut used = BTreeSet::new();
+        for &var in self.vars.iter() {
+            if let Some(val) = search.get_assigned(var) {
+                used.insert(val);
+            }
+        }
+
+        if used.len() > self.max_distinct {
+            return Err(());
+        } else if used.len() == self.max_distinct {
+            // All distinct values are taken; restrict the remaining
+            // variables to the values already used.
+            for &var in self.vars.iter() {
+                if !search.is_assigned(var) {
+                    let remove: Vec<Val> = search.get_unassigned(var)
+                        .filter(|val| !used.contains(val))
+                        .collect();
+
+                    for val in remove.into_iter() {
+                        try!(search.remove_candidate(var, val));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let new_vars = self.vars.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(MaxCardinality{
+            vars: new_vars,
+            max_distinct: self.max_distinct,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::MaxCardinality;
+
+    #[test]
+    fn test_restriction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[2]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        puzzle.add_constraint(MaxCardinality::new(vec![v0,v1,v2], 2));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v2).collect::<Vec<Val>>(), &[1,2]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[2]);
+        let v2 = puzzle.new_var_with_candidates(&[3]);
+
+        puzzle.add_constraint(MaxCardinality::new(vec![v0,v1,v2], 2));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2]);
+        puzzle.add_constraint(MaxCardinality::new(vars, 1));
+
+        // Either all 1s or all 2s.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 2);
+    }
+}
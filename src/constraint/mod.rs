@@ -9,6 +9,22 @@ use std::rc::Rc;
 
 use ::{PsResult,PuzzleSearch,Val,VarToken};
 
+/// How much filtering a constraint has achieved on the current
+/// search state, see `Constraint::check_consistency`.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum ConsistencyLevel {
+    /// The constraint does not report its consistency.
+    Unknown,
+
+    /// Running the constraint's propagation again would prune more
+    /// candidates (or fail).
+    NotConsistent,
+
+    /// The constraint's filtering is at fixpoint: running it again
+    /// would change nothing.
+    ArcConsistent,
+}
+
 /// Constraint trait.
 pub trait Constraint {
     /// An iterator over the variables that are involved in the constraint.
@@ -25,18 +41,108 @@ pub trait Constraint {
         Ok(())
     }
 
+    /// Report whether the constraint's filtering is at fixpoint on
+    /// the current search state.
+    ///
+    /// This is instrumentation for research and teaching, see
+    /// `PuzzleSearch::is_arc_consistent`; it must not modify the
+    /// search state.  Constraints that do not implement it report
+    /// Unknown.
+    fn check_consistency(&self, _search: &PuzzleSearch)
+            -> ConsistencyLevel {
+        ConsistencyLevel::Unknown
+    }
+
     /// Substitute the "from" variable with the "to" variable.
     ///
     /// Returns a new constraint with all instances of "from" replaced
     /// with "to", or Err if a contradiction was found.
+    ///
+    /// The returned constraint must not refer to "from" any more,
+    /// i.e. it must not appear in the new constraint's `vars()`.
+    /// Keeping the old token breaks the solver's bookkeeping; the
+    /// offending search branch fails with an error message naming
+    /// the constraint.
     fn substitute(&self, from: VarToken, to: VarToken)
             -> PsResult<Rc<Constraint>>;
 }
 
 pub use self::alldifferent::AllDifferent;
+pub use self::alldifferentgroups::AllDifferentGroups;
+pub use self::alternatingparity::AlternatingParity;
+pub use self::antiknight::AntiKnight;
+pub use self::arcconsistency::ArcConsistency;
+pub use self::between::Between;
+pub use self::bitwise::{BitAnd,BitOr,BitXor};
+pub use self::cage::Cage;
+pub use self::congruence::Congruence;
+pub use self::connected::Connected;
+pub use self::consecutive::Consecutive;
+pub use self::difference::Difference;
+pub use self::distinct::Distinct;
+pub use self::distinctsums::DistinctSums;
+pub use self::divcage::DivCage;
+pub use self::entropy::Entropy;
 pub use self::equality::Equality;
+pub use self::evenodd::EvenOdd;
+pub use self::hypercubealldifferent::HypercubeAllDifferent;
+pub use self::knapsackexact::KnapsackExact;
+pub use self::kropki::{Kropki,KropkiKind};
+pub use self::latticepath::LatticePath;
+pub use self::lineboxreduction::LineBoxReduction;
+pub use self::lowerbound::LowerBound;
+pub use self::magicconstant::MagicConstant;
+pub use self::maxcardinality::MaxCardinality;
+pub use self::mulcage::MulCage;
+pub use self::renban::Renban;
+pub use self::restrictedsum::RestrictedSum;
+pub use self::skyscraper::Skyscraper;
+pub use self::softalldifferent::SoftAllDifferent;
+pub use self::starbattle::StarBattle;
+pub use self::subcage::SubCage;
+pub use self::sumparity::SumParity;
 pub use self::unify::Unify;
+pub use self::upperbound::UpperBound;
+pub use self::viewcount::ViewCount;
+pub use self::whisper::Whisper;
+pub use self::xor::Xor;
 
 mod alldifferent;
+mod alldifferentgroups;
+mod alternatingparity;
+mod antiknight;
+mod arcconsistency;
+mod between;
+mod bitwise;
+mod cage;
+mod congruence;
+mod connected;
+mod consecutive;
+mod difference;
+mod distinct;
+mod distinctsums;
+mod divcage;
+mod entropy;
 mod equality;
+mod evenodd;
+mod hypercubealldifferent;
+mod knapsackexact;
+mod kropki;
+mod latticepath;
+mod lineboxreduction;
+mod lowerbound;
+mod magicconstant;
+mod maxcardinality;
+mod mulcage;
+mod renban;
+mod restrictedsum;
+mod skyscraper;
+mod softalldifferent;
+mod starbattle;
+mod subcage;
+mod sumparity;
 mod unify;
+mod upperbound;
+mod viewcount;
+mod whisper;
+mod xor;
@@ -0,0 +1,172 @@
+//! Multiplication cage (calcudoku) implementation.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct MulCage {
+    total: Val,
+    cells: Vec<VarToken>,
+}
+
+impl MulCage {
+    /// Allocate a new MulCage constraint.  The cells multiply to the
+    /// total, as in calcudoku/kenken cages.  The cell values must be
+    /// non-zero; values may repeat (spanning a row and a column is
+    /// the row's and column's business).
+    ///
+    /// Propagation enumerates the feasible value combinations and
+    /// prunes each cell to the divisors of the total appearing in at
+    /// least one combination.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+    ///
+    /// puzzle_solver::constraint::MulCage::new(12, vars);
+    /// ```
+    pub fn new(total: Val, cells: Vec<VarToken>) -> Self {
+        MulCage {
+            total: total,
+            cells: cells,
+        }
+    }
+}
+
+/// Enumerate the combinations of values multiplying to the remaining
+/// total, recording the values used in any feasible combination.
+/// Returns true if any combination was found.
+fn feasible(domains: &[Vec<Val>], remaining: Val, idx: usize,
+        chosen: &mut Vec<Val>, allowed: &mut [BTreeSet<Val>]) -> bool {
+    if idx >= domains.len() {
+        if remaining == 1 {
+            for (cell, &val) in chosen.iter().enumerate() {
+                allowed[cell].insert(val);
+            }
+            return true;
+        }
+
+        return false;
+    }
+
+    let mut found = false;
+    for &val in domains[idx].iter() {
+        if val == 0 || remaining % val != 0 {
+            continue;
+        }
+
+        chosen.push(val);
+        found = feasible(domains, remaining / val, idx + 1, chosen, allowed)
+                || found;
+        chosen.pop();
+    }
+
+    found
+}
+
+impl Constraint for MulCage {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let domains: Vec<Vec<Val>> = self.cells.iter().map(|&var|
+                if let Some(val) = search.get_assigned(var) {
+                    vec![val]
+                } else {
+                    search.get_unassigned(var).collect()
+                })
+            .collect();
+
+        let mut allowed = vec![BTreeSet::new(); self.cells.len()];
+        let mut chosen = Vec::with_capacity(self.cells.len());
+        if !feasible(&domains, self.total, 0, &mut chosen, &mut allowed) {
+            return Err(());
+        }
+
+        for (cell, &var) in self.cells.iter().enumerate() {
+            if !search.is_assigned(var) {
+                for &val in domains[cell].iter() {
+                    if !allowed[cell].contains(&val) {
+                        try!(search.remove_candidate(var, val));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        // Values may repeat, so merging two cells is fine.
+        let cells = self.cells.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(MulCage::new(self.total, cells)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+
+    #[test]
+    fn test_two_cell_twelve() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        puzzle.mul_cage(12, &vars);
+
+        // 12 = 2 * 6 = 3 * 4.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(vars[0]).collect::<Vec<Val>>(),
+                &[2,3,4,6]);
+        assert_eq!(search.get_unassigned(vars[1]).collect::<Vec<Val>>(),
+                &[2,3,4,6]);
+    }
+
+    #[test]
+    fn test_force_last_cell() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3,4,5,6]);
+
+        puzzle.mul_cage(36, &vars);
+        puzzle.set_value(vars[0], 2);
+        puzzle.set_value(vars[1], 3);
+
+        // The last cell is forced to 36 / (2 * 3).
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[vars[2]], 6);
+    }
+
+    #[test]
+    fn test_unify_repeated_cell() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        // 4 = 2 * 2: unifying the cells must keep the solution.
+        puzzle.mul_cage(4, &vars);
+        puzzle.unify(vars[0], vars[1]);
+
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0][vars[0]], 2);
+        assert_eq!(solutions[0][vars[1]], 2);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        puzzle.mul_cage(35, &vars);
+
+        // 35 = 5 * 7 needs a digit above six.
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,125 @@
+//! Renban implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Renban {
+    cells: Vec<VarToken>,
+}
+
+impl Renban {
+    /// Allocate a new Renban constraint.  The cells must take a set
+    /// of consecutive distinct values, in any order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle_solver::constraint::Renban::new(vars);
+    /// ```
+    pub fn new(cells: Vec<VarToken>) -> Self {
+        Renban {
+            cells: cells,
+        }
+    }
+}
+
+impl Constraint for Renban {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        // The cells are distinct.
+        for &var2 in self.cells.iter() {
+            if var2 != var {
+                try!(search.remove_candidate(var2, val));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        // All values lie in a window of cells.len() consecutive
+        // values, so each cell is within (len - 1) of every other
+        // cell's closest possible value.
+        let span = (self.cells.len() as Val) - 1;
+        let mut lo = Val::min_value();
+        let mut hi = Val::max_value();
+
+        for &var in self.cells.iter() {
+            let (min_val, max_val) = try!(search.get_min_max(var));
+            lo = ::std::cmp::max(lo, min_val - span);
+            hi = ::std::cmp::min(hi, max_val + span);
+        }
+
+        for &var in self.cells.iter() {
+            if let Some(val) = search.get_assigned(var) {
+                if val < lo || hi < val {
+                    return Err(());
+                }
+            } else {
+                try!(search.bound_candidate_range(var, lo, hi));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let cells = self.cells.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(Renban{ cells: cells }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+    use super::Renban;
+
+    #[test]
+    fn test_pinned_window() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[3]);
+        let v1 = puzzle.new_var_with_candidates(&[1]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+
+        puzzle.add_constraint(Renban::new(vec![v0,v1,v2]));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v2], 2);
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3,4]);
+        puzzle.renban(&vars);
+
+        // The windows 1-3 and 2-4, in any order.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 12);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[5]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+
+        puzzle.add_constraint(Renban::new(vec![v0,v1,v2]));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Restricted sum implementation.
+
+use std::collections::BTreeSet;
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct RestrictedSum {
+    cells: Vec<VarToken>,
+    allowed: Vec<Val>,
+    total: Val,
+}
+
+impl RestrictedSum {
+    /// Allocate a new Restricted Sum constraint.  Each cell must
+    /// take a value from the allowed set, and the sum of the cells
+    /// equals the total.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2,
+    ///         &[1,2,3,4,5]);
+    ///
+    /// puzzle_solver::constraint::RestrictedSum::new(vars, vec![1,3,5], 8);
+    /// ```
+    pub fn new(cells: Vec<VarToken>, allowed: Vec<Val>, total: Val) -> Self {
+        RestrictedSum {
+            cells: cells,
+            allowed: allowed,
+            total: total,
+        }
+    }
+}
+
+impl Constraint for RestrictedSum {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let n = self.cells.len();
+        let allowed: BTreeSet<Val> = self.allowed.iter().cloned().collect();
+
+        // The allowed candidates of each cell.
+        let mut domains: Vec<Vec<Val>> = Vec::with_capacity(n);
+        for &var in self.cells.iter() {
+            if let Some(val) = search.get_assigned(var) {
+                if !allowed.contains(&val) {
+                    return Err(());
+                }
+                domains.push(vec![val]);
+            } else {
+                domains.push(search.get_unassigned(var)
+                        .filter(|val| allowed.contains(val))
+                        .collect());
+            }
+        }
+
+        // The sums reachable by the cells before (after) each cell,
+        // for exact subset-sum feasibility.
+        let mut forward: Vec<BTreeSet<Val>> = Vec::with_capacity(n + 1);
+        forward.push(iter::once(0).collect());
+        for i in 0..n {
+            let sums = domains[i].iter()
+                .flat_map(|&val| forward[i].iter().map(move |&f| f + val))
+                .collect();
+            forward.push(sums);
+        }
+
+        let mut backward: Vec<BTreeSet<Val>> = vec![BTreeSet::new(); n + 1];
+        backward[n].insert(0);
+        for i in (0..n).rev() {
+            backward[i] = domains[i].iter()
+                .flat_map(|&val| backward[i + 1].iter().map(move |&b| b + val))
+                .collect();
+        }
+
+        // Keep only the values that participate in some combination
+        // hitting the total.
+        for (i, &var) in self.cells.iter().enumerate() {
+            let keep: BTreeSet<Val> = domains[i].iter()
+                .filter(|&&val| forward[i].iter().any(|&f|
+                        backward[i + 1].contains(&(self.total - f - val))))
+                .cloned()
+                .collect();
+
+            if search.is_assigned(var) {
+                if keep.is_empty() {
+                    return Err(());
+                }
+            } else {
+                let remove: Vec<Val> = search.get_unassigned(var)
+                    .filter(|val| !keep.contains(val))
+                    .collect();
+
+                for val in remove.into_iter() {
+                    try!(search.remove_candidate(var, val));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let cells = self.cells.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(RestrictedSum::new(cells, self.allowed.clone(),
+                self.total)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::RestrictedSum;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5]);
+        puzzle.add_constraint(RestrictedSum::new(vars.clone(),
+                vec![1,3,5], 8));
+
+        // Only 3 + 5 hits the total from the allowed values.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(vars[0]).collect::<Vec<Val>>(),
+                &[3,5]);
+        assert_eq!(search.get_unassigned(vars[1]).collect::<Vec<Val>>(),
+                &[3,5]);
+
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 2);
+    }
+
+    #[test]
+    fn test_forced() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5]);
+        puzzle.add_constraint(RestrictedSum::new(vars.clone(),
+                vec![1,2,5], 10));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[vars[0]], 5);
+        assert_eq!(search[vars[1]], 5);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3]);
+        puzzle.add_constraint(RestrictedSum::new(vars, vec![1,3], 5));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,166 @@
+//! Skyscraper implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Skyscraper {
+    line: Vec<VarToken>,
+    visible: Val,
+}
+
+/// The minimum number of visible values achievable given the
+/// (min, max) bounds of each position.  A position must be visible if
+/// even its smallest candidate is taller than everything before it.
+pub fn min_visible(bounds: &[(Val, Val)]) -> Val {
+    let mut count = 0;
+    let mut tallest = Val::min_value();
+
+    for &(min, max) in bounds.iter() {
+        if min > tallest {
+            count = count + 1;
+        }
+        tallest = ::std::cmp::max(tallest, max);
+    }
+
+    count
+}
+
+/// The maximum number of visible values achievable given the
+/// (min, max) bounds of each position.  A position may be visible if
+/// its tallest candidate is taller than the shortest possible prefix.
+pub fn max_visible(bounds: &[(Val, Val)]) -> Val {
+    let mut count = 0;
+    let mut tallest = Val::min_value();
+
+    for &(min, max) in bounds.iter() {
+        if max > tallest {
+            count = count + 1;
+        }
+        tallest = ::std::cmp::max(tallest, min);
+    }
+
+    count
+}
+
+impl Skyscraper {
+    /// Allocate a new Skyscraper constraint.  Looking along the line
+    /// from the front, exactly "visible" values can be seen; a value
+    /// is visible if it is taller than all of the values before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::Skyscraper::new(vars, 2);
+    /// ```
+    pub fn new(line: Vec<VarToken>, visible: Val) -> Self {
+        Skyscraper {
+            line: line,
+            visible: visible,
+        }
+    }
+}
+
+impl Constraint for Skyscraper {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.line.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let mut bounds = Vec::with_capacity(self.line.len());
+        for &var in self.line.iter() {
+            bounds.push(try!(search.get_min_max(var)));
+        }
+
+        if self.visible < min_visible(&bounds)
+                || self.visible > max_visible(&bounds) {
+            return Err(());
+        }
+
+        for (idx, &var) in self.line.iter().enumerate() {
+            if search.is_assigned(var) {
+                continue;
+            }
+
+            let saved = bounds[idx];
+            let mut remove = Vec::new();
+            for val in search.get_unassigned(var) {
+                bounds[idx] = (val, val);
+                if self.visible < min_visible(&bounds)
+                        || self.visible > max_visible(&bounds) {
+                    remove.push(val);
+                }
+            }
+            bounds[idx] = saved;
+
+            for val in remove.into_iter() {
+                try!(search.remove_candidate(var, val));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let line = self.line.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(Skyscraper{
+            line: line,
+            visible: self.visible,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+    use super::Skyscraper;
+
+    #[test]
+    fn test_clue_one_forces_tallest() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[2,4]);
+        let v1 = puzzle.new_var_with_candidates(&[3]);
+        let v2 = puzzle.new_var_with_candidates(&[1]);
+
+        puzzle.add_constraint(Skyscraper::new(vec![v0,v1,v2], 1));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v0], 4);
+    }
+
+    #[test]
+    fn test_all_visible() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2,3]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        puzzle.add_constraint(Skyscraper::new(vec![v0,v1,v2], 3));
+        puzzle.all_different(&[v0,v1,v2]);
+
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0][v0], 1);
+        assert_eq!(solutions[0][v1], 2);
+        assert_eq!(solutions[0][v2], 3);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[3]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2]);
+
+        puzzle.add_constraint(Skyscraper::new(vec![v0,v1,v2], 2));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
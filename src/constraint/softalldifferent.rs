@@ -0,0 +1,133 @@
+//! Soft all different implementation.
+
+use std::collections::BTreeSet;
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct SoftAllDifferent {
+    vars: Vec<VarToken>,
+    violations: VarToken,
+}
+
+impl SoftAllDifferent {
+    /// Allocate a new Soft All Different constraint.  The variables
+    /// may repeat values, and the violations variable counts the
+    /// pairs (i, j) where vars[i] == vars[j].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2]);
+    /// let violations = puzzle.new_var_with_candidates(&[0,1,2,3]);
+    ///
+    /// puzzle_solver::constraint::SoftAllDifferent::new(vars, violations);
+    /// ```
+    pub fn new(vars: Vec<VarToken>, violations: VarToken) -> Self {
+        SoftAllDifferent {
+            vars: vars,
+            violations: violations,
+        }
+    }
+}
+
+impl Constraint for SoftAllDifferent {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.vars.iter().chain(iter::once(&self.violations)))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let sets: Vec<BTreeSet<Val>> = self.vars.iter().map(|&var|
+                if let Some(val) = search.get_assigned(var) {
+                    iter::once(val).collect()
+                } else {
+                    search.get_unassigned(var).collect()
+                })
+            .collect();
+
+        // The violations are bounded below by the pairs already
+        // forced equal, and above by the pairs that can still take a
+        // common value.
+        let mut min_violations = 0;
+        let mut max_violations = 0;
+
+        for i in 0..self.vars.len() {
+            for j in (i + 1)..self.vars.len() {
+                if !sets[i].is_disjoint(&sets[j]) {
+                    max_violations = max_violations + 1;
+
+                    if sets[i].len() == 1 && sets[j].len() == 1 {
+                        min_violations = min_violations + 1;
+                    }
+                }
+            }
+        }
+
+        if let Some(val) = search.get_assigned(self.violations) {
+            if val < min_violations || max_violations < val {
+                return Err(());
+            }
+        } else {
+            try!(search.bound_candidate_range(self.violations,
+                    min_violations, max_violations));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |&var| if var == from { to } else { var };
+        Ok(Rc::new(SoftAllDifferent{
+            vars: self.vars.iter().map(&subst).collect(),
+            violations: subst(&self.violations),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+    use super::SoftAllDifferent;
+
+    #[test]
+    fn test_forced_violation() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[1]);
+        let violations = puzzle.new_var_with_candidates(&[0,1,2,3]);
+
+        puzzle.add_constraint(SoftAllDifferent::new(vec![v0,v1], violations));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[violations], 1);
+    }
+
+    #[test]
+    fn test_no_violation() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1,2]);
+        let v1 = puzzle.new_var_with_candidates(&[3,4]);
+        let violations = puzzle.new_var_with_candidates(&[0,1]);
+
+        puzzle.add_constraint(SoftAllDifferent::new(vec![v0,v1], violations));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[violations], 0);
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+        let violations = puzzle.new_var_with_candidates(&[0,1]);
+
+        puzzle.add_constraint(SoftAllDifferent::new(vars, violations));
+
+        // (1,1,1), (1,2,0), (2,1,0), (2,2,1).
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 4);
+    }
+}
@@ -0,0 +1,203 @@
+//! Star Battle implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct StarBattle {
+    grid: Vec<Vec<VarToken>>,
+    regions: Vec<Vec<(usize, usize)>>,
+    k: usize,
+}
+
+impl StarBattle {
+    /// Allocate a new Star Battle constraint over a grid of boolean
+    /// (0 = empty, 1 = star) variables.  Every row, column, and
+    /// region contains exactly k stars, and no two stars are in
+    /// adjacent cells, including diagonally.
+    ///
+    /// The regions are lists of (y, x) cell positions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let grid = puzzle.new_vars_with_candidates_2d(4, 4, &[0,1]);
+    /// let regions = vec![
+    ///     vec![ (0,0), (0,1), (1,0), (1,1) ],
+    ///     vec![ (0,2), (0,3), (1,2), (1,3) ],
+    ///     vec![ (2,0), (2,1), (3,0), (3,1) ],
+    ///     vec![ (2,2), (2,3), (3,2), (3,3) ] ];
+    ///
+    /// puzzle_solver::constraint::StarBattle::new(grid, regions, 1);
+    /// ```
+    pub fn new(grid: Vec<Vec<VarToken>>, regions: Vec<Vec<(usize, usize)>>,
+            k: usize) -> Self {
+        StarBattle {
+            grid: grid,
+            regions: regions,
+            k: k,
+        }
+    }
+
+    /// Require the group of cells to contain exactly k stars.
+    fn constrain_group<I>(&self, search: &mut PuzzleSearch, vars: I)
+            -> PsResult<()>
+            where I: Iterator<Item=VarToken> {
+        let mut stars = 0;
+        let mut undecided = Vec::new();
+
+        for var in vars {
+            match search.get_assigned(var) {
+                Some(1) => stars = stars + 1,
+                Some(_) => (),
+                None => undecided.push(var),
+            }
+        }
+
+        if stars > self.k || stars + undecided.len() < self.k {
+            return Err(());
+        } else if stars == self.k {
+            for var in undecided.into_iter() {
+                try!(search.remove_candidate(var, 1));
+            }
+        } else if stars + undecided.len() == self.k {
+            for var in undecided.into_iter() {
+                try!(search.set_candidate(var, 1));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Constraint for StarBattle {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.grid.iter().flat_map(|row| row.iter()))
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        if val != 1 {
+            return Ok(());
+        }
+
+        // No two stars are adjacent, including diagonally.
+        let height = self.grid.len() as isize;
+        let width = self.grid[0].len() as isize;
+
+        for y in 0..self.grid.len() {
+            for x in 0..self.grid[y].len() {
+                if self.grid[y][x] != var {
+                    continue;
+                }
+
+                for dy in -1..2 as isize {
+                    for dx in -1..2 as isize {
+                        let (y2, x2) = (y as isize + dy, x as isize + dx);
+                        if (dy, dx) != (0, 0)
+                                && 0 <= y2 && y2 < height
+                                && 0 <= x2 && x2 < width {
+                            try!(search.remove_candidate(
+                                    self.grid[y2 as usize][x2 as usize], 1));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let height = self.grid.len();
+        let width = self.grid[0].len();
+
+        for y in 0..height {
+            let vars: Vec<VarToken> = self.grid[y].clone();
+            try!(self.constrain_group(search, vars.into_iter()));
+        }
+
+        for x in 0..width {
+            let vars: Vec<VarToken> = (0..height).map(|y| self.grid[y][x])
+                .collect();
+            try!(self.constrain_group(search, vars.into_iter()));
+        }
+
+        for region in self.regions.iter() {
+            let vars: Vec<VarToken> = region.iter()
+                .map(|&(y, x)| self.grid[y][x])
+                .collect();
+            try!(self.constrain_group(search, vars.into_iter()));
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let grid = self.grid.iter()
+            .map(|row| row.iter()
+                 .map(|&var| if var == from { to } else { var })
+                 .collect())
+            .collect();
+        Ok(Rc::new(StarBattle{
+            grid: grid,
+            regions: self.regions.clone(),
+            k: self.k,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+    use super::StarBattle;
+
+    fn quad_regions() -> Vec<Vec<(usize, usize)>> {
+        vec![
+            vec![ (0,0), (0,1), (1,0), (1,1) ],
+            vec![ (0,2), (0,3), (1,2), (1,3) ],
+            vec![ (2,0), (2,1), (3,0), (3,1) ],
+            vec![ (2,2), (2,3), (3,2), (3,3) ] ]
+    }
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let grid = puzzle.new_vars_with_candidates_2d(4, 4, &[0,1]);
+        puzzle.set_value(grid[0][0], 1);
+        puzzle.add_constraint(StarBattle::new(grid.clone(), quad_regions(), 1));
+
+        let search = puzzle.step().expect("contradiction");
+
+        // The rest of the row, column, region, and the adjacent
+        // cells are all empty.
+        assert_eq!(search[grid[0][3]], 0);
+        assert_eq!(search[grid[3][0]], 0);
+        assert_eq!(search[grid[1][1]], 0);
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let grid = puzzle.new_vars_with_candidates_2d(4, 4, &[0,1]);
+        puzzle.add_constraint(StarBattle::new(grid, quad_regions(), 1));
+
+        // The non-adjacent permutation matrices (1,3,0,2), (2,0,3,1).
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 2);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let grid = puzzle.new_vars_with_candidates_2d(4, 4, &[0,1]);
+        puzzle.set_value(grid[0][0], 1);
+        puzzle.set_value(grid[1][1], 1);
+        puzzle.add_constraint(StarBattle::new(grid, quad_regions(), 1));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,150 @@
+//! Subtraction cage (kenken) implementation.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct SubCage {
+    result: Val,
+    cells: [VarToken; 2],
+}
+
+impl SubCage {
+    /// Allocate a new SubCage constraint: |a - b| = result, as in
+    /// kenken subtraction cages (the clue does not say which cell is
+    /// larger).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+    ///
+    /// puzzle_solver::constraint::SubCage::new(5, vars[0], vars[1]);
+    /// ```
+    pub fn new(result: Val, a: VarToken, b: VarToken) -> Self {
+        SubCage {
+            result: result,
+            cells: [a, b],
+        }
+    }
+}
+
+impl Constraint for SubCage {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let domains: Vec<Vec<Val>> = self.cells.iter().map(|&var|
+                if let Some(val) = search.get_assigned(var) {
+                    vec![val]
+                } else {
+                    search.get_unassigned(var).collect()
+                })
+            .collect();
+
+        // Prune each cell to the values supported by some pair.
+        let mut allowed = vec![BTreeSet::new(); 2];
+        for &a in domains[0].iter() {
+            for &b in domains[1].iter() {
+                if (a - b).abs() == self.result {
+                    allowed[0].insert(a);
+                    allowed[1].insert(b);
+                }
+            }
+        }
+
+        if allowed[0].is_empty() {
+            return Err(());
+        }
+
+        for (cell, &var) in self.cells.iter().enumerate() {
+            if !search.is_assigned(var) {
+                for &val in domains[cell].iter() {
+                    if !allowed[cell].contains(&val) {
+                        try!(search.remove_candidate(var, val));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        // Keep a duplicated token if the cells are merged: the pair
+        // enumeration rejects |a - a| = result itself unless the
+        // result is zero.
+        let subst = |var| if var == from { to } else { var };
+        Ok(Rc::new(SubCage{
+            result: self.result,
+            cells: [subst(self.cells[0]), subst(self.cells[1])],
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+
+    #[test]
+    fn test_two_cell_five() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        puzzle.sub_cage(5, vars[0], vars[1]);
+
+        // 5 = |1 - 6| only.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(vars[0]).collect::<Vec<Val>>(),
+                &[1,6]);
+        assert_eq!(search.get_unassigned(vars[1]).collect::<Vec<Val>>(),
+                &[1,6]);
+    }
+
+    #[test]
+    fn test_assigned_cell() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        puzzle.sub_cage(3, vars[0], vars[1]);
+        puzzle.set_value(vars[0], 2);
+
+        // |2 - b| = 3 forces b = 5.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[vars[1]], 5);
+    }
+
+    #[test]
+    fn test_unify_merged_cells() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        // |a - a| = 0 holds for every candidate.
+        puzzle.sub_cage(0, vars[0], vars[1]);
+        puzzle.unify(vars[0], vars[1]);
+        assert_eq!(puzzle.solve_all().len(), 6);
+
+        // ... but a non-zero result is a contradiction.
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+        puzzle.sub_cage(2, vars[0], vars[1]);
+        puzzle.unify(vars[0], vars[1]);
+        assert_eq!(puzzle.solve_all().len(), 0);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+
+        // The largest difference over 1..6 is 5.
+        puzzle.sub_cage(6, vars[0], vars[1]);
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,146 @@
+//! Sum parity implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,LinExpr,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct SumParity {
+    // The expression whose value must have the required parity.
+    expr: LinExpr,
+    even: bool,
+}
+
+impl SumParity {
+    /// Allocate a new Sum Parity constraint, enforcing that the
+    /// value of the expression is even (or odd).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4]);
+    ///
+    /// puzzle_solver::constraint::SumParity::new(vars[0] + vars[1], true);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression has a non-integer constant or
+    /// coefficient.
+    pub fn new(expr: LinExpr, even: bool) -> Self {
+        assert!(expr.constant.is_integer());
+        assert!(expr.coef.values().all(|c| c.is_integer()));
+        SumParity {
+            expr: expr,
+            even: even,
+        }
+    }
+}
+
+impl Constraint for SumParity {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.expr.coef.keys())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let target: Val = if self.even { 0 } else { 1 };
+        let mut parity = self.expr.constant.to_integer() & 1;
+        let mut unassigned_var = None;
+
+        for (&var, &coef) in self.expr.coef.iter() {
+            if let Some(val) = search.get_assigned(var) {
+                parity = parity ^ (coef.to_integer() & val & 1);
+            } else {
+                // If we find more than one unassigned variable,
+                // the parity is not yet determined.
+                if unassigned_var.is_some() {
+                    return Ok(());
+                } else {
+                    unassigned_var = Some((var, coef));
+                }
+            }
+        }
+
+        match unassigned_var {
+            None => {
+                if parity != target {
+                    return Err(());
+                }
+            },
+
+            Some((var, coef)) => {
+                if coef.to_integer() & 1 == 0 {
+                    // The last variable cannot change the parity.
+                    if parity != target {
+                        return Err(());
+                    }
+                } else {
+                    // Keep only the candidates with the parity that
+                    // completes the sum.
+                    let want = parity ^ target;
+                    let remove: Vec<Val> = search.get_unassigned(var)
+                        .filter(|&val| val & 1 != want)
+                        .collect();
+
+                    for val in remove.into_iter() {
+                        try!(search.remove_candidate(var, val));
+                    }
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let mut expr = self.expr.clone();
+        if let Some(coef) = expr.coef.remove(&from) {
+            expr = expr + coef * to;
+        }
+
+        Ok(Rc::new(SumParity{ expr: expr, even: self.even }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::SumParity;
+
+    #[test]
+    fn test_even_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4]);
+
+        puzzle.add_constraint(SumParity::new(v0 + v1, true));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[1,3]);
+    }
+
+    #[test]
+    fn test_odd_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[2]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4]);
+
+        puzzle.sum_odd(v0 + v1);
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[1,3]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[1]);
+        let v1 = puzzle.new_var_with_candidates(&[2]);
+
+        puzzle.sum_even(v0 + v1);
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,75 @@
+//! Upper bound implementation.
+
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct UpperBound {
+    var: VarToken,
+    max: Val,
+}
+
+impl UpperBound {
+    /// Allocate a new Upper Bound constraint, removing all candidates
+    /// greater than the maximum from the variable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2,3,4]);
+    ///
+    /// puzzle_solver::constraint::UpperBound::new(var, 2);
+    /// ```
+    pub fn new(var: VarToken, max: Val) -> Self {
+        UpperBound {
+            var: var,
+            max: max,
+        }
+    }
+}
+
+impl Constraint for UpperBound {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(iter::once(&self.var))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        try!(search.bound_candidate_range(self.var, Val::min_value(),
+                self.max));
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let var = if self.var == from { to } else { self.var };
+        Ok(Rc::new(UpperBound::new(var, self.max)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::UpperBound;
+
+    #[test]
+    fn test_elimination() {
+        let mut puzzle = Puzzle::new();
+        let var = puzzle.new_var_with_candidates(&[1,2,3,4]);
+        puzzle.add_constraint(UpperBound::new(var, 2));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(var).collect::<Vec<Val>>(), &[1,2]);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let var = puzzle.new_var_with_candidates(&[3,4]);
+        puzzle.add_constraint(UpperBound::new(var, 2));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! View count implementation.
+
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,VarToken};
+use super::Skyscraper;
+use super::skyscraper::{max_visible,min_visible};
+
+pub struct ViewCount {
+    line: Vec<VarToken>,
+    clue: VarToken,
+}
+
+impl ViewCount {
+    /// Allocate a new View Count constraint.  Looking along the line
+    /// from the front, the number of visible values equals the clue
+    /// variable; a value is visible if it is taller than all of the
+    /// values before it.
+    ///
+    /// Unlike Skyscraper, the clue is itself a puzzle variable and
+    /// may be inferred from the line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    /// let clue = puzzle.new_var_with_candidates(&[1,2,3]);
+    ///
+    /// puzzle_solver::constraint::ViewCount::new(vars, clue);
+    /// ```
+    pub fn new(vars: Vec<VarToken>, clue_var: VarToken) -> Self {
+        ViewCount {
+            line: vars,
+            clue: clue_var,
+        }
+    }
+}
+
+impl Constraint for ViewCount {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(iter::once(&self.clue).chain(&self.line))
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        let mut bounds = Vec::with_capacity(self.line.len());
+        for &var in self.line.iter() {
+            bounds.push(try!(search.get_min_max(var)));
+        }
+
+        match search.get_assigned(self.clue) {
+            Some(visible) => {
+                if visible < min_visible(&bounds)
+                        || visible > max_visible(&bounds) {
+                    return Err(());
+                }
+
+                // The clue is known; delegate to the Skyscraper
+                // propagation.
+                Skyscraper::new(self.line.clone(), visible)
+                    .on_updated(search)
+            },
+
+            None => {
+                try!(search.bound_candidate_range(self.clue,
+                        min_visible(&bounds), max_visible(&bounds)));
+                Ok(())
+            },
+        }
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |&var| if var == from { to } else { var };
+        Ok(Rc::new(ViewCount{
+            line: self.line.iter().map(&subst).collect(),
+            clue: subst(&self.clue),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::ViewCount;
+
+    #[test]
+    fn test_infer_clue_bounds() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[2,4]);
+        let v1 = puzzle.new_var_with_candidates(&[3]);
+        let v2 = puzzle.new_var_with_candidates(&[1]);
+        let clue = puzzle.new_var_with_candidates(&[1,2,3]);
+
+        puzzle.add_constraint(ViewCount::new(vec![v0,v1,v2], clue));
+
+        // At most the first two positions can be visible.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(clue).collect::<Vec<Val>>(), &[1,2]);
+    }
+
+    #[test]
+    fn test_assigned_clue_delegates() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[2,4]);
+        let v1 = puzzle.new_var_with_candidates(&[3]);
+        let v2 = puzzle.new_var_with_candidates(&[1]);
+        let clue = puzzle.new_var_with_candidates(&[1]);
+
+        puzzle.add_constraint(ViewCount::new(vec![v0,v1,v2], clue));
+
+        // Only one building visible: the first must be the tallest.
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v0], 4);
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+        let clue = puzzle.new_var_with_candidates(&[3]);
+
+        puzzle.all_different(&vars);
+        puzzle.add_constraint(ViewCount::new(vars.clone(), clue));
+
+        // All three visible: the line must be ascending.
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0][vars[0]], 1);
+        assert_eq!(solutions[0][vars[1]], 2);
+        assert_eq!(solutions[0][vars[2]], 3);
+    }
+}
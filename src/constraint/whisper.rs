@@ -0,0 +1,118 @@
+//! Whisper implementation.
+
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Whisper {
+    cells: Vec<VarToken>,
+    min_diff: Val,
+}
+
+impl Whisper {
+    /// Allocate a new Whisper constraint.  Consecutive cells along
+    /// the line must differ by at least min_diff, as in German
+    /// whisper lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle_solver::constraint::Whisper::new(vars, 5);
+    /// ```
+    pub fn new(cells: Vec<VarToken>, min_diff: Val) -> Self {
+        Whisper {
+            cells: cells,
+            min_diff: min_diff,
+        }
+    }
+}
+
+impl Constraint for Whisper {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(self.cells.iter())
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, var: VarToken, val: Val)
+            -> PsResult<()> {
+        for idx in 0..self.cells.len() {
+            if self.cells[idx] != var {
+                continue;
+            }
+
+            // The neighbouring cells must avoid the band of values
+            // less than min_diff away.
+            for idx2 in [idx.wrapping_sub(1), idx + 1].iter()
+                    .filter(|&&idx2| idx2 < self.cells.len()) {
+                for band in (val - self.min_diff + 1)..(val + self.min_diff) {
+                    try!(search.remove_candidate(self.cells[*idx2], band));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let cells = self.cells.iter()
+            .map(|&var| if var == from { to } else { var })
+            .collect();
+        Ok(Rc::new(Whisper{ cells: cells, min_diff: self.min_diff }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::{Puzzle,Val};
+    use super::Whisper;
+
+    #[test]
+    fn test_band_elimination() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[4]);
+        let v1 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        let v2 = puzzle.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+
+        puzzle.add_constraint(Whisper::new(vec![v0,v1,v2], 5));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[v1], 9);
+        assert_eq!(search.get_unassigned(v2).collect::<Vec<Val>>(), &[1,2,3,4]);
+    }
+
+    #[test]
+    fn test_alternation() {
+        let mut puzzle = Puzzle::new();
+        let vars = puzzle.new_vars_with_candidates_1d(4,
+                &[1,2,3,4,5,6,7,8,9]);
+        puzzle.whisper(&vars, 5);
+        puzzle.set_value(vars[0], 1);
+
+        // With min_diff = 5 on a 1..9 line, the values must alternate
+        // between the low band 1..4 and the high band 6..9.
+        let solutions = puzzle.solve_all();
+        assert!(!solutions.is_empty());
+        for dict in solutions.iter() {
+            for pair in vars.windows(2) {
+                assert!((dict[pair[0]] - dict[pair[1]]).abs() >= 5);
+                assert!((dict[pair[0]] <= 4) != (dict[pair[1]] <= 4));
+            }
+        }
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let v0 = puzzle.new_var_with_candidates(&[4]);
+        let v1 = puzzle.new_var_with_candidates(&[5,6,7,8]);
+
+        puzzle.add_constraint(Whisper::new(vec![v0,v1], 5));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+}
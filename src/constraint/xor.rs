@@ -0,0 +1,155 @@
+//! Xor implementation.
+
+use std::iter;
+use std::rc::Rc;
+
+use ::{Constraint,PsResult,PuzzleSearch,Val,VarToken};
+
+pub struct Xor {
+    a: VarToken,
+    b: VarToken,
+    result: VarToken,
+}
+
+impl Xor {
+    /// Allocate a new Xor constraint over boolean (0 or 1)
+    /// variables, enforcing result = a ^ b.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let a = puzzle.new_var_with_candidates(&[0,1]);
+    /// let b = puzzle.new_var_with_candidates(&[0,1]);
+    /// let result = puzzle.new_var_with_candidates(&[0,1]);
+    ///
+    /// puzzle_solver::constraint::Xor::new(a, b, result);
+    /// ```
+    pub fn new(a: VarToken, b: VarToken, result: VarToken) -> Self {
+        Xor {
+            a: a,
+            b: b,
+            result: result,
+        }
+    }
+}
+
+impl Constraint for Xor {
+    fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+        Box::new(iter::once(&self.a)
+                .chain(iter::once(&self.b))
+                .chain(iter::once(&self.result)))
+    }
+
+    fn on_assigned(&self, search: &mut PuzzleSearch, _: VarToken, _: Val)
+            -> PsResult<()> {
+        // Any two assigned variables imply the third.
+        match (search.get_assigned(self.a),
+               search.get_assigned(self.b),
+               search.get_assigned(self.result)) {
+            (Some(a), Some(b), _) =>
+                try!(search.set_candidate(self.result, a ^ b)),
+            (Some(a), _, Some(result)) =>
+                try!(search.set_candidate(self.b, a ^ result)),
+            (_, Some(b), Some(result)) =>
+                try!(search.set_candidate(self.a, b ^ result)),
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+        for &var in [self.a, self.b, self.result].iter() {
+            if !search.is_assigned(var) {
+                try!(search.bound_candidate_range(var, 0, 1));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn substitute(&self, from: VarToken, to: VarToken)
+            -> PsResult<Rc<Constraint>> {
+        let subst = |var| if var == from { to } else { var };
+        Ok(Rc::new(Xor{
+            a: subst(self.a),
+            b: subst(self.b),
+            result: subst(self.result),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::Puzzle;
+    use super::Xor;
+
+    #[test]
+    fn test_implies_result() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[1]);
+        let b = puzzle.new_var_with_candidates(&[0]);
+        let result = puzzle.new_var_with_candidates(&[0,1]);
+
+        puzzle.add_constraint(Xor::new(a, b, result));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[result], 1);
+    }
+
+    #[test]
+    fn test_implies_a() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[0,1]);
+        let b = puzzle.new_var_with_candidates(&[1]);
+        let result = puzzle.new_var_with_candidates(&[1]);
+
+        puzzle.add_constraint(Xor::new(a, b, result));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[a], 0);
+    }
+
+    #[test]
+    fn test_implies_b() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[1]);
+        let b = puzzle.new_var_with_candidates(&[0,1]);
+        let result = puzzle.new_var_with_candidates(&[0]);
+
+        puzzle.add_constraint(Xor::new(a, b, result));
+
+        let search = puzzle.step().expect("contradiction");
+        assert_eq!(search[b], 1);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[1]);
+        let b = puzzle.new_var_with_candidates(&[1]);
+        let result = puzzle.new_var_with_candidates(&[1]);
+
+        puzzle.add_constraint(Xor::new(a, b, result));
+
+        let search = puzzle.step();
+        assert!(search.is_none());
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut puzzle = Puzzle::new();
+        let a = puzzle.new_var_with_candidates(&[0,1]);
+        let b = puzzle.new_var_with_candidates(&[0,1]);
+        let result = puzzle.new_var_with_candidates(&[0,1]);
+
+        puzzle.boolean_xor(a, b, result);
+
+        let solutions = puzzle.solve_all();
+        assert_eq!(solutions.len(), 4);
+        for dict in solutions.iter() {
+            assert_eq!(dict[result], dict[a] ^ dict[b]);
+        }
+    }
+}
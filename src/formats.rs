@@ -0,0 +1,159 @@
+//! Importers for common text-based puzzle formats.
+//!
+//! Puzzle data often arrives as plain text: 81-character sudoku
+//! strings, rows of 0/1/? for takuzu, and so on.  A `GridSpec` maps
+//! the characters to candidate values, and `parse_grid` turns the
+//! text into a grid of optional values, ready to feed to
+//! `Puzzle::set_values_2d`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use ::Val;
+
+/// A specification of a character grid.
+pub struct GridSpec {
+    // Map from character to its value.
+    values: HashMap<char, Val>,
+
+    // The characters standing for an empty cell.
+    blanks: Vec<char>,
+
+    // Decorative characters to be skipped, e.g. block separators.
+    separators: Vec<char>,
+}
+
+/// An error while parsing a character grid.
+#[derive(Debug,Eq,PartialEq)]
+pub struct ParseError {
+    /// The line of the unexpected character, counting from 1.
+    pub line: usize,
+
+    /// The column of the unexpected character, counting from 1.
+    pub column: usize,
+
+    /// The unexpected character.
+    pub ch: char,
+}
+
+impl GridSpec {
+    /// Allocate a new grid specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// puzzle_solver::formats::GridSpec::new('?', &[('0',0), ('1',1)]);
+    /// ```
+    pub fn new(blank: char, values: &[(char, Val)]) -> Self {
+        GridSpec {
+            values: values.iter().cloned().collect(),
+            blanks: vec![blank],
+            separators: Vec::new(),
+        }
+    }
+
+    /// The standard sudoku specification: the digits 1-9, with "."
+    /// or "0" for an empty cell.
+    pub fn sudoku() -> Self {
+        let values = (1..10).map(|n|
+                (::std::char::from_digit(n as u32, 10).unwrap(), n));
+        GridSpec {
+            values: values.collect(),
+            blanks: vec!['.', '0'],
+            separators: Vec::new(),
+        }
+    }
+
+    /// Add decorative characters to be skipped, e.g. "|-+" for
+    /// drawn block separators.
+    pub fn separators(mut self, separators: &str) -> Self {
+        self.separators.extend(separators.chars());
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unexpected character {:?} at line {}, column {}",
+                self.ch, self.line, self.column)
+    }
+}
+
+/// Parse a character grid, one row per line.
+///
+/// Whitespace and separator characters are skipped, and blank lines
+/// are ignored.  The blank character parses as None.
+///
+/// # Examples
+///
+/// ```
+/// use puzzle_solver::formats::{GridSpec,parse_grid};
+///
+/// let spec = GridSpec::new('.', &[('0',0), ('1',1)]);
+/// let grid = parse_grid("01.\n.10\n", &spec).unwrap();
+/// assert_eq!(grid[0], vec![Some(0), Some(1), None]);
+/// assert_eq!(grid[1], vec![None, Some(1), Some(0)]);
+/// ```
+pub fn parse_grid(src: &str, spec: &GridSpec)
+        -> Result<Vec<Vec<Option<Val>>>, ParseError> {
+    let mut grid = Vec::new();
+
+    for (row, line) in src.lines().enumerate() {
+        let mut cells = Vec::new();
+
+        for (col, ch) in line.chars().enumerate() {
+            if ch.is_whitespace() || spec.separators.contains(&ch) {
+                continue;
+            } else if spec.blanks.contains(&ch) {
+                cells.push(None);
+            } else if let Some(&val) = spec.values.get(&ch) {
+                cells.push(Some(val));
+            } else {
+                return Err(ParseError {
+                    line: row + 1,
+                    column: col + 1,
+                    ch: ch,
+                });
+            }
+        }
+
+        if !cells.is_empty() {
+            grid.push(cells);
+        }
+    }
+
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GridSpec,parse_grid};
+
+    #[test]
+    fn test_takuzu() {
+        let spec = GridSpec::new('?', &[('0',0), ('1',1)]);
+        let grid = parse_grid("0?1\n\n?1?\n", &spec).expect("parse");
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid[0], vec![Some(0), None, Some(1)]);
+        assert_eq!(grid[1], vec![None, Some(1), None]);
+    }
+
+    #[test]
+    fn test_separators() {
+        let spec = GridSpec::new('.', &[('1',1), ('2',2)]).separators("|");
+        let grid = parse_grid("1|.\n.|2\n", &spec).expect("parse");
+        assert_eq!(grid[0], vec![Some(1), None]);
+        assert_eq!(grid[1], vec![None, Some(2)]);
+    }
+
+    #[test]
+    fn test_malformed() {
+        let spec = GridSpec::new('.', &[('1',1)]);
+        let err = parse_grid("11.\n.1x\n", &spec).expect_err("parse");
+        assert_eq!(err.line, 2);
+        assert_eq!(err.column, 3);
+        assert_eq!(err.ch, 'x');
+        assert_eq!(err.to_string(),
+                "unexpected character 'x' at line 2, column 3");
+    }
+}
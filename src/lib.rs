@@ -4,17 +4,36 @@
 extern crate bit_set;
 extern crate num_rational;
 extern crate num_traits;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "rand")]
+extern crate rand;
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::io;
 use std::ops;
 use num_rational::Rational32;
 
+pub use constraint::ConsistencyLevel;
 pub use constraint::Constraint;
+pub use puzzle::DifficultyReport;
+pub use puzzle::GroupId;
+pub use puzzle::Hint;
+pub use puzzle::HintKind;
+pub use puzzle::Metric;
+pub use puzzle::OptimizeOutcome;
+pub use puzzle::ProbeResult;
 pub use puzzle::Puzzle;
 pub use puzzle::PuzzleSearch;
+pub use puzzle::SearchStats;
+pub use puzzle::SolutionCount;
+pub use puzzle::SolvedSearch;
+pub use puzzle::Solver;
+pub use puzzle::SolverStatus;
+pub use puzzle::StopReason;
 
 /// A puzzle variable token.
-#[derive(Copy,Clone,Debug,Eq,Hash,PartialEq)]
+#[derive(Copy,Clone,Debug,Eq,Hash,Ord,PartialEq,PartialOrd)]
 pub struct VarToken(usize);
 
 /// The type of a puzzle variable's value (i.e. the candidate type).
@@ -35,23 +54,245 @@ pub struct LinExpr {
     // The non-zero coefficients in the linear expression.  If, after
     // some manipulations, the coefficient is 0, then it must be
     // removed from the map.
-    coef: HashMap<VarToken, Coef>,
+    coef: BTreeMap<VarToken, Coef>,
 }
 
 /// A result during a puzzle solution search (Err = contradiction).
 pub type PsResult<T> = Result<T, ()>;
 
 /// A dictionary mapping puzzle variables to the solution value.
-#[derive(Debug)]
+#[derive(Debug,Eq,Hash,PartialEq)]
 pub struct Solution {
     vars: Vec<Val>,
 }
 
 pub mod constraint;
+pub mod formats;
+#[cfg(feature = "proptest")]
+pub mod strategy;
 
 mod linexpr;
 mod puzzle;
 
+/// Write a set of solutions in CSV form, one row per solution.
+///
+/// The selected variables are written in the given order, prefixed by
+/// a header row.  Fields are separated by commas and rows are
+/// terminated by a single newline.
+///
+/// # Examples
+///
+/// ```
+/// let mut puzzle = puzzle_solver::Puzzle::new();
+/// let x = puzzle.new_var_with_candidates(&[1,2]);
+///
+/// let solutions = puzzle.solve_all();
+/// let mut out = Vec::new();
+/// puzzle_solver::write_csv(&solutions, &[x], &["x"], &mut out).unwrap();
+/// assert_eq!(out, b"x\n1\n2\n");
+/// ```
+///
+/// # Panics
+///
+/// Panics if the number of headers differs from the number of
+/// variables.
+pub fn write_csv<W: io::Write>(solutions: &[Solution],
+        vars: &[VarToken], headers: &[&str], writer: &mut W)
+        -> io::Result<()> {
+    assert_eq!(vars.len(), headers.len());
+    try!(write_csv_row(headers.iter(), writer));
+
+    for solution in solutions.iter() {
+        try!(write_csv_row(vars.iter().map(|&var| solution[var]), writer));
+    }
+
+    Ok(())
+}
+
+/// Write a set of solutions in CSV form, including every variable.
+///
+/// The header names are generated from the variable indices:
+/// "var0", "var1", ...
+pub fn write_csv_all<W: io::Write>(solutions: &[Solution], writer: &mut W)
+        -> io::Result<()> {
+    let num_vars = solutions.iter().map(|s| s.vars.len()).max().unwrap_or(0);
+    let headers: Vec<String> = (0..num_vars)
+        .map(|idx| format!("var{}", idx))
+        .collect();
+    let vars: Vec<VarToken> = (0..num_vars).map(VarToken).collect();
+    let headers: Vec<&str> = headers.iter().map(|s| &s[..]).collect();
+    write_csv(solutions, &vars, &headers, writer)
+}
+
+/// Build a puzzle with `n` variables over the candidates `1..=n`,
+/// all different: its solutions are the permutations of `1..=n`.
+///
+/// # Examples
+///
+/// ```
+/// let (mut puzzle, vars) = puzzle_solver::permutation_puzzle(3);
+/// assert_eq!(vars.len(), 3);
+/// assert_eq!(puzzle.solve_all().len(), 6);
+/// ```
+pub fn permutation_puzzle(n: usize) -> (Puzzle, Vec<VarToken>) {
+    let mut puzzle = Puzzle::new();
+    let candidates: Vec<Val> = (1..=n as Val).collect();
+    let vars = puzzle.new_vars_with_candidates_1d(n, &candidates);
+    puzzle.all_different(&vars);
+    (puzzle, vars)
+}
+
+fn write_csv_row<T, I, W>(fields: I, writer: &mut W) -> io::Result<()>
+        where T: ToString, I: Iterator<Item=T>, W: io::Write {
+    let mut sep = "";
+    for field in fields {
+        try!(write!(writer, "{}{}", sep, field.to_string()));
+        sep = ",";
+    }
+    writeln!(writer, "")
+}
+
+impl Solution {
+    /// Hash the solution's value vector in variable-token order.
+    ///
+    /// The hash (FNV-1a) is stable across runs and processes — it
+    /// does not use `RandomState` — so it is suitable for comparing
+    /// solutions across libraries or sessions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1]);
+    ///
+    /// let solution = puzzle.solve_any().unwrap();
+    /// assert_eq!(solution.canonical_hash(), solution.canonical_hash());
+    /// ```
+    pub fn canonical_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for &val in self.vars.iter() {
+            for &byte in (val as u32).to_le_bytes().iter() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
+    }
+
+    /// Render the solution using a template.
+    ///
+    /// The template references variables by the names registered on
+    /// the puzzle (see `Puzzle::name_var` and `Puzzle::name_grid`):
+    /// "{x}" is replaced by the value of the variable named "x", and
+    /// "{grid[r][c]}" by the value of the given cell of the grid
+    /// named "grid".  A bare "{grid}" expands the whole grid, one
+    /// row per line with the values separated by spaces.  Literal
+    /// braces are written "{{" and "}}".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1]);
+    /// puzzle.name_var("x", var);
+    ///
+    /// let solution = puzzle.solve_any().unwrap();
+    /// assert_eq!(solution.format("x={x}", &puzzle), "x=1");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the template is malformed, or if it references a
+    /// name that has not been registered on the puzzle.
+    pub fn format(&self, template: &str, puzzle: &Puzzle) -> String {
+        let mut out = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                },
+
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                },
+
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(ch) => spec.push(ch),
+                            None => panic!("unterminated reference in template: {{{}", spec),
+                        }
+                    }
+                    self.format_reference(&mut out, &spec, puzzle);
+                },
+
+                '}' => panic!("unmatched {:?} in template", '}'),
+
+                ch => out.push(ch),
+            }
+        }
+
+        out
+    }
+
+    /// Expand a single "{...}" reference into the output.
+    fn format_reference(&self, out: &mut String, spec: &str, puzzle: &Puzzle) {
+        if let Some(pos) = spec.find('[') {
+            let (name, indices) = spec.split_at(pos);
+            let grid = puzzle.lookup_grid(name)
+                .unwrap_or_else(|| panic!("unknown grid name: {}", name));
+            let (row, col) = parse_grid_indices(spec, indices);
+
+            let cell = grid.get(row).and_then(|cells| cells.get(col))
+                .unwrap_or_else(|| panic!("index out of range: {}", spec));
+            out.push_str(&self[*cell].to_string());
+        } else if let Some(var) = puzzle.lookup_var(spec) {
+            out.push_str(&self[var].to_string());
+        } else if let Some(grid) = puzzle.lookup_grid(spec) {
+            for row in grid.iter() {
+                let mut sep = "";
+                for &cell in row.iter() {
+                    out.push_str(sep);
+                    out.push_str(&self[cell].to_string());
+                    sep = " ";
+                }
+                out.push('\n');
+            }
+        } else {
+            panic!("unknown variable name: {}", spec);
+        }
+    }
+}
+
+/// Parse the "[r][c]" part of a grid reference.
+fn parse_grid_indices(spec: &str, indices: &str) -> (usize, usize) {
+    let mut vals = Vec::with_capacity(2);
+    let mut rest = indices;
+
+    while !rest.is_empty() {
+        if !rest.starts_with('[') {
+            panic!("malformed reference in template: {}", spec);
+        }
+        let end = rest.find(']')
+            .unwrap_or_else(|| panic!("malformed reference in template: {}", spec));
+        let val = rest[1..end].parse()
+            .unwrap_or_else(|_| panic!("malformed reference in template: {}", spec));
+        vals.push(val);
+        rest = &rest[end + 1..];
+    }
+
+    if vals.len() != 2 {
+        panic!("expected two indices in template reference: {}", spec);
+    }
+
+    (vals[0], vals[1])
+}
+
 impl ops::Index<VarToken> for Solution {
     type Output = Val;
     fn index(&self, var: VarToken) -> &Val {
@@ -59,3 +300,10 @@ impl ops::Index<VarToken> for Solution {
         &self.vars[idx]
     }
 }
+
+impl ops::Index<usize> for Solution {
+    type Output = Val;
+    fn index(&self, idx: usize) -> &Val {
+        &self.vars[idx]
+    }
+}
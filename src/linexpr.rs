@@ -1,7 +1,7 @@
 //! Linear expressions.
 
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
 use std::convert::From;
 use std::ops::{Add,Mul,Neg,Sub};
 use num_rational::{Ratio,Rational32};
@@ -47,18 +47,105 @@ impl IntoCoef for Rational32 {
 
 /*--------------------------------------------------------------*/
 
+impl LinExpr {
+    /// Count the number of variables with non-zero coefficients.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let x = puzzle.new_var();
+    /// let y = puzzle.new_var();
+    ///
+    /// assert_eq!((x + y).degree(), 2);
+    /// assert_eq!((x - x).degree(), 0);
+    /// ```
+    pub fn degree(&self) -> usize {
+        self.coef.len()
+    }
+
+    /// Check if the expression is a constant, i.e. has no variables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let x = puzzle.new_var();
+    ///
+    /// assert!(!(x + 1).is_constant());
+    /// assert!((x - x).is_constant());
+    /// ```
+    pub fn is_constant(&self) -> bool {
+        self.degree() == 0
+    }
+
+    /// Split the given variable's term out of the expression.
+    ///
+    /// Returns the expression with the term removed, and the
+    /// variable's coefficient if it had one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let x = puzzle.new_var();
+    /// let y = puzzle.new_var();
+    ///
+    /// let (expr, coef) = (2 * x + y).without(x);
+    /// assert_eq!(expr.degree(), 1);
+    /// assert_eq!(coef, Some(2.into()));
+    /// assert_eq!(expr.without(x).1, None);
+    /// ```
+    pub fn without(&self, var: VarToken) -> (LinExpr, Option<Coef>) {
+        let mut expr = self.clone();
+        let coef = expr.coef.remove(&var);
+        (expr, coef)
+    }
+
+    /// Replace every occurrence of the variable with the given
+    /// expression, scaled by the variable's coefficient.  Terms are
+    /// merged and cancelled terms are dropped, as usual.
+    ///
+    /// Evaluating the result is equivalent to evaluating the
+    /// original under any assignment where the variable equals the
+    /// replacement expression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let x = puzzle.new_var();
+    /// let y = puzzle.new_var();
+    ///
+    /// // 2x + y with y := x + 1 is 3x + 1.
+    /// let expr = (2 * x + y).substitute(y, &(x + 1));
+    /// assert_eq!(expr.degree(), 1);
+    /// assert_eq!(expr.without(x).1, Some(3.into()));
+    /// ```
+    pub fn substitute(&self, var: VarToken, replacement: &LinExpr)
+            -> LinExpr {
+        let (expr, coef) = self.without(var);
+        match coef {
+            Some(coef) => expr + replacement.clone() * coef,
+            None => expr,
+        }
+    }
+}
+
+/*--------------------------------------------------------------*/
+
 impl<T: IntoCoef> From<T> for LinExpr {
     fn from(constant: T) -> Self {
         LinExpr {
             constant: constant.into_coef(),
-            coef: HashMap::new(),
+            coef: BTreeMap::new(),
         }
     }
 }
 
 impl From<VarToken> for LinExpr {
     fn from(var: VarToken) -> Self {
-        let mut coef = HashMap::new();
+        let mut coef = BTreeMap::new();
         coef.insert(var, Ratio::one());
 
         LinExpr {
@@ -149,7 +236,7 @@ impl<T: IntoCoef> Mul<T> for LinExpr {
     fn mul(mut self, rhs: T) -> Self::Output {
         if rhs.is_zero() {
             self.constant = Ratio::zero();
-            self.coef = HashMap::new();
+            self.coef = BTreeMap::new();
         } else {
             let rhs = rhs.into_coef();
             if rhs != Ratio::one() {
@@ -189,10 +276,10 @@ impl_subtract_op!(VarToken - LinExpr);
 
 impl Add for LinExpr {
     type Output = LinExpr;
-    fn add(mut self, mut rhs: LinExpr) -> Self::Output {
+    fn add(mut self, rhs: LinExpr) -> Self::Output {
         self.constant = self.constant + rhs.constant;
 
-        for (x2, a2) in rhs.coef.drain() {
+        for (x2, a2) in rhs.coef.into_iter() {
             match self.coef.entry(x2) {
                 Entry::Vacant(e) => {
                     e.insert(a2);
@@ -219,7 +306,18 @@ impl_subtract_op!(LinExpr - LinExpr);
 #[cfg(test)]
 mod tests {
     use num_rational::Ratio;
-    use ::Puzzle;
+    use ::{Coef,LinExpr,Puzzle,Val,VarToken};
+
+    /// Evaluate the expression under the given assignment.
+    fn eval(expr: &LinExpr, vals: &[(VarToken, Val)]) -> Coef {
+        let mut sum = expr.constant;
+        for (&var, &coef) in expr.coef.iter() {
+            let val = vals.iter().find(|&&(v, _)| v == var)
+                .expect("unassigned variable").1;
+            sum = sum + coef * Ratio::from_integer(val);
+        }
+        sum
+    }
 
     #[test]
     fn test_ops() {
@@ -278,6 +376,84 @@ mod tests {
         let _ = (x + y) - (x + y);
     }
 
+    #[test]
+    fn test_degree() {
+        let mut puzzle = Puzzle::new();
+        let x = puzzle.new_var();
+        let y = puzzle.new_var();
+
+        let expr = LinExpr::from(1);
+        assert_eq!(expr.degree(), 0);
+        assert!(expr.is_constant());
+
+        let expr = x + 1;
+        assert_eq!(expr.degree(), 1);
+        assert!(!expr.is_constant());
+
+        let expr = x + y;
+        assert_eq!(expr.degree(), 2);
+        assert!(!expr.is_constant());
+
+        let expr = (x + y) - (x + y);
+        assert_eq!(expr.degree(), 0);
+        assert!(expr.is_constant());
+    }
+
+    #[test]
+    fn test_without() {
+        let mut puzzle = Puzzle::new();
+        let x = puzzle.new_var();
+        let y = puzzle.new_var();
+
+        let (expr, coef) = (2 * x + 3 * y + 5).without(y);
+        assert_eq!(coef, Some(Ratio::from_integer(3)));
+        assert_eq!(expr.degree(), 1);
+        assert_eq!(expr.constant, Ratio::from_integer(5));
+
+        let (expr, coef) = expr.without(y);
+        assert_eq!(coef, None);
+        assert_eq!(expr.degree(), 1);
+    }
+
+    #[test]
+    fn test_substitute_eval() {
+        let mut puzzle = Puzzle::new();
+        let x = puzzle.new_var();
+        let y = puzzle.new_var();
+        let z = puzzle.new_var();
+
+        // Substituting y := x - 2z + 1 into 2x + 3y - z + 5 must
+        // agree with the original on every consistent assignment.
+        let original = 2 * x + 3 * y - z + 5;
+        let replacement = x - 2 * z + 1;
+        let substituted = original.substitute(y, &replacement);
+
+        for xv in -3..4 {
+            for zv in -3..4 {
+                let yv = xv - 2 * zv + 1;
+                assert_eq!(eval(&substituted, &[ (x, xv), (z, zv) ]),
+                        eval(&original, &[ (x, xv), (y, yv), (z, zv) ]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_substitute_cancellation() {
+        let mut puzzle = Puzzle::new();
+        let x = puzzle.new_var();
+        let y = puzzle.new_var();
+
+        // x - y with y := x cancels to a constant.
+        let expr = (x - y + 1).substitute(y, &LinExpr::from(x));
+        assert!(expr.is_constant());
+        assert_eq!(expr.constant, Ratio::from_integer(1));
+
+        // Substituting an absent variable changes nothing.
+        let expr = (x + 1).substitute(y, &LinExpr::from(x));
+        assert_eq!(expr.degree(), 1);
+        assert_eq!(eval(&expr, &[ (x, 2) ]), Ratio::from_integer(3));
+    }
+
     #[test]
     fn test_coef_zero() {
         let mut puzzle = Puzzle::new();
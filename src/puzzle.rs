@@ -1,15 +1,25 @@
 //! The puzzle's state and rules.
 
 use std::cell::Cell;
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::fmt;
 use std::iter;
 use std::mem;
 use std::ops;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::mpsc::SyncSender;
+use std::time::Duration;
+use std::time::Instant;
 use bit_set::BitSet;
+use num_rational::Ratio;
+use num_traits::Zero;
 
-use ::{Constraint,LinExpr,PsResult,Solution,Val,VarToken};
+use ::{Coef,ConsistencyLevel,Constraint,LinExpr,PsResult,Solution,Val,
+       VarToken};
 use constraint;
 
 /// A collection of candidates.
@@ -28,19 +38,215 @@ enum VarState {
     Unified(VarToken),
 }
 
+/// A metric emitted to the metrics sink while solving.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Metric {
+    /// A guess was taken.
+    GuessTaken,
+
+    /// The search backtracked from a dead end.
+    Backtrack,
+
+    /// A solution was found.
+    SolutionFound,
+
+    /// A woken constraint was propagated.
+    PropagationRun {
+        /// The type name of the constraint.
+        constraint_kind: &'static str,
+    },
+}
+
+/// Statistics of the last solve, see `Puzzle::last_stats`.
+#[derive(Clone,Debug)]
+pub struct SearchStats {
+    /// The wall-clock time taken by the last solve.
+    pub elapsed: Duration,
+
+    /// The number of choice points entered.
+    pub decisions: u64,
+
+    /// The number of candidate values tried.
+    pub guesses: u64,
+
+    /// The number of dead ends backtracked from.
+    pub backtracks: u64,
+}
+
+/// The reason an optimization run stopped, see
+/// `Puzzle::solve_maximize`.
+#[derive(Copy,Clone,Debug,Eq,PartialEq)]
+pub enum StopReason {
+    /// The search space was exhausted.
+    Exhausted,
+
+    /// The guess limit was reached.
+    GuessLimit,
+}
+
+/// The outcome of an optimization run, see `Puzzle::solve_maximize`.
+///
+/// The incumbent is always returned if one was found, even when the
+/// run stopped before proving it optimal.
+#[derive(Debug)]
+pub struct OptimizeOutcome {
+    /// The best solution found and its objective value, if any.
+    pub best: Option<(Solution, Coef)>,
+
+    /// Whether the incumbent was proven optimal.
+    pub proven_optimal: bool,
+
+    /// Why the run stopped.
+    pub stopped_by: StopReason,
+}
+
+/// A difficulty estimate from search behaviour, see
+/// `Puzzle::rate_difficulty`.
+#[derive(Debug)]
+pub struct DifficultyReport {
+    /// Whether constraint propagation alone solves the puzzle.
+    pub propagation_only: bool,
+
+    /// The number of candidate values tried while solving.
+    pub guesses: u64,
+
+    /// The number of dead ends backtracked from while solving.
+    pub backtracks: u64,
+
+    /// The maximum depth of the search tree.
+    pub max_depth: usize,
+
+    /// The kinds of constraints whose propagation ran while solving.
+    pub propagation_kinds: Vec<&'static str>,
+
+    /// The difficulty score, see `Puzzle::rate_difficulty`.
+    pub score: u64,
+}
+
+/// A constraint group token, see `Puzzle::new_group`.
+#[derive(Copy,Clone,Debug,Eq,PartialEq)]
+pub struct GroupId(usize);
+
+/// A single forced deduction, see `Puzzle::next_hint`.
+#[derive(Debug)]
+pub struct Hint {
+    /// The variable that propagation can fix right now.
+    pub var: VarToken,
+
+    /// The value that the variable is forced to.
+    pub val: Val,
+
+    /// The index of the constraint whose propagation completed the
+    /// deduction, if identifiable.
+    pub because: Option<usize>,
+
+    /// How the deduction arises.
+    pub kind: HintKind,
+}
+
+/// The mechanism behind a hint, see `Puzzle::next_hint`.
+#[derive(Copy,Clone,Debug,Eq,PartialEq)]
+pub enum HintKind {
+    /// The variable's domain was whittled down to a single candidate.
+    NakedSingle,
+
+    /// A constraint forced the assignment directly, e.g. an
+    /// AllDifferent hidden single, or the last unassigned variable
+    /// of an Equality.
+    ForcedAssignment,
+}
+
+/// The result of a what-if probe, see `Puzzle::probe`.
+#[derive(Debug,Eq,PartialEq)]
+pub enum ProbeResult {
+    /// The assumptions lead to a contradiction.
+    Contradiction,
+
+    /// The assumptions propagate cleanly.
+    Consistent {
+        /// The consequent forced assignments, in variable order.
+        forced: Vec<(VarToken, Val)>,
+
+        /// The candidates removed from the variables that are still
+        /// unassigned, in variable order.
+        reduced: Vec<(VarToken, Vec<Val>)>,
+    },
+}
+
 /// The puzzle to be solved.
 pub struct Puzzle {
     // The number of variables in the puzzle.
     num_vars: usize,
 
-    // The number of guesses to solve the puzzle.
-    num_guesses: Cell<u32>,
+    // The number of choice points entered to solve the puzzle.
+    num_decisions: Cell<u64>,
+
+    // The number of candidate values tried to solve the puzzle.
+    num_guesses: Cell<u64>,
+
+    // The number of dead ends backtracked from to solve the puzzle.
+    num_backtracks: Cell<u64>,
+
+    // The number of gimme passes to solve the puzzle.
+    num_gimme_passes: Cell<u32>,
 
     // The list of candidates for each variable.
     candidates: Vec<Candidates>,
 
     // The list of puzzle constraints.
     constraints: Vec<Rc<Constraint>>,
+
+    // The type names of the puzzle constraints, for metrics.
+    constraint_kinds: Vec<&'static str>,
+
+    // The name and enabled flag of each constraint group.
+    groups: Vec<(String, bool)>,
+
+    // The group that each constraint belongs to, if any.
+    constraint_groups: Vec<Option<usize>>,
+
+    // The equations posted via equals: the constraint index and a
+    // copy of the equation, kept for model rewriting passes.
+    equalities: Vec<(usize, LinExpr)>,
+
+    // The soft constraints, which may be violated at a penalty.
+    soft_constraints: Vec<Rc<Constraint>>,
+
+    // The penalty weights of the soft constraints.
+    soft_weights: Vec<u32>,
+
+    // Descriptions of constraints that were found to be trivially
+    // infeasible against the root candidates when they were added.
+    violations: Vec<String>,
+
+    // An optional sink for solver metrics.
+    metrics: Option<Box<Fn(Metric) + Send>>,
+
+    // The time when the current solve started, see last_stats.
+    solve_started: Cell<Option<Instant>>,
+
+    // The wall-clock duration of the last completed solve.
+    elapsed: Cell<Duration>,
+
+    // Trace domain wipeouts to stderr, see set_debug.
+    debug: bool,
+
+    // The index of the constraint currently propagating, for the
+    // debug traces.  Only maintained when debug is set.
+    propagating: Cell<Option<usize>>,
+
+    // The variable of the most recent domain wipeout.  Only recorded
+    // when debug is set.
+    last_wipeout: Cell<Option<VarToken>>,
+
+    // Human-readable names for variables and grids of variables,
+    // used when formatting solutions.
+    var_names: HashMap<String, VarToken>,
+    grid_names: HashMap<String, Vec<Vec<VarToken>>>,
+
+    // Evaluate woken constraints in variable-disjoint groups.
+    #[cfg(feature = "grouped")]
+    grouped: bool,
 }
 
 /// The puzzle constraints, and the variables that wake them up.
@@ -52,6 +258,72 @@ struct PuzzleConstraints {
     // The list of constraints that each variable affects.  These will
     // be woken up when the variable's candidates are changed.
     wake: Vec<BitSet>,
+
+    // The constraints that are still active.  Constraints that become
+    // trivially true after substitution (empty vars) are tombstoned:
+    // they keep their index, but are never applied again.
+    active: BitSet,
+}
+
+/// An owned snapshot of the variable states at the end of a
+/// solution search.
+pub struct SolvedSearch {
+    vars: Vec<VarState>,
+}
+
+/// Status of an incremental solver run.
+#[derive(Debug)]
+pub enum SolverStatus {
+    /// The search has not yet finished.
+    Running,
+
+    /// The search found a solution.  Running the solver further
+    /// searches for the next solution.
+    Solved(Solution),
+
+    /// The search space was exhausted.
+    Unsat,
+}
+
+/// The number of solutions to a puzzle, counted up to a cap.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum SolutionCount {
+    /// The exact number of solutions (fewer than the cap).
+    Exact(usize),
+
+    /// The count hit the cap; there may be more solutions.
+    AtLeast(usize),
+}
+
+/// An incremental driver for the solution search.
+///
+/// Unlike the solve methods on the puzzle, the solver preserves the
+/// full search state between calls, so that the search can be pumped
+/// in small time slices, e.g. from an interactive event loop.
+pub struct Solver<'a> {
+    puzzle: &'a Puzzle,
+
+    // The stack of choice points in the depth-first search.
+    stack: Vec<SolverFrame<'a>>,
+
+    // The next search state to be expanded, if any.
+    pending: Option<PuzzleSearch<'a>>,
+
+    // Set once the search space has been exhausted.
+    done: bool,
+}
+
+/// A choice point in the depth-first search.
+struct SolverFrame<'a> {
+    // The search state at the choice point.
+    search: PuzzleSearch<'a>,
+
+    // The variable being guessed, and its candidates.
+    var_idx: usize,
+    vals: Vec<Val>,
+
+    // The position of the next candidate to try.
+    pos: usize,
 }
 
 /// Intermediate puzzle search state.
@@ -63,6 +335,10 @@ pub struct PuzzleSearch<'a> {
 
     // The list of constraints that need to be re-evaluated.
     wake: BitSet,
+
+    // The list of variables whose candidates have changed, to be
+    // checked for gimmes.
+    dirty: BitSet,
 }
 
 /*--------------------------------------------------------------*/
@@ -100,9 +376,29 @@ impl Puzzle {
     pub fn new() -> Self {
         Puzzle {
             num_vars: 0,
+            num_decisions: Cell::new(0),
             num_guesses: Cell::new(0),
+            num_backtracks: Cell::new(0),
+            num_gimme_passes: Cell::new(0),
             candidates: Vec::new(),
             constraints: Vec::new(),
+            constraint_kinds: Vec::new(),
+            groups: Vec::new(),
+            constraint_groups: Vec::new(),
+            equalities: Vec::new(),
+            soft_constraints: Vec::new(),
+            soft_weights: Vec::new(),
+            violations: Vec::new(),
+            metrics: None,
+            solve_started: Cell::new(None),
+            elapsed: Cell::new(Duration::new(0, 0)),
+            debug: false,
+            propagating: Cell::new(None),
+            last_wipeout: Cell::new(None),
+            var_names: HashMap::new(),
+            grid_names: HashMap::new(),
+            #[cfg(feature = "grouped")]
+            grouped: false,
         }
     }
 
@@ -174,6 +470,25 @@ impl Puzzle {
         vars
     }
 
+    /// Allocate a 2d array of puzzle variables, each initialised to
+    /// have the candidates in the given closed interval.
+    ///
+    /// This avoids collecting a candidate vector for grid puzzles
+    /// with contiguous domains, e.g. sudokus.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut sudoku = puzzle_solver::Puzzle::new();
+    /// sudoku.new_vars_with_range_2d(9, 9, 1..=9);
+    /// ```
+    pub fn new_vars_with_range_2d(&mut self,
+            width: usize, height: usize, range: ops::RangeInclusive<Val>)
+            -> Vec<Vec<VarToken>> {
+        let candidates: Vec<Val> = range.collect();
+        self.new_vars_with_candidates_2d(width, height, &candidates)
+    }
+
     /// Set a variable to a known value.
     ///
     /// This is useful when the variable is given as part of the
@@ -201,6 +516,37 @@ impl Puzzle {
         self.candidates[idx] = Candidates::Value(value);
     }
 
+    /// Set a grid of variables to known values, skipping the None
+    /// entries.  The values are laid out as returned by
+    /// `formats::parse_grid`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_2d(2, 2, &[1,2]);
+    ///
+    /// puzzle.set_values_2d(&vars, &[
+    ///         vec![ Some(1), None ],
+    ///         vec![ None, Some(2) ]]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid dimensions differ.
+    pub fn set_values_2d(&mut self, vars: &[Vec<VarToken>],
+            values: &[Vec<Option<Val>>]) {
+        assert_eq!(vars.len(), values.len());
+        for (row_vars, row_values) in vars.iter().zip(values.iter()) {
+            assert_eq!(row_vars.len(), row_values.len());
+            for (&var, &value) in row_vars.iter().zip(row_values.iter()) {
+                if let Some(value) = value {
+                    self.set_value(var, value);
+                }
+            }
+        }
+    }
+
     /// Add candidates to a variable.
     ///
     /// # Examples
@@ -227,7 +573,7 @@ impl Puzzle {
         }
 
         if let Candidates::Set(ref mut rc) = self.candidates[idx] {
-            let cs = Rc::get_mut(rc).expect("unique");
+            let cs = Rc::make_mut(rc);
             cs.extend(candidates);
         }
     }
@@ -256,7 +602,7 @@ impl Puzzle {
             Candidates::None => (),
 
             Candidates::Set(ref mut rc) => {
-                let cs = Rc::get_mut(rc).expect("unique");
+                let cs = Rc::make_mut(rc);
                 for c in candidates.iter() {
                     cs.remove(c);
                 }
@@ -287,7 +633,7 @@ impl Puzzle {
             Candidates::None => (),
 
             Candidates::Set(ref mut rc) => {
-                let cs = Rc::get_mut(rc).expect("unique");
+                let cs = Rc::make_mut(rc);
                 let mut set = BTreeSet::new();
                 set.extend(candidates);
                 *cs = cs.intersection(&set).cloned().collect();
@@ -296,9 +642,87 @@ impl Puzzle {
     }
 
     /// Add a constraint to the puzzle solution.
-    pub fn add_constraint<T>(&mut self, constraint: T)
+    ///
+    /// Returns the puzzle, so that the constraint methods can be
+    /// chained, e.g. `puzzle.all_different(&vars).equals(a + b, 3)`.
+    pub fn add_constraint<T>(&mut self, constraint: T) -> &mut Self
             where T: Constraint + 'static {
         self.constraints.push(Rc::new(constraint));
+        self.constraint_kinds.push(::std::any::type_name::<T>());
+        self.constraint_groups.push(None);
+        self
+    }
+
+    /// Allocate a new constraint group (initially enabled).
+    ///
+    /// Constraints added to a group with `add_constraint_in` are
+    /// skipped by the solve methods while the group is disabled,
+    /// e.g. to compare puzzle variants without rebuilding the model.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    ///
+    /// let group = puzzle.new_group("variant");
+    /// puzzle.add_constraint_in(group,
+    ///         puzzle_solver::constraint::Equality::new(vars[0] - 1));
+    /// puzzle.set_group_enabled(group, false);
+    /// ```
+    pub fn new_group(&mut self, name: &str) -> GroupId {
+        self.groups.push((String::from(name), true));
+        GroupId(self.groups.len() - 1)
+    }
+
+    /// Add a constraint to the puzzle solution, as a member of the
+    /// given constraint group.
+    ///
+    /// Returns the puzzle, so that the constraint methods can be
+    /// chained.
+    pub fn add_constraint_in<T>(&mut self, group: GroupId, constraint: T)
+            -> &mut Self
+            where T: Constraint + 'static {
+        let GroupId(gidx) = group;
+        self.add_constraint(constraint);
+        let cidx = self.constraints.len() - 1;
+        self.constraint_groups[cidx] = Some(gidx);
+        self
+    }
+
+    /// Enable or disable a constraint group.
+    pub fn set_group_enabled(&mut self, group: GroupId, enabled: bool) {
+        let GroupId(gidx) = group;
+        self.groups[gidx].1 = enabled;
+    }
+
+    /// Get the number of constraints added to the puzzle.
+    pub fn constraint_count(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Add a soft constraint to the puzzle, with the given violation
+    /// weight.
+    ///
+    /// Soft constraints do not restrict the search; they contribute
+    /// their weight to the penalty of any solution that violates
+    /// them, see `solve_min_penalty`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2]);
+    ///
+    /// puzzle.add_soft_constraint(1,
+    ///         puzzle_solver::constraint::Equality::new(var - 1));
+    /// ```
+    pub fn add_soft_constraint<T>(&mut self, weight: u32, constraint: T)
+            -> &mut Self
+            where T: Constraint + 'static {
+        self.soft_constraints.push(Rc::new(constraint));
+        self.soft_weights.push(weight);
+        self
     }
 
     /// Add an All Different constraint.
@@ -312,600 +736,4312 @@ impl Puzzle {
     ///
     /// send_more_money.all_different(&vars);
     /// ```
-    pub fn all_different<'a, I>(&mut self, vars: I)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the same variable appears more than once.
+    pub fn all_different<'a, I>(&mut self, vars: I) -> &mut Self
             where I: IntoIterator<Item=&'a VarToken> {
-        self.add_constraint(constraint::AllDifferent::new(vars));
+        let vars: Vec<VarToken> = vars.into_iter().cloned().collect();
+        self.check_all_different_feasible(&vars);
+        self.add_constraint(constraint::AllDifferent::new(&vars))
     }
 
-    /// Add an Equality constraint.
+    /// Check an All Different constraint against the root candidates:
+    /// there must be at least as many distinct candidates as variables.
+    fn check_all_different_feasible(&mut self, vars: &[VarToken]) {
+        let mut pool = BTreeSet::new();
+        for &VarToken(idx) in vars.iter() {
+            if let Candidates::None = self.candidates[idx] {
+                // Not yet known, nothing to check.
+                return;
+            }
+
+            pool.extend(self.candidates[idx].iter());
+        }
+
+        if pool.len() < vars.len() {
+            self.violations.push(format!(
+                    "all_different: {} variables sharing {} candidates",
+                    vars.len(), pool.len()));
+        }
+    }
+
+    /// Add an Alternating Parity constraint.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut magic_square = puzzle_solver::Puzzle::new();
-    /// let vars = magic_square.new_vars_with_candidates_2d(3, 3,
-    ///         &[1,2,3,4,5,6,7,8,9]);
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2,3,4]);
     ///
-    /// magic_square.equals(vars[0][0] + vars[0][1] + vars[0][2], 15);
+    /// puzzle.alternating_parity(&vars);
     /// ```
-    pub fn equals<L,R>(&mut self, lhs: L, rhs: R)
-            where L: Into<LinExpr>, R: Into<LinExpr> {
-        self.add_constraint(constraint::Equality::new(lhs.into() - rhs.into()));
+    pub fn alternating_parity<'a, I>(&mut self, vars: I) -> &mut Self
+            where I: IntoIterator<Item=&'a VarToken> {
+        self.add_constraint(constraint::AlternatingParity::new(vars))
     }
 
-    /// Add a Unify constraint.
+    /// Add a Between constraint, i.e. b is strictly between a and c
+    /// in value, in either direction.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut send_more_money = puzzle_solver::Puzzle::new();
-    /// let carry = send_more_money.new_vars_with_candidates_1d(4, &[0,1]);
-    /// let vars = send_more_money.new_vars_with_candidates_1d(8,
-    ///         &[0,1,2,3,4,5,6,7,8,9]);
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
     ///
-    /// let m = vars[4];
-    /// send_more_money.unify(m, carry[3]);
+    /// puzzle.between(vars[0], vars[1], vars[2]);
     /// ```
-    pub fn unify(&mut self, var1: VarToken, var2: VarToken) {
-        self.add_constraint(constraint::Unify::new(var1, var2));
+    pub fn between(&mut self, a: VarToken, b: VarToken, c: VarToken)
+            -> &mut Self {
+        self.add_constraint(constraint::Between::new(a, b, c))
     }
 
-    /// Find any solution to the given puzzle.
+    /// Add a Difference constraint, i.e. result = a - b.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut puzzle = puzzle_solver::Puzzle::new();
-    /// puzzle.new_var_with_candidates(&[1,2]);
-    /// puzzle.new_var_with_candidates(&[3,4]);
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
     ///
-    /// let solution = puzzle.solve_any();
-    /// assert!(solution.is_some());
+    /// puzzle.difference(vars[0], vars[1], vars[2]);
     /// ```
-    pub fn solve_any(&mut self) -> Option<Solution> {
-        let mut solutions = Vec::with_capacity(1);
-
-        self.num_guesses.set(0);
-        if self.num_vars > 0 {
-            let mut search = PuzzleSearch::new(self);
-            search.solve(1, &mut solutions);
-        }
+    pub fn difference(&mut self, result: VarToken, a: VarToken, b: VarToken)
+            -> &mut Self {
+        self.add_constraint(constraint::Difference::new(result, a, b))
+    }
 
-        solutions.pop()
+    /// Add a Connected constraint, i.e. the cells set to 1 form a
+    /// single orthogonally-connected component of the grid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1]);
+    /// let cells = (0..3).map(|x| (vars[x], x, 0)).collect();
+    ///
+    /// puzzle.connected(cells, 3, 1);
+    /// ```
+    pub fn connected(&mut self, cells: Vec<(VarToken, usize, usize)>,
+            width: usize, height: usize) -> &mut Self {
+        self.add_constraint(constraint::Connected::new(cells, width, height))
     }
 
-    /// Find the solution to the given puzzle, verifying that it is
-    /// unique.
+    /// Add the disjoint groups sudoku constraints, i.e. the cells
+    /// sharing the same position within their 3x3 block take nine
+    /// different values.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut puzzle = puzzle_solver::Puzzle::new();
-    /// puzzle.new_var_with_candidates(&[1,2]);
-    /// puzzle.new_var_with_candidates(&[3,4]);
+    /// let vars = puzzle.new_vars_with_candidates_2d(9, 9,
+    ///         &[1,2,3,4,5,6,7,8,9]);
     ///
-    /// let solution = puzzle.solve_unique();
-    /// assert!(solution.is_none());
+    /// puzzle.disjoint_groups(&vars);
     /// ```
-    pub fn solve_unique(&mut self) -> Option<Solution> {
-        self.num_guesses.set(0);
-        if self.num_vars > 0 {
-            let mut search = PuzzleSearch::new(self);
-            let mut solutions = Vec::with_capacity(2);
-            search.solve(2, &mut solutions);
-            if solutions.len() == 1 {
-                return solutions.pop();
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid is not 9x9.
+    pub fn disjoint_groups(&mut self, grid: &[Vec<VarToken>]) -> &mut Self {
+        assert_eq!(grid.len(), 9);
+
+        for y in 0..3 {
+            for x in 0..3 {
+                let mut group = Vec::with_capacity(9);
+                for by in 0..3 {
+                    assert_eq!(grid[3 * by + y].len(), 9);
+                    for bx in 0..3 {
+                        group.push(grid[3 * by + y][3 * bx + x]);
+                    }
+                }
+
+                self.all_different(&group);
             }
         }
 
-        None
+        self
     }
 
-    /// Find all solutions to the given puzzle.
+    /// Add the LineBoxReduction constraints between every sudoku row
+    /// or column and every 3x3 box that it intersects.
+    ///
+    /// These are redundant propagators: they implement the "pointing
+    /// pairs" and "box-line reduction" human techniques, trading
+    /// extra propagation work for fewer guesses.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut puzzle = puzzle_solver::Puzzle::new();
-    /// puzzle.new_var_with_candidates(&[1,2]);
-    /// puzzle.new_var_with_candidates(&[3,4]);
+    /// let vars = puzzle.new_vars_with_candidates_2d(9, 9,
+    ///         &[1,2,3,4,5,6,7,8,9]);
     ///
-    /// let solutions = puzzle.solve_all();
-    /// assert_eq!(solutions.len(), 4);
+    /// puzzle.line_box_reduction(&vars);
     /// ```
-    pub fn solve_all(&mut self) -> Vec<Solution> {
-        let mut solutions = Vec::new();
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid is not 9x9.
+    pub fn line_box_reduction(&mut self, grid: &[Vec<VarToken>]) -> &mut Self {
+        assert_eq!(grid.len(), 9);
 
-        self.num_guesses.set(0);
-        if self.num_vars > 0 {
-            let mut search = PuzzleSearch::new(self);
-            search.solve(::std::usize::MAX, &mut solutions);
-        }
+        for by in 0..3 {
+            for bx in 0..3 {
+                let mut box_cells = Vec::with_capacity(9);
+                for y in 0..3 {
+                    assert_eq!(grid[3 * by + y].len(), 9);
+                    for x in 0..3 {
+                        box_cells.push(grid[3 * by + y][3 * bx + x]);
+                    }
+                }
 
-        solutions
-    }
+                for y in 0..3 {
+                    let row = grid[3 * by + y].clone();
+                    self.add_constraint(constraint::LineBoxReduction::new(
+                            row, box_cells.clone()));
+                }
 
-    /// Take any obvious non-choices, using the constraints to
-    /// eliminate candidates.  Stops when it must start guessing.
-    /// Primarily for testing.
-    ///
-    /// Returns the intermediate puzzle search state, or None if a
-    /// contradiction was found.
-    pub fn step(&mut self) -> Option<PuzzleSearch> {
-        if self.num_vars > 0 {
-            let mut search = PuzzleSearch::new(self);
-            if search.constrain().is_ok() {
-                return Some(search);
+                for x in 0..3 {
+                    let col = grid.iter().map(|row| row[3 * bx + x]).collect();
+                    self.add_constraint(constraint::LineBoxReduction::new(
+                            col, box_cells.clone()));
+                }
             }
         }
 
-        None
+        self
     }
 
-    /// Get the number of guesses taken to solve the last puzzle.
-    pub fn num_guesses(&self) -> u32 {
-        self.num_guesses.get()
+    /// Add an arrow sudoku constraint, i.e. the cells along the arrow
+    /// path sum to the value in the bulb.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle.arrow(vars[0], &vars[1..]);
+    /// ```
+    pub fn arrow(&mut self, bulb: VarToken, path: &[VarToken]) -> &mut Self {
+        let sum = path.iter().fold(LinExpr::from(0), |sum, &var| sum + var);
+        self.equals(bulb, sum)
     }
-}
 
-/*--------------------------------------------------------------*/
+    /// Add a Kropki dot constraint between two cells, i.e. the cells
+    /// are consecutive (white dot) or one is twice the other (black
+    /// dot).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle.kropki(vars[0], vars[1],
+    ///         puzzle_solver::constraint::KropkiKind::Consecutive);
+    /// ```
+    pub fn kropki(&mut self, a: VarToken, b: VarToken,
+            kind: constraint::KropkiKind) -> &mut Self {
+        self.add_constraint(constraint::Kropki::new(a, b, kind))
+    }
 
-impl PuzzleConstraints {
+    /// Add a killer sudoku Cage constraint, i.e. the cells take
+    /// distinct values summing to the total.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle.killer_cage(17, &vars);
+    /// ```
+    pub fn killer_cage(&mut self, total: Val, cells: &[VarToken])
+            -> &mut Self {
+        self.add_constraint(constraint::Cage::new(total, cells.to_vec()))
+    }
+
+    /// Add a killer cage whose total is itself a puzzle variable.
+    /// The cells take distinct values summing to the total.
+    ///
+    /// Cage totals do not have to be constants: this posts
+    /// all-different over the cells plus `equals(total, sum)`, so
+    /// the total is deduced from the cells and vice versa.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let total = puzzle.new_var_with_candidates(&[3,4,5]);
+    /// let cells = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    ///
+    /// puzzle.cage_var(total, &cells);
+    /// ```
+    pub fn cage_var(&mut self, total: VarToken, cells: &[VarToken])
+            -> &mut Self {
+        let sum = cells.iter().fold(LinExpr::from(0), |sum, &var| sum + var);
+        self.all_different(cells);
+        self.equals(total, sum)
+    }
+
+    /// Add a multiplication cage, i.e. the cells multiply to the
+    /// total, as in calcudoku/kenken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+    ///
+    /// puzzle.mul_cage(12, &vars);
+    /// ```
+    pub fn mul_cage(&mut self, total: Val, cells: &[VarToken])
+            -> &mut Self {
+        self.add_constraint(constraint::MulCage::new(total, cells.to_vec()))
+    }
+
+    /// Add a subtraction cage, i.e. |a - b| equals the result, as in
+    /// kenken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+    ///
+    /// puzzle.sub_cage(5, vars[0], vars[1]);
+    /// ```
+    pub fn sub_cage(&mut self, result: Val, a: VarToken, b: VarToken)
+            -> &mut Self {
+        self.add_constraint(constraint::SubCage::new(result, a, b))
+    }
+
+    /// Add a division cage, i.e. the larger of the two cells divided
+    /// by the smaller equals the result exactly, as in kenken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6]);
+    ///
+    /// puzzle.div_cage(3, vars[0], vars[1]);
+    /// ```
+    pub fn div_cage(&mut self, result: Val, a: VarToken, b: VarToken)
+            -> &mut Self {
+        self.add_constraint(constraint::DivCage::new(result, a, b))
+    }
+
+    /// Add a Restricted Sum constraint.  Each cell takes a value
+    /// from the allowed set, and the cells sum to the total.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4,5]);
+    ///
+    /// puzzle.restricted_sum(&vars, &[1,3,5], 8);
+    /// ```
+    pub fn restricted_sum(&mut self, cells: &[VarToken], allowed: &[Val],
+            total: Val) -> &mut Self {
+        self.add_constraint(constraint::RestrictedSum::new(cells.to_vec(),
+                allowed.to_vec(), total))
+    }
+
+    /// Add an Xor constraint over boolean (0 or 1) variables,
+    /// i.e. result = a ^ b.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let a = puzzle.new_var_with_candidates(&[0,1]);
+    /// let b = puzzle.new_var_with_candidates(&[0,1]);
+    /// let result = puzzle.new_var_with_candidates(&[0,1]);
+    ///
+    /// puzzle.boolean_xor(a, b, result);
+    /// ```
+    pub fn boolean_xor(&mut self, a: VarToken, b: VarToken, result: VarToken)
+            -> &mut Self {
+        self.add_constraint(constraint::Xor::new(a, b, result))
+    }
+
+    /// Add an Entropy constraint, i.e. every run of 3 consecutive
+    /// cells along the line contains one low {1,2,3}, one mid {4,5,6}
+    /// and one high {7,8,9} value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle.entropy_line(&vars);
+    /// ```
+    pub fn entropy_line(&mut self, cells: &[VarToken]) -> &mut Self {
+        self.add_constraint(constraint::Entropy::new(cells.to_vec()))
+    }
+
+    /// Add a Renban constraint, i.e. the cells contain a set of
+    /// consecutive distinct values, in any order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle.renban(&vars);
+    /// ```
+    pub fn renban(&mut self, cells: &[VarToken]) -> &mut Self {
+        self.add_constraint(constraint::Renban::new(cells.to_vec()))
+    }
+
+    /// Add a Whisper constraint, i.e. consecutive cells along the
+    /// line differ by at least min_diff.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// puzzle.whisper(&vars, 5);
+    /// ```
+    pub fn whisper(&mut self, cells: &[VarToken], min_diff: Val) -> &mut Self {
+        self.add_constraint(constraint::Whisper::new(cells.to_vec(), min_diff))
+    }
+
+    /// Add a Skyscraper constraint.  Looking along the line from the
+    /// front, exactly "visible" values can be seen; a value is
+    /// visible if it is taller than all of the values before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[1,2,3]);
+    ///
+    /// puzzle.skyscraper(&vars, 2);
+    /// ```
+    pub fn skyscraper(&mut self, line: &[VarToken], visible: Val)
+            -> &mut Self {
+        self.add_constraint(constraint::Skyscraper::new(line.to_vec(), visible))
+    }
+
+    /// Add an Equality constraint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut magic_square = puzzle_solver::Puzzle::new();
+    /// let vars = magic_square.new_vars_with_candidates_2d(3, 3,
+    ///         &[1,2,3,4,5,6,7,8,9]);
+    ///
+    /// magic_square.equals(vars[0][0] + vars[0][1] + vars[0][2], 15);
+    /// ```
+    pub fn equals<L,R>(&mut self, lhs: L, rhs: R) -> &mut Self
+            where L: Into<LinExpr>, R: Into<LinExpr> {
+        let eq = lhs.into() - rhs.into();
+        self.check_equals_feasible(&eq);
+        self.add_constraint(constraint::Equality::new(eq.clone()));
+        let cidx = self.constraints.len() - 1;
+        self.equalities.push((cidx, eq));
+        self
+    }
+
+    /// Add a Lower Bound constraint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2,3,4]);
+    ///
+    /// puzzle.at_least_val(var, 3);
+    /// ```
+    pub fn at_least_val(&mut self, var: VarToken, min: Val) -> &mut Self {
+        self.add_constraint(constraint::LowerBound::new(var, min))
+    }
+
+    /// Add an Upper Bound constraint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2,3,4]);
+    ///
+    /// puzzle.at_most_val(var, 2);
+    /// ```
+    pub fn at_most_val(&mut self, var: VarToken, max: Val) -> &mut Self {
+        self.add_constraint(constraint::UpperBound::new(var, max))
+    }
+
+    /// Check an equality constraint against the root candidates:
+    /// zero must be within the bounds of the expression.
+    fn check_equals_feasible(&mut self, eq: &LinExpr) {
+        let widen = |r: ::Coef| Ratio::new(*r.numer() as i64, *r.denom() as i64);
+        let mut min = widen(eq.constant);
+        let mut max = min;
+
+        for (&VarToken(idx), &coef) in eq.coef.iter() {
+            let (cand_min, cand_max) = match (self.candidates[idx].iter().min(),
+                    self.candidates[idx].iter().max()) {
+                (Some(lo), Some(hi)) => (Ratio::from_integer(lo as i64),
+                                         Ratio::from_integer(hi as i64)),
+                // Not yet known, nothing to check.
+                _ => return,
+            };
+
+            let coef = widen(coef);
+            if coef > Ratio::zero() {
+                min = min + coef * cand_min;
+                max = max + coef * cand_max;
+            } else {
+                min = min + coef * cand_max;
+                max = max + coef * cand_min;
+            }
+        }
+
+        if min > Ratio::zero() || max < Ratio::zero() {
+            self.violations.push(format!(
+                    "equals: expression bounds [{}, {}] exclude zero",
+                    min, max));
+        }
+    }
+
+    /// Add a Sum Parity constraint, i.e. the value of the expression
+    /// is even.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4]);
+    ///
+    /// puzzle.sum_even(vars[0] + vars[1]);
+    /// ```
+    pub fn sum_even<E: Into<LinExpr>>(&mut self, expr: E) -> &mut Self {
+        self.add_constraint(constraint::SumParity::new(expr.into(), true))
+    }
+
+    /// Add a Sum Parity constraint, i.e. the value of the expression
+    /// is odd.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2,3,4]);
+    ///
+    /// puzzle.sum_odd(vars[0] + vars[1]);
+    /// ```
+    pub fn sum_odd<E: Into<LinExpr>>(&mut self, expr: E) -> &mut Self {
+        self.add_constraint(constraint::SumParity::new(expr.into(), false))
+    }
+
+    /// Constrain a line of cells to read the same in both directions,
+    /// i.e. cells[i] = cells[n - 1 - i], by unifying the mirrored
+    /// pairs.  Used in e.g. palindrome-line sudoku.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(4, &[1,2,3,4]);
+    ///
+    /// puzzle.palindrome(&vars);
+    /// ```
+    pub fn palindrome(&mut self, cells: &[VarToken]) -> &mut Self {
+        for i in 0..cells.len() / 2 {
+            self.unify(cells[i], cells[cells.len() - 1 - i]);
+        }
+
+        self
+    }
+
+    /// Add a Unify constraint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut send_more_money = puzzle_solver::Puzzle::new();
+    /// let carry = send_more_money.new_vars_with_candidates_1d(4, &[0,1]);
+    /// let vars = send_more_money.new_vars_with_candidates_1d(8,
+    ///         &[0,1,2,3,4,5,6,7,8,9]);
+    ///
+    /// let m = vars[4];
+    /// send_more_money.unify(m, carry[3]);
+    /// ```
+    pub fn unify(&mut self, var1: VarToken, var2: VarToken) -> &mut Self {
+        self.check_unify_feasible(var1, var2);
+        self.add_constraint(constraint::Unify::new(var1, var2))
+    }
+
+    /// Check a Unify constraint against the root candidates:
+    /// the two variables must have a candidate in common.
+    fn check_unify_feasible(&mut self, var1: VarToken, var2: VarToken) {
+        let (VarToken(idx1), VarToken(idx2)) = (var1, var2);
+        match (&self.candidates[idx1], &self.candidates[idx2]) {
+            // Not yet known, nothing to check.
+            (&Candidates::None, _) | (_, &Candidates::None) => (),
+
+            (cs1, cs2) => {
+                let set: BTreeSet<Val> = cs1.iter().collect();
+                if !cs2.iter().any(|val| set.contains(&val)) {
+                    self.violations.push(format!(
+                            "unify: {:?} and {:?} have no common candidates",
+                            var1, var2));
+                }
+            },
+        }
+    }
+
+    /// Check the puzzle for constraints that were found to be
+    /// trivially infeasible when they were added, e.g. equals(3, 5),
+    /// returning their descriptions.  Such puzzles have no solutions;
+    /// validating before solving reports the offending constraints
+    /// instead of failing after a potentially long search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.equals(3, 5);
+    ///
+    /// assert!(puzzle.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(self.violations.clone())
+        }
+    }
+
+    /// Verify a solution against the puzzle's constraints.
+    ///
+    /// Returns Ok if every constraint is satisfied, or the list of
+    /// failing constraint indices, e.g. to report exactly which rules
+    /// a hand-made solution breaks during puzzle design.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    ///
+    /// let solution = puzzle.solve_any().unwrap();
+    /// assert!(puzzle.verify(&solution).is_ok());
+    /// ```
+    pub fn verify(&self, solution: &Solution) -> Result<(), Vec<usize>> {
+        let mut search = PuzzleSearch::new(self);
+        for idx in 0..self.num_vars {
+            search.vars[idx] = VarState::Assigned(solution.vars[idx]);
+        }
+
+        let mut failed = Vec::new();
+        for cidx in 0..self.constraints.len() {
+            let constraint = self.constraints[cidx].clone();
+            let ok = constraint.vars()
+                .all(|&VarToken(idx)| constraint.on_assigned(&mut search,
+                        VarToken(idx), solution.vars[idx]).is_ok())
+                && constraint.on_updated(&mut search).is_ok();
+            if !ok {
+                failed.push(cidx);
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(failed)
+        }
+    }
+
+    /// Find any solution to the given puzzle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// let solution = puzzle.solve_any();
+    /// assert!(solution.is_some());
+    /// ```
+    pub fn solve_any(&mut self) -> Option<Solution> {
+        let mut solutions = Vec::with_capacity(1);
+
+        self.reset_stats();
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            search.solve(1, &mut solutions, None);
+        }
+
+        self.finish_stats();
+        solutions.pop()
+    }
+
+    /// Find any solution to the given puzzle, returning both the
+    /// solution and a snapshot of the final search state, e.g. to
+    /// inspect which variables were unified away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let v0 = puzzle.new_var_with_candidates(&[1,2]);
+    /// let v1 = puzzle.new_var_with_candidates(&[2,3]);
+    /// puzzle.unify(v0, v1);
+    ///
+    /// let (solution, _search) = puzzle.solve_any_with_search().unwrap();
+    /// assert_eq!(solution[v0], 2);
+    /// ```
+    pub fn solve_any_with_search(&mut self)
+            -> Option<(Solution, SolvedSearch)> {
+        let mut solutions = Vec::with_capacity(1);
+        let mut capture = None;
+
+        self.reset_stats();
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            search.solve(1, &mut solutions, Some(&mut capture));
+        }
+
+        self.finish_stats();
+        match (solutions.pop(), capture) {
+            (Some(solution), Some(search)) => Some((solution, search)),
+            _ => None,
+        }
+    }
+
+    /// Find any solution to the given puzzle under the given
+    /// assumptions.
+    ///
+    /// The assumptions hold only for the duration of this solve; the
+    /// puzzle itself is left unchanged, unlike `set_value`.  This is
+    /// the primitive for "what-if" queries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let v0 = puzzle.new_var_with_candidates(&[1,2]);
+    ///
+    /// let solution = puzzle.solve_under(&[(v0, 2)]).unwrap();
+    /// assert_eq!(solution[v0], 2);
+    /// ```
+    pub fn solve_under(&mut self, assumptions: &[(VarToken, Val)])
+            -> Option<Solution> {
+        let mut solutions = Vec::with_capacity(1);
+
+        self.reset_stats();
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            for &(var, val) in assumptions.iter() {
+                if search.set_candidate(var, val).is_err() {
+                    self.finish_stats();
+                    return None;
+                }
+            }
+
+            search.solve(1, &mut solutions, None);
+        }
+
+        self.finish_stats();
+        solutions.pop()
+    }
+
+    /// Find a solution satisfying all of the hard constraints that
+    /// minimizes the total weight of the violated soft constraints,
+    /// by branch and bound on the penalty.
+    ///
+    /// Returns the solution and its penalty, or None if the hard
+    /// constraints cannot be satisfied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.set_value(var, 2);
+    /// puzzle.add_soft_constraint(1,
+    ///         puzzle_solver::constraint::Equality::new(var - 1));
+    ///
+    /// let (_, penalty) = puzzle.solve_min_penalty().unwrap();
+    /// assert_eq!(penalty, 1);
+    /// ```
+    pub fn solve_min_penalty(&mut self) -> Option<(Solution, u32)> {
+        self.reset_stats();
+        let mut best = None;
+        if self.num_vars > 0 {
+            let search = PuzzleSearch::new(self);
+            search.solve_min_penalty(&mut best);
+        }
+
+        self.finish_stats();
+        best
+    }
+
+    /// Find a solution maximizing the given linear objective, by
+    /// branch and bound, taking at most max_guesses guesses.
+    ///
+    /// This is an anytime optimization: the incumbent found so far is
+    /// always returned, together with whether it was proven optimal
+    /// and why the run stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    ///
+    /// let outcome = puzzle.solve_maximize(vars[0] + vars[1],
+    ///         ::std::u64::MAX);
+    /// assert!(outcome.proven_optimal);
+    /// let (_, value) = outcome.best.unwrap();
+    /// assert_eq!(value, 3.into());
+    /// ```
+    pub fn solve_maximize<E>(&mut self, objective: E, max_guesses: u64)
+            -> OptimizeOutcome where E: Into<LinExpr> {
+        self.reset_stats();
+        let objective = objective.into();
+        let mut best = None;
+        let mut completed = true;
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            completed = search.solve_maximize(&objective, max_guesses,
+                    &mut best);
+        }
+
+        self.finish_stats();
+        OptimizeOutcome {
+            proven_optimal: completed && best.is_some(),
+            stopped_by: if completed {
+                StopReason::Exhausted
+            } else {
+                StopReason::GuessLimit
+            },
+            best: best,
+        }
+    }
+
+    /// Assign every variable its smallest candidate, in variable
+    /// order, without backtracking.
+    ///
+    /// This is the cheapest possible "solve": a deterministic
+    /// heuristic useful for generating a single canonical assignment
+    /// for puzzle design tools.  Returns None if any constraint is
+    /// violated along the way, even if a solution could have been
+    /// found by backtracking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let v0 = puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// let solution = puzzle.ground_first().unwrap();
+    /// assert_eq!(solution[v0], 1);
+    /// ```
+    pub fn ground_first(&mut self) -> Option<Solution> {
+        self.reset_stats();
+        let solution = if self.num_vars > 0 {
+            let search = PuzzleSearch::new(self);
+            search.ground_first()
+        } else {
+            None
+        };
+
+        self.finish_stats();
+        solution
+    }
+
+    /// Find any solution to the given puzzle using look-ahead search.
+    ///
+    /// At each branching point, try each candidate value of the most
+    /// constrained variable, apply constraint propagation, and try
+    /// the values in order of the least total remaining candidates.
+    /// The depth parameter controls how many nested propagation
+    /// rounds to look ahead; larger depths order the values better
+    /// at the cost of more propagation per branching point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// let solution = puzzle.solve_with_lookahead(1);
+    /// assert!(solution.is_some());
+    /// ```
+    pub fn solve_with_lookahead(&mut self, depth: usize) -> Option<Solution> {
+        self.reset_stats();
+        let solution = if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            search.solve_lookahead(depth)
+        } else {
+            None
+        };
+
+        self.finish_stats();
+        solution
+    }
+
+    /// Find the solution to the given puzzle, verifying that it is
+    /// unique.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// let solution = puzzle.solve_unique();
+    /// assert!(solution.is_none());
+    /// ```
+    pub fn solve_unique(&mut self) -> Option<Solution> {
+        self.reset_stats();
+        let mut result = None;
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            let mut solutions = Vec::with_capacity(2);
+            search.solve(2, &mut solutions, None);
+            if solutions.len() == 1 {
+                result = solutions.pop();
+            }
+        }
+
+        self.finish_stats();
+        result
+    }
+
+    /// Count the solutions to the given puzzle, stopping early after
+    /// finding cap solutions.
+    ///
+    /// Returns Exact if the search space was exhausted before the
+    /// cap, and AtLeast(cap) otherwise.  This distinguishes unique
+    /// puzzles from multi-solution puzzles without paying for a full
+    /// enumeration, e.g. when generating puzzles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// assert_eq!(puzzle.solution_count(10),
+    ///         puzzle_solver::SolutionCount::Exact(4));
+    /// assert_eq!(puzzle.solution_count(3),
+    ///         puzzle_solver::SolutionCount::AtLeast(3));
+    /// ```
+    pub fn solution_count(&mut self, cap: usize) -> SolutionCount {
+        let mut solutions = Vec::new();
+
+        self.reset_stats();
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            search.solve(cap, &mut solutions, None);
+        }
+
+        self.finish_stats();
+        if solutions.len() < cap {
+            SolutionCount::Exact(solutions.len())
+        } else {
+            SolutionCount::AtLeast(cap)
+        }
+    }
+
+    /// Find all solutions to the given puzzle.
+    ///
+    /// The solutions are returned in lexicographic order by the full
+    /// variable vector, i.e. comparing the values of variable 0
+    /// first, then variable 1, and so on.  This order is guaranteed,
+    /// e.g. for golden-file tests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// let solutions = puzzle.solve_all();
+    /// assert_eq!(solutions.len(), 4);
+    /// ```
+    pub fn solve_all(&mut self) -> Vec<Solution> {
+        let mut solutions = Vec::new();
+
+        self.reset_stats();
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            search.solve(::std::usize::MAX, &mut solutions, None);
+        }
+
+        self.finish_stats();
+
+        // The search branches on the most constrained variable, not
+        // variable 0, so the discovery order is not lexicographic.
+        solutions.sort_by(|a, b| a.vars.cmp(&b.vars));
+        solutions
+    }
+
+    /// Enumerate all the solutions, accumulating for each variable
+    /// (in creation order) the set of values it takes across them.
+    /// A variable with a single possible value is forced, i.e. part
+    /// of the puzzle's backbone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    ///
+    /// let domains = puzzle.solution_domains();
+    /// assert_eq!(domains[0].iter().cloned().collect::<Vec<_>>(), [1,2]);
+    /// ```
+    pub fn solution_domains(&mut self) -> Vec<BTreeSet<Val>> {
+        let mut domains = vec![BTreeSet::new(); self.num_vars];
+        for solution in self.solve_all().iter() {
+            for (idx, &val) in solution.vars.iter().enumerate() {
+                domains[idx].insert(val);
+            }
+        }
+
+        domains
+    }
+
+    /// Count, for each candidate of the given variable, how many
+    /// solutions assign it that value.  Candidates appearing in no
+    /// solution are reported with a count of 0.
+    ///
+    /// The solutions are enumerated once, tallying at each leaf, so
+    /// this costs the same as a single `solve_all`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    ///
+    /// let counts = puzzle.count_by_value(vars[0]);
+    /// assert_eq!(counts[&1], 1);
+    /// assert_eq!(counts[&2], 1);
+    /// ```
+    pub fn count_by_value(&mut self, var: VarToken) -> BTreeMap<Val, u64> {
+        let VarToken(idx) = var;
+        let mut counts: BTreeMap<Val, u64> = self.candidates[idx].iter()
+            .map(|val| (val, 0))
+            .collect();
+
+        for solution in self.solve_all().iter() {
+            let count = counts.entry(solution[var]).or_insert(0);
+            *count = *count + 1;
+        }
+
+        counts
+    }
+
+    /// Find the backbone: the variables that take the same value in
+    /// every solution, even where that is not derivable by simple
+    /// propagation.  Returns an empty vector if the puzzle has no
+    /// solutions.
+    ///
+    /// Each variable is probed with each alternative value as an
+    /// assumption, short-circuiting as soon as a second value is
+    /// shown to be possible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    /// puzzle.set_value(vars[0], 1);
+    ///
+    /// assert_eq!(puzzle.backbone(), [(vars[0], 1), (vars[1], 2)]);
+    /// ```
+    pub fn backbone(&mut self) -> Vec<(VarToken, Val)> {
+        let mut backbone = Vec::new();
+        let first = match self.solve_any() {
+            Some(solution) => solution,
+            None => return backbone,
+        };
+
+        for idx in 0..self.num_vars {
+            let var = VarToken(idx);
+            let val = first[var];
+            let alternatives: Vec<Val> = self.candidates[idx].iter()
+                .filter(|&alt| alt != val)
+                .collect();
+
+            if alternatives.into_iter()
+                    .all(|alt| self.solve_under(&[(var, alt)]).is_none()) {
+                backbone.push((var, val));
+            }
+        }
+
+        backbone
+    }
+
+    /// Probe the consequences of the given assumptions, without
+    /// touching the puzzle.
+    ///
+    /// Applies the assumptions to the propagated root state and runs
+    /// constraint propagation only (no guessing).  Returns whether a
+    /// contradiction arose, and otherwise the forced assignments and
+    /// domain reductions that follow from the assumptions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use puzzle_solver::ProbeResult;
+    ///
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    ///
+    /// match puzzle.probe(&[(vars[0], 1)]) {
+    ///     ProbeResult::Consistent{ forced, .. } =>
+    ///         assert_eq!(forced, &[(vars[1], 2)]),
+    ///     ProbeResult::Contradiction => panic!(),
+    /// }
+    /// ```
+    pub fn probe(&mut self, assumptions: &[(VarToken, Val)]) -> ProbeResult {
+        let mut base = PuzzleSearch::new(self);
+        if base.constrain().is_err() {
+            return ProbeResult::Contradiction;
+        }
+
+        let mut search = base.clone();
+        for &(var, val) in assumptions.iter() {
+            if search.set_candidate(var, val).is_err() {
+                return ProbeResult::Contradiction;
+            }
+        }
+
+        if search.constrain().is_err() {
+            return ProbeResult::Contradiction;
+        }
+
+        // Diff the pre- and post-propagation states.
+        let mut forced = Vec::new();
+        let mut reduced = Vec::new();
+        for idx in 0..self.num_vars {
+            let var = VarToken(idx);
+            if assumptions.iter().any(|&(v, _)| v == var) {
+                continue;
+            }
+
+            if !base.is_assigned(var) {
+                if let Some(val) = search.get_assigned(var) {
+                    forced.push((var, val));
+                } else {
+                    let keep: BTreeSet<Val>
+                        = search.get_unassigned(var).collect();
+                    let removed: Vec<Val> = base.get_unassigned(var)
+                        .filter(|val| !keep.contains(val))
+                        .collect();
+
+                    if !removed.is_empty() {
+                        reduced.push((var, removed));
+                    }
+                }
+            }
+        }
+
+        ProbeResult::Consistent {
+            forced: forced,
+            reduced: reduced,
+        }
+    }
+
+    /// Find a single forced deduction, if any: a variable that
+    /// constraint propagation alone can fix right now, together with
+    /// the constraint responsible and the mechanism.  Returns None if
+    /// nothing is forced without guessing, or if the puzzle is
+    /// contradictory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    /// puzzle.set_value(vars[0], 1);
+    ///
+    /// let hint = puzzle.next_hint().unwrap();
+    /// assert_eq!((hint.var, hint.val), (vars[1], 2));
+    /// ```
+    pub fn next_hint(&mut self) -> Option<Hint> {
+        let mut search = PuzzleSearch::new(self);
+
+        // Assign the given values; they are not deductions.
+        let givens: Vec<(usize, Val)> = (0..self.num_vars)
+            .filter_map(|idx| match &search.vars[idx] {
+                &VarState::Unassigned(ref cs) if cs.len() == 1 =>
+                    cs.iter().next().map(|val| (idx, val)),
+                _ => None,
+            })
+            .collect();
+
+        for (idx, val) in givens.into_iter() {
+            if search.assign(idx, val).is_err() {
+                return None;
+            }
+        }
+
+        // Candidates eliminated while assigning the givens may
+        // already have left a naked single behind.
+        match search.first_singleton() {
+            Err(()) => return None,
+            Ok(Some((idx, val))) => {
+                let because = search.constraints.wake[idx].iter()
+                    .find(|&cidx| search.constraints.active.contains(cidx));
+                return Some(Hint {
+                    var: VarToken(idx),
+                    val: val,
+                    because: because,
+                    kind: HintKind::NakedSingle,
+                });
+            },
+            Ok(None) => (),
+        }
+
+        // Run the woken constraints one at a time, stopping at the
+        // first constraint that forces an assignment.
+        while !search.wake.is_empty() {
+            let wake = mem::replace(&mut search.wake, BitSet::new());
+            for cidx in wake.iter() {
+                if !search.constraints.active.contains(cidx) {
+                    continue;
+                }
+
+                let sizes: Vec<usize> = search.vars.iter()
+                    .map(|state| match state {
+                        &VarState::Unassigned(ref cs) => cs.len(),
+                        _ => 0,
+                    })
+                    .collect();
+
+                let constraint = search.constraints.constraints[cidx].clone();
+                if constraint.on_updated(&mut search).is_err() {
+                    return None;
+                }
+
+                match search.first_singleton() {
+                    Err(()) => return None,
+                    Ok(Some((idx, val))) => {
+                        // A domain cut straight down to one value is
+                        // a direct force; merely losing the last
+                        // alternative is a naked single.
+                        let kind = if sizes[idx] > 2 {
+                            HintKind::ForcedAssignment
+                        } else {
+                            HintKind::NakedSingle
+                        };
+
+                        return Some(Hint {
+                            var: VarToken(idx),
+                            val: val,
+                            because: Some(cidx),
+                            kind: kind,
+                        });
+                    },
+                    Ok(None) => (),
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Estimate the puzzle's difficulty from the search behaviour
+    /// while looking for its first solution.
+    ///
+    /// The score is 0 if constraint propagation alone solves the
+    /// puzzle, and otherwise:
+    ///
+    /// ```text
+    ///   1 + guesses + 3 * backtracks + 10 * max_depth
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    /// puzzle.set_value(vars[0], 1);
+    ///
+    /// let report = puzzle.rate_difficulty();
+    /// assert!(report.propagation_only);
+    /// assert_eq!(report.score, 0);
+    /// ```
+    pub fn rate_difficulty(&mut self) -> DifficultyReport {
+        self.reset_stats();
+
+        // Capture which constraint kinds propagated while solving.
+        let kinds = Arc::new(Mutex::new(BTreeSet::new()));
+        let saved = self.metrics.take();
+        {
+            let kinds = kinds.clone();
+            self.metrics = Some(Box::new(move |metric| {
+                if let Metric::PropagationRun{ constraint_kind } = metric {
+                    kinds.lock().unwrap().insert(constraint_kind);
+                }
+            }));
+        }
+
+        let mut propagation_only = self.num_vars == 0;
+        let mut max_depth = 0;
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            if search.constrain().is_ok() {
+                if Solver::choose(&search).is_none() {
+                    propagation_only = true;
+                } else {
+                    search.solve_rated(1, &mut max_depth);
+                }
+            }
+        }
+
+        self.metrics = saved;
+
+        let propagation_kinds: Vec<&'static str>
+            = kinds.lock().unwrap().iter().cloned().collect();
+        let guesses = self.num_guesses();
+        let backtracks = self.num_backtracks();
+        let score = if propagation_only {
+            0
+        } else {
+            1 + guesses + 3 * backtracks + 10 * max_depth as u64
+        };
+
+        DifficultyReport {
+            propagation_only: propagation_only,
+            guesses: guesses,
+            backtracks: backtracks,
+            max_depth: max_depth,
+            propagation_kinds: propagation_kinds,
+            score: score,
+        }
+    }
+
+    /// Find constraints that appear to be entailed by the rest of
+    /// the puzzle, returned as indices in order of `add_constraint`.
+    ///
+    /// Each constraint is temporarily disabled in turn, and the
+    /// relaxed puzzle's solutions are counted with a cap of `budget`
+    /// solutions.  If the relaxed puzzle has exactly as many
+    /// solutions as the original, removing the constraint changed
+    /// nothing, and it is flagged as redundant.
+    ///
+    /// This is a heuristic diagnostic: if either count hits the
+    /// budget the check is inconclusive and nothing is flagged, and
+    /// the cost is one bounded enumeration per constraint.  It is
+    /// intended for simplifying generated models, e.g. dropping the
+    /// whole-number equation from a carry-model verbal arithmetic
+    /// puzzle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    /// puzzle.all_different(&vars);
+    ///
+    /// // Either all-different copy can stand in for the other.
+    /// assert_eq!(puzzle.find_redundant(10), &[0, 1]);
+    /// ```
+    pub fn find_redundant(&mut self, budget: usize) -> Vec<usize> {
+        let baseline = match self.solution_count(budget) {
+            SolutionCount::Exact(count) => count,
+            SolutionCount::AtLeast(_) => return Vec::new(),
+        };
+
+        let scratch = self.new_group("find_redundant");
+        self.set_group_enabled(scratch, false);
+        let GroupId(gidx) = scratch;
+
+        let mut redundant = Vec::new();
+        for cidx in 0..self.constraints.len() {
+            let saved = self.constraint_groups[cidx];
+            if let Some(old) = saved {
+                if !self.groups[old].1 {
+                    continue;
+                }
+            }
+
+            self.constraint_groups[cidx] = Some(gidx);
+            let relaxed = self.solution_count(budget);
+            self.constraint_groups[cidx] = saved;
+
+            if relaxed == SolutionCount::Exact(baseline) {
+                redundant.push(cidx);
+            }
+        }
+
+        self.groups.pop();
+        redundant
+    }
+
+    /// Factor common subexpressions out of the equations posted via
+    /// `equals`.
+    ///
+    /// Term pairs (a variable together with its coefficient)
+    /// occurring in at least `min_occurrences` equations are
+    /// replaced by a fresh auxiliary variable with one defining
+    /// equation.  The pass repeats until no pair is common enough,
+    /// so larger shared groups are factored over several rounds.
+    ///
+    /// Shrinking the equations' term counts tightens their bound
+    /// propagation, e.g. when the same word appears in many
+    /// equations of a cryptarithm.  Only pairs with integer
+    /// coefficients and a reasonably small auxiliary domain are
+    /// factored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(3, &[0,1,2,3]);
+    /// puzzle.equals(vars[0] + vars[1] + vars[2], 5);
+    /// puzzle.equals(vars[0] + vars[1] - vars[2], 3);
+    ///
+    /// // One auxiliary for the shared x + y, defined by one new
+    /// // equation; the solutions are unchanged.
+    /// puzzle.factor_common_subexpressions(2);
+    /// assert_eq!(puzzle.constraint_count(), 3);
+    /// assert_eq!(puzzle.solve_all().len(), 3);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_occurrences` is less than two.
+    pub fn factor_common_subexpressions(&mut self, min_occurrences: usize)
+            -> &mut Self {
+        assert!(min_occurrences >= 2);
+        while self.factor_one_pair(min_occurrences) {
+        }
+        self
+    }
+
+    /// Factor out the most common term pair across the recorded
+    /// equations.  Returns false if no pair occurs often enough.
+    fn factor_one_pair(&mut self, min_occurrences: usize) -> bool {
+        // The largest auxiliary domain worth materialising.
+        const MAX_AUX_DOMAIN: i64 = 10_000;
+
+        let mut counts = BTreeMap::new();
+        for &(_, ref eqn) in self.equalities.iter() {
+            let terms: Vec<(VarToken, Coef)> = eqn.coef.iter()
+                .map(|(&var, &coef)| (var, coef))
+                .collect();
+            for i in 0..terms.len() {
+                for j in (i + 1)..terms.len() {
+                    let count = counts.entry((terms[i], terms[j]))
+                        .or_insert(0);
+                    *count = *count + 1;
+                }
+            }
+        }
+
+        let mut best = None;
+        for (&pair, &count) in counts.iter() {
+            if count < min_occurrences {
+                continue;
+            } else if best.map_or(false, |(_, _, top)| count <= top) {
+                continue;
+            }
+
+            let ((var1, coef1), (var2, coef2)) = pair;
+            let (lo1, hi1) = match self.term_bounds(var1, coef1) {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+            let (lo2, hi2) = match self.term_bounds(var2, coef2) {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+
+            let (lo, hi) = (lo1 + lo2, hi1 + hi2);
+            if hi - lo >= MAX_AUX_DOMAIN
+                    || lo < Val::min_value() as i64
+                    || (Val::max_value() as i64) < hi {
+                continue;
+            }
+
+            best = Some((pair, (lo as Val, hi as Val), count));
+        }
+
+        let (((var1, coef1), (var2, coef2)), (lo, hi), _) = match best {
+            Some(best) => best,
+            None => return false,
+        };
+
+        let aux_candidates: Vec<Val> = (lo..=hi).collect();
+        let aux = self.new_var_with_candidates(&aux_candidates);
+
+        for i in 0..self.equalities.len() {
+            let (cidx, new_eqn) = {
+                let (cidx, ref eqn) = self.equalities[i];
+                if eqn.coef.get(&var1) != Some(&coef1)
+                        || eqn.coef.get(&var2) != Some(&coef2) {
+                    continue;
+                }
+
+                (cidx, eqn.without(var1).0.without(var2).0 + aux)
+            };
+
+            self.constraints[cidx]
+                = Rc::new(constraint::Equality::new(new_eqn.clone()));
+            self.equalities[i] = (cidx, new_eqn);
+        }
+
+        self.equals(aux, coef1 * var1 + coef2 * var2);
+        true
+    }
+
+    /// Bounds of coef * var over the root candidates, for terms with
+    /// integer coefficients.
+    fn term_bounds(&self, var: VarToken, coef: Coef) -> Option<(i64, i64)> {
+        if *coef.denom() != 1 {
+            return None;
+        }
+
+        let VarToken(idx) = var;
+        let (lo, hi) = match (self.candidates[idx].iter().min(),
+                self.candidates[idx].iter().max()) {
+            (Some(lo), Some(hi)) => (lo as i64, hi as i64),
+            _ => return None,
+        };
+
+        let coef = *coef.numer() as i64;
+        if coef >= 0 {
+            Some((coef * lo, coef * hi))
+        } else {
+            Some((coef * hi, coef * lo))
+        }
+    }
+
+    /// Split the puzzle into independent subproblems by expanding the
+    /// first `depth` choice points, returning the partial search
+    /// states at the frontier.
+    ///
+    /// Each subproblem can be completed independently with
+    /// `solve_all_from`, e.g. from different threads; the caller is
+    /// in control of the scheduling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    ///
+    /// assert_eq!(puzzle.frontier(1).len(), 2);
+    /// ```
+    pub fn frontier(&self, depth: usize) -> Vec<PuzzleSearch> {
+        self.reset_stats();
+
+        let mut frontier = Vec::new();
+        if self.num_vars > 0 {
+            frontier.push(PuzzleSearch::new(self));
+        }
+
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for mut search in frontier.into_iter() {
+                if search.constrain().is_err() {
+                    continue;
+                }
+
+                match Solver::choose(&search) {
+                    // Already fully assigned; keep as a leaf.
+                    None => next.push(search),
+
+                    Some((idx, vals)) => {
+                        for val in vals.into_iter() {
+                            let mut new = search.clone();
+                            if new.assign(idx, val).is_ok() {
+                                next.push(new);
+                            }
+                        }
+                    },
+                }
+            }
+
+            frontier = next;
+        }
+
+        frontier
+    }
+
+    /// Find all the solutions reachable from a partial search state,
+    /// e.g. a subproblem returned by `frontier`.  Concatenating the
+    /// solutions over a frontier gives the same set as `solve_all`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let vars = puzzle.new_vars_with_candidates_1d(2, &[1,2]);
+    /// puzzle.all_different(&vars);
+    ///
+    /// let total: usize = puzzle.frontier(1).iter()
+    ///     .map(|search| puzzle.solve_all_from(search).len())
+    ///     .sum();
+    /// assert_eq!(total, 2);
+    /// ```
+    pub fn solve_all_from(&self, search: &PuzzleSearch) -> Vec<Solution> {
+        let mut solutions = Vec::new();
+        let mut search = search.clone();
+        search.solve(::std::usize::MAX, &mut solutions, None);
+
+        solutions.sort_by(|a, b| a.vars.cmp(&b.vars));
+        solutions
+    }
+
+    /// Find all solutions to the given puzzle, sending each solution
+    /// over the channel as it is found.
+    ///
+    /// The bounded channel applies back-pressure to the solver when
+    /// the consumer is slow: `send` blocks when the channel is full.
+    /// When the receiver is dropped, the search stops.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (tx, rx) = std::sync::mpsc::sync_channel(1);
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    ///
+    /// std::thread::spawn(move || {
+    ///     let mut puzzle = puzzle_solver::Puzzle::new();
+    ///     puzzle.new_var_with_candidates(&[1,2]);
+    ///     puzzle.solve_all_streaming(tx);
+    /// });
+    ///
+    /// assert_eq!(rx.iter().count(), 2);
+    /// ```
+    pub fn solve_all_streaming(&mut self, tx: SyncSender<Solution>) {
+        self.reset_stats();
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            search.solve_streaming(&tx);
+        }
+
+        self.finish_stats();
+    }
+
+    /// Find any solution to the given puzzle using random restarts.
+    ///
+    /// Run a depth-first search with a randomised value ordering and
+    /// a limited node budget, doubling the budget after each failed
+    /// attempt, up to max_restarts attempts in total.  On puzzles
+    /// that cause deep backtracks, restarting often finds a solution
+    /// faster than exhausting a bad subtree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut rng = rand::thread_rng();
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// let solution = puzzle.solve_random_restart(&mut rng, 4);
+    /// assert!(solution.is_some());
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn solve_random_restart(&mut self, rng: &mut impl rand::Rng,
+            max_restarts: u32) -> Option<Solution> {
+        self.reset_stats();
+        let mut result = None;
+        if self.num_vars > 0 {
+            let mut budget = 64;
+            for _ in 0..max_restarts {
+                let mut search = PuzzleSearch::new(self);
+                let mut nodes = budget;
+                if let Some(solution) = search.solve_random(rng, &mut nodes) {
+                    result = Some(solution);
+                    break;
+                }
+
+                budget = budget.saturating_mul(2);
+            }
+        }
+
+        self.finish_stats();
+        result
+    }
+
+    /// Sample up to n solutions from the solution space.
+    ///
+    /// Each sample is drawn by a fresh depth-first search with a
+    /// randomised value ordering, deterministically seeded from
+    /// `seed` and the sample number.  This is best effort: solutions
+    /// reachable with few conflicts are somewhat over-represented,
+    /// so exact uniformity is not guaranteed.  The same puzzle and
+    /// seed always draw the same samples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    ///
+    /// let samples = puzzle.sample_solutions(10, 0);
+    /// assert_eq!(samples.len(), 10);
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn sample_solutions(&mut self, n: usize, seed: u64) -> Vec<Solution> {
+        use rand::SeedableRng;
+
+        self.reset_stats();
+        let mut samples = Vec::with_capacity(n);
+        if self.num_vars > 0 {
+            for sample in 0..n as u64 {
+                let mut rng = rand::rngs::StdRng::seed_from_u64(
+                        seed.wrapping_add(sample));
+                let mut search = PuzzleSearch::new(self);
+                let mut nodes = ::std::u32::MAX;
+                if let Some(solution)
+                        = search.solve_random(&mut rng, &mut nodes) {
+                    samples.push(solution);
+                }
+            }
+        }
+
+        self.finish_stats();
+        samples
+    }
+
+    /// Allocate an incremental solver for the given puzzle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    /// puzzle.new_var_with_candidates(&[3,4]);
+    ///
+    /// let mut solver = puzzle.solver();
+    /// loop {
+    ///     match solver.run_for(1) {
+    ///         puzzle_solver::SolverStatus::Running => (),
+    ///         puzzle_solver::SolverStatus::Solved(_) => break,
+    ///         puzzle_solver::SolverStatus::Unsat => panic!("unsat"),
+    ///     }
+    /// }
+    /// ```
+    pub fn solver(&mut self) -> Solver {
+        self.reset_stats();
+        Solver::new(self)
+    }
+
+    /// Take any obvious non-choices, using the constraints to
+    /// eliminate candidates.  Stops when it must start guessing.
+    /// Primarily for testing.
+    ///
+    /// Returns the intermediate puzzle search state, or None if a
+    /// contradiction was found.
+    pub fn step(&mut self) -> Option<PuzzleSearch> {
+        self.reset_stats();
+        let mut result = None;
+        if self.num_vars > 0 {
+            let mut search = PuzzleSearch::new(self);
+            if search.constrain().is_ok() {
+                result = Some(search);
+            }
+        }
+
+        self.finish_stats();
+        result
+    }
+
+    /// Get the number of guesses taken to solve the last puzzle,
+    /// i.e. the number of candidate values tried, including the ones
+    /// rejected immediately by the constraints.
+    pub fn num_guesses(&self) -> u64 {
+        self.num_guesses.get()
+    }
+
+    /// Get the number of choice points entered while solving the
+    /// last puzzle, i.e. the number of times the search had to pick
+    /// a variable to guess.  A puzzle solved by propagation alone
+    /// takes no decisions.
+    pub fn num_decisions(&self) -> u64 {
+        self.num_decisions.get()
+    }
+
+    /// Get the number of dead ends backtracked from while solving
+    /// the last puzzle.
+    pub fn num_backtracks(&self) -> u64 {
+        self.num_backtracks.get()
+    }
+
+    /// Get the number of gimme passes taken to solve the last
+    /// puzzle, i.e. the number of times the changed variables were
+    /// scanned for forced assignments.  Primarily for tuning.
+    pub fn num_gimme_passes(&self) -> u32 {
+        self.num_gimme_passes.get()
+    }
+
+    /// Reset the statistics of the last solve, and start the timer.
+    fn reset_stats(&self) {
+        self.num_decisions.set(0);
+        self.num_guesses.set(0);
+        self.num_backtracks.set(0);
+        self.num_gimme_passes.set(0);
+        self.solve_started.set(Some(Instant::now()));
+    }
+
+    /// Stop the timer started by `reset_stats`.
+    fn finish_stats(&self) {
+        if let Some(started) = self.solve_started.take() {
+            self.elapsed.set(started.elapsed());
+        }
+    }
+
+    /// Get the statistics of the last solve, including its
+    /// wall-clock time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// puzzle.new_var_with_candidates(&[1,2]);
+    ///
+    /// puzzle.solve_all();
+    /// let stats = puzzle.last_stats();
+    /// assert_eq!(stats.guesses, puzzle.num_guesses());
+    /// ```
+    pub fn last_stats(&self) -> SearchStats {
+        SearchStats {
+            elapsed: self.elapsed.get(),
+            decisions: self.num_decisions.get(),
+            guesses: self.num_guesses.get(),
+            backtracks: self.num_backtracks.get(),
+        }
+    }
+
+    /// Install a callback to receive solver metrics, e.g. to feed
+    /// counters to an external monitoring system.
+    ///
+    /// While solving, the sink receives one `GuessTaken` per guess
+    /// (matching `num_guesses`), one `Backtrack` per dead end, one
+    /// `SolutionFound` per solution, and one `PropagationRun` per
+    /// woken constraint.  `PropagationRun` is by far the most
+    /// frequent, typically orders of magnitude more than the
+    /// guesses, so sample it if the sink is expensive.  When no sink
+    /// is installed the cost per event is a single Option test.
+    pub fn set_metrics_sink(&mut self, sink: Box<Fn(Metric) + Send>) {
+        self.metrics = Some(sink);
+    }
+
+    /// Enable or disable the domain-wipeout traces, e.g. to find out
+    /// why a puzzle is unexpectedly unsatisfiable.
+    ///
+    /// When enabled, any `remove_candidate`, `set_candidate` or
+    /// `bound_candidate_range` that empties a variable's domain (or
+    /// contradicts its assignment) prints the variable, the failed
+    /// operation and the constraint currently propagating to stderr.
+    /// When disabled the cost per operation is a single bool test.
+    pub fn set_debug(&mut self, enabled: bool) {
+        self.debug = enabled;
+        self.last_wipeout.set(None);
+    }
+
+    /// Get the variable of the most recent domain wipeout.  Only
+    /// recorded while the debug traces are enabled, see `set_debug`.
+    pub fn last_wipeout(&self) -> Option<VarToken> {
+        self.last_wipeout.get()
+    }
+
+    /// Emit a metric to the sink, if one is installed.
+    fn emit(&self, metric: Metric) {
+        if let Some(ref sink) = self.metrics {
+            sink(metric);
+        }
+    }
+
+    /// Count a guess towards the guess counter and the metrics.
+    fn take_guess(&self) {
+        self.num_guesses.set(self.num_guesses.get().saturating_add(1));
+        self.emit(Metric::GuessTaken);
+    }
+
+    /// Count a choice point towards the decision counter.
+    fn take_decision(&self) {
+        self.num_decisions.set(self.num_decisions.get().saturating_add(1));
+    }
+
+    /// Count a dead end towards the backtrack counter and the metrics.
+    fn take_backtrack(&self) {
+        self.num_backtracks.set(self.num_backtracks.get().saturating_add(1));
+        self.emit(Metric::Backtrack);
+    }
+
+    /// Register a human-readable name for a variable, for use with
+    /// `Solution::format`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2,3]);
+    ///
+    /// puzzle.name_var("x", var);
+    /// assert_eq!(puzzle.lookup_var("x"), Some(var));
+    /// ```
+    pub fn name_var(&mut self, name: &str, var: VarToken) {
+        self.var_names.insert(name.to_string(), var);
+    }
+
+    /// Register a human-readable name for a grid of variables, for
+    /// use with `Solution::format`.
+    pub fn name_grid(&mut self, name: &str, grid: &[Vec<VarToken>]) {
+        self.grid_names.insert(name.to_string(), grid.to_vec());
+    }
+
+    /// Look up a variable by its registered name.
+    pub fn lookup_var(&self, name: &str) -> Option<VarToken> {
+        self.var_names.get(name).cloned()
+    }
+
+    /// Look up a grid of variables by its registered name.
+    pub fn lookup_grid(&self, name: &str) -> Option<&Vec<Vec<VarToken>>> {
+        self.grid_names.get(name)
+    }
+
+    /// Drop any cached search state, so that the next solve rebuilds
+    /// from the puzzle's current candidates.
+    ///
+    /// The solve methods always derive their search state from the
+    /// current candidates, so statistics from the previous solve
+    /// (e.g. the guess counter) are the only state discarded here.
+    /// Call this after mutating candidates between solves to mark
+    /// the earlier results as stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut puzzle = puzzle_solver::Puzzle::new();
+    /// let var = puzzle.new_var_with_candidates(&[1,2,3]);
+    /// puzzle.solve_all();
+    ///
+    /// puzzle.remove_candidates(var, &[3]);
+    /// puzzle.invalidate();
+    /// assert_eq!(puzzle.solve_all().len(), 2);
+    /// ```
+    pub fn invalidate(&mut self) {
+        self.reset_stats();
+    }
+
+    /// Enable or disable evaluation of woken constraints in
+    /// variable-disjoint groups.
+    ///
+    /// Within one propagation round, woken constraints that share no
+    /// variables cannot observe each other's domain reductions, so
+    /// they are evaluated against isolated copies of the search
+    /// state and their reductions merged afterwards.  This produces
+    /// the same fixpoint as the sequential path.
+    ///
+    /// The groups are evaluated one after another on the calling
+    /// thread: the constraint objects and candidate sets are
+    /// reference counted and so cannot be handed to a worker pool.
+    /// The grouping and merging is the part of the redesign that a
+    /// parallel evaluation would need either way.
+    #[cfg(feature = "grouped")]
+    pub fn set_grouped(&mut self, enabled: bool) {
+        self.grouped = enabled;
+    }
+
+    /// Find all solutions to the given puzzle by brute force,
+    /// checking every combination of candidates against the
+    /// constraints.
+    ///
+    /// This is a reference implementation to compare the solution
+    /// search against, e.g. in property tests.  It is only practical
+    /// for very small puzzles.
+    #[cfg(feature = "proptest")]
+    pub fn brute_force(&self) -> Vec<Solution> {
+        let mut solutions = Vec::new();
+
+        if self.num_vars > 0 {
+            let mut assignment = Vec::with_capacity(self.num_vars);
+            self.brute_force_next(&mut assignment, &mut solutions);
+        }
+
+        solutions
+    }
+
+    /// Enumerate all combinations of candidates recursively.
+    #[cfg(feature = "proptest")]
+    fn brute_force_next(&self, assignment: &mut Vec<Val>,
+            solutions: &mut Vec<Solution>) {
+        let idx = assignment.len();
+        if idx >= self.num_vars {
+            if self.brute_force_check(assignment) {
+                solutions.push(Solution{ vars: assignment.clone() });
+            }
+            return;
+        }
+
+        let vals: Vec<Val> = self.candidates[idx].iter().collect();
+        for val in vals.into_iter() {
+            assignment.push(val);
+            self.brute_force_next(assignment, solutions);
+            assignment.pop();
+        }
+    }
+
+    /// Check a complete assignment against the constraints.
+    #[cfg(feature = "proptest")]
+    fn brute_force_check(&self, assignment: &[Val]) -> bool {
+        let mut search = PuzzleSearch::new(self);
+
+        for (idx, &val) in assignment.iter().enumerate() {
+            if search.set_candidate(VarToken(idx), val).is_err() {
+                return false;
+            }
+        }
+
+        search.constrain().is_ok()
+            && (0..self.num_vars).all(|idx| {
+                    let val = assignment[idx];
+                    search.get_min_max(VarToken(idx)) == Ok((val, val))
+                })
+    }
+}
+
+/*--------------------------------------------------------------*/
+
+impl SolvedSearch {
+    /// Get the value assigned to a variable.
+    pub fn get_assigned(&self, var: VarToken) -> Option<Val> {
+        let VarToken(idx) = var;
+        match &self.vars[idx] {
+            &VarState::Assigned(val) => Some(val),
+            &VarState::Unassigned(_) => None,
+            &VarState::Unified(other) => self.get_assigned(other),
+        }
+    }
+
+    /// Check if the variable was unified away during the search,
+    /// returning the variable that replaced it.
+    pub fn get_unified(&self, var: VarToken) -> Option<VarToken> {
+        let VarToken(idx) = var;
+        match &self.vars[idx] {
+            &VarState::Unified(other) => Some(other),
+            _ => None,
+        }
+    }
+}
+
+/*--------------------------------------------------------------*/
+
+impl PuzzleConstraints {
     /// Allocate a new puzzle constraint container.
     fn new(puzzle: &Puzzle) -> Self {
-        let wake = Self::init_wake(&puzzle.constraints, puzzle.num_vars);
+        let mut active = BitSet::new();
+        for cidx in 0..puzzle.constraints.len() {
+            let enabled = match puzzle.constraint_groups[cidx] {
+                Some(gidx) => puzzle.groups[gidx].1,
+                None => true,
+            };
+
+            if enabled {
+                active.insert(cidx);
+            }
+        }
+
+        let wake = Self::init_wake(&puzzle.constraints, puzzle.num_vars,
+                &active);
+
         PuzzleConstraints {
             constraints: puzzle.constraints.clone(),
             wake: wake,
+            active: active,
+        }
+    }
+
+    /// Create a new puzzle constraint container reflecting the
+    /// substitution of "from" with "to".
+    fn substitute(&self, from: VarToken, to: VarToken) -> PsResult<Self> {
+        let VarToken(idx) = from;
+        let mut new_constraints = self.constraints.clone();
+        let mut active = self.active.clone();
+
+        for cidx in self.wake[idx].iter() {
+            let rc = try!(self.constraints[cidx].substitute(from, to));
+            if rc.vars().next().is_none() {
+                // e.g. Unify(a, b) substituted into Unify(b, b).
+                active.remove(cidx);
+            }
+            new_constraints[cidx] = rc;
+        }
+
+        let wake = Self::init_wake(&new_constraints, self.wake.len(), &active);
+        Ok(PuzzleConstraints {
+            constraints: new_constraints,
+            wake: wake,
+            active: active,
+        })
+    }
+
+    /// Determine which variables wake up which constraints, skipping
+    /// the inactive (e.g. disabled) constraints.
+    fn init_wake(constraints: &Vec<Rc<Constraint>>, num_vars: usize,
+            active: &BitSet) -> Vec<BitSet> {
+        let mut wake = vec![BitSet::new(); num_vars];
+        for cidx in 0..constraints.len() {
+            if !active.contains(cidx) {
+                continue;
+            }
+
+            for &VarToken(idx) in constraints[cidx].vars() {
+                wake[idx].insert(cidx);
+            }
+        }
+
+        wake
+    }
+}
+
+/*--------------------------------------------------------------*/
+
+impl<'a> Solver<'a> {
+    /// Allocate a new incremental solver.
+    fn new(puzzle: &'a Puzzle) -> Self {
+        let pending = if puzzle.num_vars > 0 {
+            Some(PuzzleSearch::new(puzzle))
+        } else {
+            None
+        };
+
+        Solver {
+            puzzle: puzzle,
+            stack: Vec::new(),
+            pending: pending,
+            done: puzzle.num_vars == 0,
+        }
+    }
+
+    /// Run the search until it finds a solution, exhausts the search
+    /// space, or has taken max_guesses guesses, whichever comes
+    /// first.
+    ///
+    /// After a solution has been found, running the solver again
+    /// continues the search for the next solution.
+    pub fn run_for(&mut self, max_guesses: u32) -> SolverStatus {
+        if self.done {
+            return SolverStatus::Unsat;
+        }
+
+        let mut guesses = 0;
+
+        loop {
+            // Expand the pending search state, if any.
+            if let Some(mut search) = self.pending.take() {
+                if search.constrain().is_ok() {
+                    match Self::choose(&search) {
+                        Some((idx, vals)) => {
+                            if !vals.is_empty() {
+                                self.puzzle.take_decision();
+                                self.stack.push(SolverFrame {
+                                    search: search,
+                                    var_idx: idx,
+                                    vals: vals,
+                                    pos: 0,
+                                });
+                            } else {
+                                self.puzzle.take_backtrack();
+                            }
+                        },
+
+                        None => {
+                            // No unassigned variables remaining.
+                            let vars = (0..self.puzzle.num_vars).map(|idx|
+                                    search[VarToken(idx)]).collect();
+                            self.puzzle.emit(Metric::SolutionFound);
+                            return SolverStatus::Solved(Solution{ vars: vars });
+                        },
+                    }
+                } else {
+                    self.puzzle.take_backtrack();
+                }
+            }
+
+            // Take the next untried candidate from the top of the
+            // search stack.
+            loop {
+                let mut pop = false;
+                match self.stack.last_mut() {
+                    None => {
+                        self.done = true;
+                        return SolverStatus::Unsat;
+                    },
+
+                    Some(frame) => {
+                        if frame.pos >= frame.vals.len() {
+                            pop = true;
+                        } else {
+                            if guesses >= max_guesses {
+                                return SolverStatus::Running;
+                            }
+
+                            let val = frame.vals[frame.pos];
+                            frame.pos = frame.pos + 1;
+                            guesses = guesses + 1;
+
+                            self.puzzle.take_guess();
+
+                            let mut new = frame.search.clone();
+                            if new.assign(frame.var_idx, val).is_ok() {
+                                self.pending = Some(new);
+                            } else {
+                                self.puzzle.take_backtrack();
+                            }
+                        }
+                    },
+                }
+
+                if pop {
+                    self.stack.pop();
+                } else if self.pending.is_some() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Serialise the solver's decision stack so that the search can
+    /// be resumed later, e.g. after a process restart.
+    ///
+    /// Only the decisions (variable, value, next-candidate cursor)
+    /// are recorded, not the variable domains.  The decisions are
+    /// replayed on restore, so the solver must be restored against a
+    /// puzzle built in exactly the same way.
+    pub fn checkpoint(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.push(self.done as u8);
+        data.push(self.pending.is_some() as u8);
+        data.extend_from_slice(&(self.stack.len() as u32).to_le_bytes());
+
+        for frame in self.stack.iter() {
+            let val = if frame.pos > 0 { frame.vals[frame.pos - 1] } else { 0 };
+            data.extend_from_slice(&(frame.var_idx as u32).to_le_bytes());
+            data.extend_from_slice(&val.to_le_bytes());
+            data.extend_from_slice(&(frame.pos as u32).to_le_bytes());
+        }
+
+        data
+    }
+
+    /// Rebuild a solver from a checkpoint, replaying the recorded
+    /// decisions against the given puzzle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint data is malformed, or if the replay
+    /// diverges from the recorded decisions (i.e. the puzzle was not
+    /// built in the same way as the one that was checkpointed).
+    pub fn restore(puzzle: &'a Puzzle, data: &[u8]) -> Self {
+        fn read_u32(data: &[u8], pos: &mut usize) -> u32 {
+            assert!(*pos + 4 <= data.len(), "checkpoint truncated");
+            let bytes = [data[*pos], data[*pos + 1],
+                         data[*pos + 2], data[*pos + 3]];
+            *pos = *pos + 4;
+            u32::from_le_bytes(bytes)
+        }
+
+        assert!(data.len() >= 6, "checkpoint truncated");
+        let done = data[0] != 0;
+        let has_pending = data[1] != 0;
+
+        let mut pos = 2;
+        let num_frames = read_u32(data, &mut pos) as usize;
+
+        let mut solver = Solver {
+            puzzle: puzzle,
+            stack: Vec::with_capacity(num_frames),
+            pending: None,
+            done: done,
+        };
+
+        if !has_pending && num_frames == 0 {
+            return solver;
+        }
+
+        let mut search = Some(PuzzleSearch::new(puzzle));
+
+        for _ in 0..num_frames {
+            let var_idx = read_u32(data, &mut pos) as usize;
+            let val = read_u32(data, &mut pos) as Val;
+            let frame_pos = read_u32(data, &mut pos) as usize;
+
+            let mut cur = search.take().expect("replay: missing choice point");
+            cur.constrain().expect("replay: constrain failed");
+            let (idx, vals) = Self::choose(&cur)
+                .expect("replay: no variable to guess");
+            assert_eq!(idx, var_idx, "replay diverged from checkpoint");
+
+            if frame_pos > 0 {
+                assert_eq!(vals[frame_pos - 1], val,
+                        "replay diverged from checkpoint");
+                let mut child = cur.clone();
+                child.assign(idx, val).expect("replay: assign failed");
+                search = Some(child);
+            }
+
+            solver.stack.push(SolverFrame {
+                search: cur,
+                var_idx: idx,
+                vals: vals,
+                pos: frame_pos,
+            });
+        }
+
+        if has_pending {
+            solver.pending = Some(
+                search.expect("replay: missing choice point"));
+        }
+
+        solver
+    }
+
+    /// Choose the next variable to guess, returning its index and
+    /// candidates, or None if all variables have been assigned.
+    fn choose(search: &PuzzleSearch<'a>) -> Option<(usize, Vec<Val>)> {
+        let next_unassigned = search.vars.iter().enumerate().min_by_key(
+                |&(_, vs)| match vs {
+                    &VarState::Unassigned(ref cs) => cs.len(),
+                    _ => ::std::usize::MAX,
+                });
+
+        match next_unassigned {
+            Some((idx, &VarState::Unassigned(ref cs))) =>
+                Some((idx, cs.iter().collect())),
+            _ => None,
+        }
+    }
+}
+
+/*--------------------------------------------------------------*/
+
+impl<'a> PuzzleSearch<'a> {
+    /// Allocate a new puzzle searcher.
+    fn new(puzzle: &'a Puzzle) -> Self {
+        let constraints = PuzzleConstraints::new(puzzle);
+        let vars = puzzle.candidates.iter().map(|cs|
+                VarState::Unassigned(cs.clone())).collect();
+        let mut wake = BitSet::new();
+
+        for cidx in 0..constraints.constraints.len() {
+            wake.insert(cidx);
+        }
+
+        let mut dirty = BitSet::new();
+        for idx in 0..puzzle.num_vars {
+            dirty.insert(idx);
+        }
+
+        PuzzleSearch {
+            puzzle: puzzle,
+            constraints: Rc::new(constraints),
+            vars: vars,
+            wake: wake,
+            dirty: dirty,
+        }
+    }
+
+    /// Check if the variable has been assigned to a value.
+    ///
+    /// Variables given as part of the problem (via `set_value`) are
+    /// treated as assigned from the start of the search.
+    pub fn is_assigned(&self, var: VarToken) -> bool {
+        let VarToken(idx) = var;
+        match &self.vars[idx] {
+            &VarState::Assigned(_) => true,
+            &VarState::Unassigned(ref cs) => match cs {
+                &Candidates::Value(_) => true,
+                _ => false,
+            },
+            &VarState::Unified(other) => self.is_assigned(other),
+        }
+    }
+
+    /// Get the value assigned to a variable, or None.
+    ///
+    /// This should be used if the variable may potentially be
+    /// unassigned.  For example, when implementing constraints.
+    pub fn get_assigned(&self, var: VarToken) -> Option<Val> {
+        let VarToken(idx) = var;
+        match &self.vars[idx] {
+            &VarState::Assigned(val) => Some(val),
+            &VarState::Unassigned(ref cs) => match cs {
+                &Candidates::Value(val) => Some(val),
+                _ => None,
+            },
+            &VarState::Unified(other) => self.get_assigned(other),
+        }
+    }
+
+    /// Get an iterator over the candidates to an unassigned variable.
+    ///
+    /// Assigned variables, including variables given as part of the
+    /// problem, have no unassigned candidates.
+    pub fn get_unassigned<'b>(&'b self, var: VarToken)
+            -> Box<Iterator<Item=Val> + 'b> {
+        let VarToken(idx) = var;
+        match &self.vars[idx] {
+            &VarState::Assigned(_) => Box::new(iter::empty()),
+            &VarState::Unassigned(ref cs) => match cs {
+                &Candidates::Value(_) => Box::new(iter::empty()),
+                _ => cs.iter(),
+            },
+            &VarState::Unified(other) => self.get_unassigned(other),
+        }
+    }
+
+    /// Get an iterator over the variables that still need deciding,
+    /// i.e. unassigned with more than one candidate.
+    ///
+    /// Variables that have been unified away are not yielded; they
+    /// are represented by their replacement.
+    pub fn unassigned_vars(&'a self) -> Box<Iterator<Item=VarToken> + 'a> {
+        Box::new(self.vars.iter().enumerate()
+            .filter_map(|(idx, vs)| match vs {
+                &VarState::Unassigned(ref cs) if cs.len() > 1 =>
+                    Some(VarToken(idx)),
+                _ => None,
+            }))
+    }
+
+    /// Check if every active constraint reports that its filtering
+    /// is at fixpoint, see `Constraint::check_consistency`.
+    ///
+    /// Constraints reporting Unknown are treated optimistically, so
+    /// this only detects missed propagation in the constraints that
+    /// implement the check.
+    pub fn is_arc_consistent(&self) -> bool {
+        self.constraints.active.iter()
+            .all(|cidx| self.constraints.constraints[cidx]
+                .check_consistency(self)
+                    != ConsistencyLevel::NotConsistent)
+    }
+
+    /// Get the minimum and maximum values for variable.
+    pub fn get_min_max(&self, var: VarToken) -> PsResult<(Val, Val)> {
+        let VarToken(idx) = var;
+        match &self.vars[idx] {
+            &VarState::Assigned(val) => Ok((val, val)),
+            &VarState::Unassigned(ref cs) => match cs {
+                &Candidates::None => Err(()),
+                &Candidates::Value(val) => Ok((val, val)),
+                &Candidates::Set(ref rc) => {
+                    rc.iter().cloned().min().into_iter()
+                        .zip(rc.iter().cloned().max()).next()
+                        .ok_or(())
+                }
+            },
+            &VarState::Unified(other) => self.get_min_max(other),
+        }
+    }
+
+    /// Get a reference to the constraint at the given index, as
+    /// active at this search node, i.e. possibly with variables
+    /// substituted out.
+    ///
+    /// Together with `Puzzle::constraint_count`, this allows user
+    /// code to iterate over all active constraints, e.g. to build a
+    /// constraint graph.
+    pub fn get_constraint(&self, idx: usize) -> &Constraint {
+        &*self.constraints.constraints[idx]
+    }
+
+    /// Apply the constraint at the given index directly, bypassing
+    /// the normal wake mechanism.
+    ///
+    /// This is useful for testing constraint implementations:
+    /// manually place some assignments, then apply a single
+    /// constraint to see exactly what propagation it performs.
+    pub fn apply_constraint(&mut self, idx: usize) -> PsResult<()> {
+        let constraint = self.constraints.constraints[idx].clone();
+        self.puzzle.emit(Metric::PropagationRun {
+            constraint_kind: self.puzzle.constraint_kinds[idx],
+        });
+        constraint.on_updated(self)
+    }
+
+    /// Get the number of constraints that are still active at this
+    /// search node.  Constraints that become trivially true after a
+    /// substitution, e.g. Unify(a, b) rewritten into Unify(b, b), are
+    /// dropped from the active set and are never applied again.
+    pub fn active_constraint_count(&self) -> usize {
+        self.constraints.active.len()
+    }
+
+    /// Assign every variable that is down to a single candidate,
+    /// returning how many were assigned.
+    ///
+    /// This is a single pass over the variables, not run to fixpoint:
+    /// variables reduced to singletons by the resulting propagation
+    /// are left unassigned, e.g. for controlled stepping in an
+    /// interactive solver.
+    pub fn assign_singletons(&mut self) -> PsResult<usize> {
+        let singletons: Vec<(usize, Val)> = self.vars.iter().enumerate()
+            .filter_map(|(idx, vs)| match vs {
+                &VarState::Unassigned(ref cs) if cs.len() == 1 =>
+                    Some((idx, cs.iter().next().expect("candidate"))),
+                _ => None,
+            })
+            .collect();
+
+        let count = singletons.len();
+        for (idx, val) in singletons.into_iter() {
+            try!(self.assign(idx, val));
+        }
+
+        Ok(count)
+    }
+
+    /// Report a failed domain operation to stderr, see
+    /// `Puzzle::set_debug`.
+    fn trace_wipeout(&self, var: VarToken, op: String) {
+        self.puzzle.last_wipeout.set(Some(var));
+        match self.puzzle.propagating.get() {
+            Some(cidx) => eprintln!(
+                    "puzzle-solver: {} failed on {:?} (constraint {} = {})",
+                    op, var, cidx, self.puzzle.constraint_kinds[cidx]),
+            None => eprintln!("puzzle-solver: {} failed on {:?}", op, var),
+        }
+    }
+
+    /// Set a variable to a value.
+    pub fn set_candidate(&mut self, var: VarToken, val: Val)
+            -> PsResult<()> {
+        let result = self.set_candidate_impl(var, val);
+        if result.is_err() && self.puzzle.debug {
+            self.trace_wipeout(var, format!("set candidate {}", val));
+        }
+        result
+    }
+
+    fn set_candidate_impl(&mut self, var: VarToken, val: Val)
+            -> PsResult<()> {
+        let VarToken(idx) = var;
+
+        match &self.vars[idx] {
+            &VarState::Assigned(v) => return bool_to_result(v == val),
+            &VarState::Unassigned(ref cs) => match cs {
+                &Candidates::None => return Err(()),
+                &Candidates::Value(v) => return bool_to_result(v == val),
+                &Candidates::Set(_) => (),
+            },
+            &VarState::Unified(_) => (),
+        }
+
+        if let &VarState::Unified(other) = &self.vars[idx] {
+            self.set_candidate_impl(other, val)
+        } else if let &mut VarState::Unassigned(Candidates::Set(ref mut rc))
+                = &mut self.vars[idx] {
+            if rc.contains(&val) {
+                let mut set = Rc::make_mut(rc);
+                set.clear();
+                set.insert(val);
+                self.wake.union_with(&self.constraints.wake[idx]);
+                self.dirty.insert(idx);
+                Ok(())
+            } else {
+                Err(())
+            }
+        } else {
+            unreachable!();
+        }
+    }
+
+    /// Remove a single candidate from a variable.
+    pub fn remove_candidate(&mut self, var: VarToken, val: Val)
+            -> PsResult<()> {
+        let result = self.remove_candidate_impl(var, val);
+        if result.is_err() && self.puzzle.debug {
+            self.trace_wipeout(var, format!("remove candidate {}", val));
+        }
+        result
+    }
+
+    fn remove_candidate_impl(&mut self, var: VarToken, val: Val)
+            -> PsResult<()> {
+        let VarToken(idx) = var;
+
+        match &self.vars[idx] {
+            &VarState::Assigned(v) => return bool_to_result(v != val),
+            &VarState::Unassigned(ref cs) => match cs {
+                &Candidates::None => return Err(()),
+                &Candidates::Value(v) => return bool_to_result(v != val),
+                &Candidates::Set(_) => (),
+            },
+            &VarState::Unified(_) => (),
+        }
+
+        if let &VarState::Unified(other) = &self.vars[idx] {
+            self.remove_candidate_impl(other, val)
+        } else if let &mut VarState::Unassigned(Candidates::Set(ref mut rc))
+                = &mut self.vars[idx] {
+            if rc.contains(&val) {
+                let mut set = Rc::make_mut(rc);
+                set.remove(&val);
+                self.wake.union_with(&self.constraints.wake[idx]);
+                self.dirty.insert(idx);
+            }
+            bool_to_result(!rc.is_empty())
+        } else {
+            unreachable!();
+        }
+    }
+
+    /// Bound an variable to the given range.
+    pub fn bound_candidate_range(&mut self, var: VarToken, min: Val, max: Val)
+            -> PsResult<(Val, Val)> {
+        let result = self.bound_candidate_range_impl(var, min, max);
+        if result.is_err() && self.puzzle.debug {
+            self.trace_wipeout(var, format!("bound to {}..={}", min, max));
+        }
+        result
+    }
+
+    fn bound_candidate_range_impl(&mut self, var: VarToken, min: Val, max: Val)
+            -> PsResult<(Val, Val)> {
+        let VarToken(idx) = var;
+
+        match &self.vars[idx] {
+            &VarState::Assigned(v) =>
+                if min <= v && v <= max {
+                    return Ok((v, v))
+                } else {
+                    return Err(())
+                },
+            &VarState::Unassigned(ref cs) => match cs {
+                &Candidates::None => return Err(()),
+                &Candidates::Value(v) =>
+                    if min <= v && v <= max {
+                        return Ok((v, v))
+                    } else {
+                        return Err(())
+                    },
+                &Candidates::Set(_) => (),
+            },
+            &VarState::Unified(_) => (),
+        }
+
+        if let &VarState::Unified(other) = &self.vars[idx] {
+            self.bound_candidate_range_impl(other, min, max)
+        } else if let &mut VarState::Unassigned(Candidates::Set(ref mut rc))
+                = &mut self.vars[idx] {
+            // The set may already have been emptied by another
+            // constraint that ignored the resulting Err earlier in
+            // the same propagation round.
+            let (curr_min, curr_max) = try!(rc.iter().cloned().min().into_iter()
+                    .zip(rc.iter().cloned().max()).next()
+                    .ok_or(()));
+
+            if curr_min < min || max < curr_max {
+                {
+                    let mut set = Rc::make_mut(rc);
+                    *set = set.iter()
+                        .filter(|&val| min <= *val && *val <= max)
+                        .cloned()
+                        .collect();
+                    self.wake.union_with(&self.constraints.wake[idx]);
+                    self.dirty.insert(idx);
+                }
+                rc.iter().cloned().min().into_iter()
+                    .zip(rc.iter().cloned().max()).next()
+                    .ok_or(())
+            } else {
+                Ok((curr_min, curr_max))
+            }
+        } else {
+            unreachable!();
+        }
+    }
+
+    /// Solve the puzzle, finding up to count solutions.
+    ///
+    /// If a capture slot is given, it is filled with a snapshot of
+    /// the search state at the most recently found solution.
+    fn solve(&mut self, count: usize, solutions: &mut Vec<Solution>,
+            mut capture: Option<&mut Option<SolvedSearch>>) {
+        // Depth-first search over an explicit stack of choice points,
+        // rather than the call stack: deep models (thousands of
+        // chained variables) would otherwise overflow the thread
+        // stack and abort.
+        let mut stack: Vec<SolverFrame<'a>> = Vec::new();
+        let mut pending = Some(self.clone());
+
+        loop {
+            // Expand the pending search state, if any.
+            if let Some(mut search) = pending.take() {
+                if search.constrain().is_ok() {
+                    match Solver::choose(&search) {
+                        Some((idx, vals)) => {
+                            if !vals.is_empty() {
+                                self.puzzle.take_decision();
+                                stack.push(SolverFrame {
+                                    search: search,
+                                    var_idx: idx,
+                                    vals: vals,
+                                    pos: 0,
+                                });
+                            } else {
+                                // Contradiction.
+                                self.puzzle.take_backtrack();
+                            }
+                        },
+
+                        None => {
+                            // No unassigned variables remaining.
+                            let vars = (0..self.puzzle.num_vars).map(|idx|
+                                    search[VarToken(idx)]).collect();
+                            solutions.push(Solution{ vars: vars });
+                            self.puzzle.emit(Metric::SolutionFound);
+
+                            if let Some(capture) = capture.as_deref_mut() {
+                                *capture = Some(SolvedSearch{
+                                    vars: search.vars.clone(),
+                                });
+                            }
+
+                            if solutions.len() >= count {
+                                // Reached desired number of solutions.
+                                return;
+                            }
+                        },
+                    }
+                } else {
+                    self.puzzle.take_backtrack();
+                }
+            }
+
+            // Take the next untried candidate from the top of the
+            // search stack.
+            loop {
+                let mut pop = false;
+                match stack.last_mut() {
+                    None => return,
+
+                    Some(frame) => {
+                        if frame.pos >= frame.vals.len() {
+                            pop = true;
+                        } else {
+                            let val = frame.vals[frame.pos];
+                            frame.pos = frame.pos + 1;
+
+                            self.puzzle.take_guess();
+
+                            let mut new = frame.search.clone();
+                            if new.assign(frame.var_idx, val).is_ok() {
+                                pending = Some(new);
+                            } else {
+                                self.puzzle.take_backtrack();
+                            }
+                        }
+                    },
+                }
+
+                if pop {
+                    stack.pop();
+                } else if pending.is_some() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Solve the puzzle, ordering the values at each branching point
+    /// by how constrained the puzzle becomes after assigning them.
+    fn solve_lookahead(&mut self, depth: usize) -> Option<Solution> {
+        if self.constrain().is_err() {
+            self.puzzle.take_backtrack();
+            return None;
+        }
+
+        let next_unassigned = self.vars.iter().enumerate().min_by_key(
+                |&(_, vs)| match vs {
+                    &VarState::Unassigned(ref cs) => cs.len(),
+                    _ => ::std::usize::MAX,
+                });
+
+        if let Some((idx, &VarState::Unassigned(ref cs))) = next_unassigned {
+            if cs.len() == 0 {
+                // Contradiction.
+                self.puzzle.take_backtrack();
+                return None;
+            }
+
+            self.puzzle.take_decision();
+
+            // Look ahead: propagate each value, and order by the
+            // least total remaining candidates.  Values leading to a
+            // contradiction are pruned outright.
+            let mut scored = Vec::new();
+            for val in cs.iter() {
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    continue;
+                }
+
+                if let Some(score) = new.lookahead_score(depth) {
+                    scored.push((score, val));
+                }
+            }
+
+            scored.sort();
+
+            for (_, val) in scored.into_iter() {
+                self.puzzle.take_guess();
+
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    self.puzzle.take_backtrack();
+                    continue;
+                }
+
+                if let Some(solution) = new.solve_lookahead(depth) {
+                    return Some(solution);
+                }
+            }
+
+            None
+        } else {
+            // No unassigned variables remaining.
+            let vars = (0..self.puzzle.num_vars).map(|idx|
+                    self[VarToken(idx)]).collect();
+            self.puzzle.emit(Metric::SolutionFound);
+            Some(Solution{ vars: vars })
+        }
+    }
+
+    /// Estimate how constrained the search becomes, by propagating
+    /// the constraints and summing the remaining candidates over all
+    /// variables.  Nested propagation rounds explore depth levels of
+    /// assignments to the most constrained variable.
+    ///
+    /// Returns None if a contradiction was found.
+    fn lookahead_score(mut self, depth: usize) -> Option<usize> {
+        if self.constrain().is_err() {
+            return None;
+        }
+
+        let total = self.vars.iter().map(|vs| match vs {
+            &VarState::Unassigned(ref cs) => cs.len(),
+            _ => 0,
+        }).sum::<usize>();
+
+        if depth <= 1 {
+            return Some(total);
+        }
+
+        let next_unassigned = self.vars.iter().enumerate().min_by_key(
+                |&(_, vs)| match vs {
+                    &VarState::Unassigned(ref cs) => cs.len(),
+                    _ => ::std::usize::MAX,
+                });
+
+        if let Some((idx, &VarState::Unassigned(ref cs))) = next_unassigned {
+            if cs.len() == 0 {
+                return None;
+            }
+
+            let mut best = None;
+            for val in cs.iter() {
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    continue;
+                }
+
+                if let Some(score) = new.lookahead_score(depth - 1) {
+                    best = Some(match best {
+                        Some(prev) => ::std::cmp::min(prev, score),
+                        None => score,
+                    });
+                }
+            }
+
+            best
+        } else {
+            Some(total)
+        }
+    }
+
+    /// Assign every variable its smallest candidate, in variable
+    /// order, without backtracking.
+    fn ground_first(mut self) -> Option<Solution> {
+        loop {
+            if self.constrain().is_err() {
+                return None;
+            }
+
+            let next_unassigned = self.vars.iter().enumerate()
+                .filter_map(|(idx, vs)| match vs {
+                    &VarState::Unassigned(ref cs) => Some((idx, cs.iter().next())),
+                    _ => None,
+                })
+                .next();
+
+            match next_unassigned {
+                Some((idx, Some(val))) => {
+                    if self.assign(idx, val).is_err() {
+                        return None;
+                    }
+                },
+                Some((_, None)) => return None,
+                None => {
+                    // No unassigned variables remaining.
+                    let vars = (0..self.puzzle.num_vars).map(|idx|
+                            self[VarToken(idx)]).collect();
+                    self.puzzle.emit(Metric::SolutionFound);
+                    return Some(Solution{ vars: vars });
+                },
+            }
+        }
+    }
+
+    /// Solve the puzzle, sending each solution over the channel.
+    ///
+    /// Returns false if the receiver was dropped and the search
+    /// should stop.
+    fn solve_streaming(&mut self, tx: &SyncSender<Solution>) -> bool {
+        if self.constrain().is_err() {
+            self.puzzle.take_backtrack();
+            return true;
+        }
+
+        let next_unassigned = self.vars.iter().enumerate().min_by_key(
+                |&(_, vs)| match vs {
+                    &VarState::Unassigned(ref cs) => cs.len(),
+                    _ => ::std::usize::MAX,
+                });
+
+        if let Some((idx, &VarState::Unassigned(ref cs))) = next_unassigned {
+            if cs.len() == 0 {
+                // Contradiction.
+                self.puzzle.take_backtrack();
+                return true;
+            }
+
+            self.puzzle.take_decision();
+            for val in cs.iter() {
+                self.puzzle.take_guess();
+
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    self.puzzle.take_backtrack();
+                    continue;
+                }
+
+                if !new.solve_streaming(tx) {
+                    return false;
+                }
+            }
+        } else {
+            // No unassigned variables remaining.
+            let vars = (0..self.puzzle.num_vars).map(|idx|
+                    self[VarToken(idx)]).collect();
+            self.puzzle.emit(Metric::SolutionFound);
+            return tx.send(Solution{ vars: vars }).is_ok();
+        }
+
+        true
+    }
+
+    /// Solve the puzzle with a randomised value ordering and a
+    /// limited node budget.
+    ///
+    /// Returns None if no solution was found, either because the
+    /// subtree was exhausted or because the budget ran out.
+    #[cfg(feature = "rand")]
+    fn solve_random<R: rand::Rng>(&mut self, rng: &mut R, nodes: &mut u32)
+            -> Option<Solution> {
+        use rand::seq::SliceRandom;
+
+        if self.constrain().is_err() {
+            self.puzzle.take_backtrack();
+            return None;
+        }
+
+        let next_unassigned = self.vars.iter().enumerate().min_by_key(
+                |&(_, vs)| match vs {
+                    &VarState::Unassigned(ref cs) => cs.len(),
+                    _ => ::std::usize::MAX,
+                });
+
+        if let Some((idx, &VarState::Unassigned(ref cs))) = next_unassigned {
+            if cs.len() == 0 {
+                // Contradiction.
+                self.puzzle.take_backtrack();
+                return None;
+            }
+
+            let mut vals: Vec<Val> = cs.iter().collect();
+            vals.shuffle(rng);
+
+            self.puzzle.take_decision();
+            for val in vals.into_iter() {
+                if *nodes == 0 {
+                    // Budget exhausted.
+                    return None;
+                }
+                *nodes = *nodes - 1;
+
+                self.puzzle.take_guess();
+
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    self.puzzle.take_backtrack();
+                    continue;
+                }
+
+                if let Some(solution) = new.solve_random(rng, nodes) {
+                    return Some(solution);
+                }
+            }
+
+            None
+        } else {
+            // No unassigned variables remaining.
+            let vars = (0..self.puzzle.num_vars).map(|idx|
+                    self[VarToken(idx)]).collect();
+            self.puzzle.emit(Metric::SolutionFound);
+            Some(Solution{ vars: vars })
+        }
+    }
+
+    /// Sum the weights of the soft constraints that are certainly
+    /// violated in the current search state, i.e. whose propagation
+    /// fails on a copy of the state.  For a fully assigned state this
+    /// is the exact penalty.
+    fn soft_penalty(&self) -> u32 {
+        let mut penalty = 0;
+
+        for (cidx, constraint) in self.puzzle.soft_constraints.iter()
+                .enumerate() {
+            let mut probe = self.clone();
+            let mut ok = true;
+
+            for &var in constraint.vars() {
+                if let Some(val) = probe.get_assigned(var) {
+                    if constraint.on_assigned(&mut probe, var, val).is_err() {
+                        ok = false;
+                        break;
+                    }
+                }
+            }
+
+            if !(ok && constraint.on_updated(&mut probe).is_ok()) {
+                penalty = penalty + self.puzzle.soft_weights[cidx];
+            }
+        }
+
+        penalty
+    }
+
+    /// Solve the puzzle by branch and bound, minimizing the total
+    /// weight of the violated soft constraints and keeping the best
+    /// solution found so far.
+    fn solve_min_penalty(mut self, best: &mut Option<(Solution, u32)>) {
+        if self.constrain().is_err() {
+            self.puzzle.take_backtrack();
+            return;
+        }
+
+        // The certainly-violated soft constraints bound the penalty
+        // of every solution below this node.
+        let penalty = self.soft_penalty();
+        if let Some(&(_, best_penalty)) = best.as_ref() {
+            if penalty >= best_penalty {
+                return;
+            }
+        }
+
+        if let Some((idx, vals)) = Solver::choose(&self) {
+            self.puzzle.take_decision();
+            for val in vals.into_iter() {
+                self.puzzle.take_guess();
+
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    self.puzzle.take_backtrack();
+                    continue;
+                }
+
+                new.solve_min_penalty(best);
+            }
+        } else {
+            // No unassigned variables remaining.
+            let vars = (0..self.puzzle.num_vars).map(|idx|
+                    self[VarToken(idx)]).collect();
+            self.puzzle.emit(Metric::SolutionFound);
+            *best = Some((Solution{ vars: vars }, penalty));
+        }
+    }
+
+    /// Compute an upper bound of the objective over the current
+    /// domains: each variable contributes its largest (positive
+    /// coefficient) or smallest (negative coefficient) candidate.
+    fn objective_bound(&self, objective: &LinExpr) -> PsResult<Coef> {
+        let mut bound = objective.constant;
+        for (&var, &coef) in objective.coef.iter() {
+            let (min_val, max_val) = try!(self.get_min_max(var));
+            let val = if coef > Ratio::zero() { max_val } else { min_val };
+            bound = bound + coef * Ratio::from_integer(val);
+        }
+
+        Ok(bound)
+    }
+
+    /// Maximize the objective by branch and bound, pruning subtrees
+    /// whose objective bound cannot beat the incumbent.  Returns
+    /// false if the run was stopped by the guess limit.
+    fn solve_maximize(&mut self, objective: &LinExpr, max_guesses: u64,
+            best: &mut Option<(Solution, Coef)>) -> bool {
+        if self.constrain().is_err() {
+            self.puzzle.take_backtrack();
+            return true;
+        }
+
+        let bound = match self.objective_bound(objective) {
+            Ok(bound) => bound,
+            Err(()) => {
+                self.puzzle.take_backtrack();
+                return true;
+            },
+        };
+
+        if let Some(&(_, incumbent)) = best.as_ref() {
+            if bound <= incumbent {
+                return true;
+            }
+        }
+
+        if let Some((idx, vals)) = Solver::choose(&self) {
+            self.puzzle.take_decision();
+            for val in vals.into_iter() {
+                if self.puzzle.num_guesses() >= max_guesses {
+                    return false;
+                }
+
+                self.puzzle.take_guess();
+
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    self.puzzle.take_backtrack();
+                    continue;
+                }
+
+                if !new.solve_maximize(objective, max_guesses, best) {
+                    return false;
+                }
+            }
+
+            true
+        } else {
+            // No unassigned variables remaining; the bound is exact.
+            let vars = (0..self.puzzle.num_vars).map(|idx|
+                    self[VarToken(idx)]).collect();
+            self.puzzle.emit(Metric::SolutionFound);
+            *best = Some((Solution{ vars: vars }, bound));
+            true
+        }
+    }
+
+    /// Find any solution, tracking the maximum depth of the search
+    /// tree for difficulty rating.  Returns whether a solution was
+    /// found.
+    fn solve_rated(&mut self, depth: usize, max_depth: &mut usize) -> bool {
+        if depth > *max_depth {
+            *max_depth = depth;
+        }
+
+        if self.constrain().is_err() {
+            self.puzzle.take_backtrack();
+            return false;
+        }
+
+        if let Some((idx, vals)) = Solver::choose(&self) {
+            self.puzzle.take_decision();
+            for val in vals.into_iter() {
+                self.puzzle.take_guess();
+
+                let mut new = self.clone();
+                if new.assign(idx, val).is_err() {
+                    self.puzzle.take_backtrack();
+                    continue;
+                }
+
+                if new.solve_rated(depth + 1, max_depth) {
+                    return true;
+                }
+            }
+
+            false
+        } else {
+            // No unassigned variables remaining.
+            self.puzzle.emit(Metric::SolutionFound);
+            true
+        }
+    }
+
+    /// Assign a variable (given by index) to a value.
+    fn assign(&mut self, idx: usize, val: Val) -> PsResult<()> {
+        let var = VarToken(idx);
+        self.vars[idx] = VarState::Assigned(val);
+        self.wake.union_with(&self.constraints.wake[idx]);
+
+        for cidx in 0..self.constraints.constraints.len() {
+            if self.constraints.wake[idx].contains(cidx) {
+                if self.puzzle.debug {
+                    self.puzzle.propagating.set(Some(cidx));
+                }
+
+                let constraint = self.constraints.constraints[cidx].clone();
+                try!(constraint.on_assigned(self, var, val));
+            }
+        }
+
+        if self.puzzle.debug {
+            self.puzzle.propagating.set(None);
+        }
+
+        Ok(())
+    }
+
+    /// Scan for a domain wipeout (Err), or for the first unassigned
+    /// variable whose domain has been reduced to a single candidate.
+    fn first_singleton(&self) -> PsResult<Option<(usize, Val)>> {
+        for (idx, state) in self.vars.iter().enumerate() {
+            if let &VarState::Unassigned(ref cs) = state {
+                match cs.len() {
+                    0 => return Err(()),
+                    1 => return Ok(cs.iter().next().map(|val| (idx, val))),
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// "Gimme" phase, checking only the variables whose candidates
+    /// have changed:
+    /// - abort if any variables with 0 candidates,
+    /// - assign variables with only 1 candidate.
+    /// - repeat until no more gimmes found.
+    fn promote_gimmes(&mut self) -> PsResult<()> {
+        while !self.dirty.is_empty() {
+            let passes = self.puzzle.num_gimme_passes.get() + 1;
+            self.puzzle.num_gimme_passes.set(passes);
+
+            let dirty = mem::replace(&mut self.dirty, BitSet::new());
+            for idx in dirty.iter() {
+                let gimme = match &self.vars[idx] {
+                    &VarState::Assigned(_) => None,
+                    &VarState::Unassigned(ref cs) => match cs.len() {
+                        0 => return Err(()),
+                        1 => cs.iter().next(),
+                        _ => None,
+                    },
+                    &VarState::Unified(_) => None,
+                };
+
+                if let Some(val) = gimme {
+                    try!(self.assign(idx, val));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Take any obvious non-choices, using the constraints to
+    /// eliminate candidates.  Stops when it must start guessing.
+    fn constrain(&mut self) -> PsResult<()> {
+        while !self.wake.is_empty() {
+            try!(self.promote_gimmes());
+
+            // Apply constraints.
+            if !self.wake.is_empty() {
+                let wake = mem::replace(&mut self.wake, BitSet::new());
+
+                #[cfg(feature = "grouped")]
+                {
+                    if self.puzzle.grouped {
+                        try!(self.on_updated_grouped(&wake));
+                        continue;
+                    }
+                }
+
+                for cidx in wake.iter() {
+                    if !self.constraints.active.contains(cidx) {
+                        continue;
+                    }
+
+                    // Promote any singleton domains first, so that
+                    // constraints which only implement on_assigned
+                    // have reacted before the next propagation, and
+                    // on_updated never sees an unassigned variable
+                    // with a single candidate.
+                    try!(self.promote_gimmes());
+
+                    if self.puzzle.debug {
+                        self.puzzle.propagating.set(Some(cidx));
+                    }
+
+                    let constraint = self.constraints.constraints[cidx].clone();
+                    self.puzzle.emit(Metric::PropagationRun {
+                        constraint_kind: self.puzzle.constraint_kinds[cidx],
+                    });
+                    try!(constraint.on_updated(self));
+                }
+            }
+        }
+
+        if self.puzzle.debug {
+            self.puzzle.propagating.set(None);
+        }
+
+        Ok(())
+    }
+
+    /// Apply the woken constraints, evaluated in variable-disjoint
+    /// groups against isolated copies of the search state, merging
+    /// the domain reductions afterwards.
+    #[cfg(feature = "grouped")]
+    fn on_updated_grouped(&mut self, wake: &BitSet) -> PsResult<()> {
+        // Partition the woken constraints into variable-disjoint
+        // groups, resolving unified variables to their replacements.
+        let mut groups: Vec<(Vec<usize>, BitSet)> = Vec::new();
+        for cidx in wake.iter() {
+            if !self.constraints.active.contains(cidx) {
+                continue;
+            }
+
+            let mut merged = BitSet::new();
+            for &VarToken(idx) in self.constraints.constraints[cidx].vars() {
+                merged.insert(self.resolve_idx(idx));
+            }
+
+            let mut cidxs = vec![cidx];
+            let mut remaining = Vec::with_capacity(groups.len());
+            for (gcidxs, gvars) in groups.drain(..) {
+                if gvars.is_disjoint(&merged) {
+                    remaining.push((gcidxs, gvars));
+                } else {
+                    merged.union_with(&gvars);
+                    cidxs.extend(gcidxs);
+                }
+            }
+
+            cidxs.sort();
+            remaining.push((cidxs, merged));
+            groups = remaining;
+        }
+
+        for (cidxs, gvars) in groups.into_iter() {
+            let mut delta = self.clone();
+            delta.wake = BitSet::new();
+            delta.dirty = BitSet::new();
+
+            let mut unified = false;
+            for &cidx in cidxs.iter() {
+                let constraint = delta.constraints.constraints[cidx].clone();
+                try!(constraint.on_updated(&mut delta));
+
+                if !Rc::ptr_eq(&delta.constraints, &self.constraints) {
+                    unified = true;
+                    break;
+                }
+            }
+
+            if unified {
+                // A unification rewrites the shared constraint list,
+                // which cannot be merged as a variable-local delta;
+                // apply the group directly instead.
+                for &cidx in cidxs.iter() {
+                    if !self.constraints.active.contains(cidx) {
+                        continue;
+                    }
+
+                    let constraint = self.constraints.constraints[cidx].clone();
+                    try!(constraint.on_updated(self));
+                }
+            } else {
+                for idx in gvars.iter() {
+                    self.vars[idx] = delta.vars[idx].clone();
+                }
+                self.wake.union_with(&delta.wake);
+                self.dirty.union_with(&delta.dirty);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve a variable index through any unifications.
+    #[cfg(feature = "grouped")]
+    fn resolve_idx(&self, idx: usize) -> usize {
+        match &self.vars[idx] {
+            &VarState::Unified(VarToken(other)) => self.resolve_idx(other),
+            _ => idx,
+        }
+    }
+
+    /// Unify the "from" variable with the "to" variable.
+    pub fn unify(&mut self, from: VarToken, to: VarToken)
+            -> PsResult<()> {
+        if from == to {
+            return Ok(());
+        }
+
+        // Some preliminary checks, and maybe swap "from" and "to" in
+        // order to simplify our logic.
+        let (from, to) = {
+            let VarToken(search) = from;
+            let VarToken(replace) = to;
+            match (&self.vars[search], &self.vars[replace]) {
+                (&VarState::Assigned(val1), &VarState::Assigned(val2)) =>
+                    return bool_to_result(val1 == val2),
+
+                // Unified variables should have been substituted out previously.
+                (&VarState::Unified(_), _) | (_, &VarState::Unified(_)) =>
+                    panic!("internal representation corrupt"),
+
+                (&VarState::Unassigned(_), &VarState::Assigned(_)) =>
+                    (to, from),
+
+                (&VarState::Assigned(_), &VarState::Unassigned(_))
+                | (&VarState::Unassigned(_), &VarState::Unassigned(_)) =>
+                    (from, to),
+            }
+        };
+
+        let VarToken(search) = from;
+        let VarToken(replace) = to;
+
+        // Create new constraints to reflect the unification.
+        let new_constraints = try!(self.constraints.substitute(from, to));
+        self.constraints = Rc::new(new_constraints);
+        self.wake.union_with(&self.constraints.wake[replace]);
+        self.dirty.insert(replace);
+
+        if !self.constraints.wake[search].is_empty() {
+            // A constraint's substitute() kept a reference to the old
+            // token, violating the contract documented on the
+            // Constraint trait.  Fail the branch instead of aborting
+            // the whole process.
+            for cidx in self.constraints.wake[search].iter() {
+                eprintln!("puzzle-solver: constraint {} ({}) did not \
+                           substitute out {:?}",
+                          cidx, self.puzzle.constraint_kinds[cidx], from);
+            }
+            return Err(());
+        }
+
+        // Take intersection of the candidates.
+        match &self.vars[search] {
+            &VarState::Assigned(val)
+            | &VarState::Unassigned(Candidates::Value(val)) => {
+                try!(self.set_candidate(to, val));
+            },
+            _ => match get_two_mut(&mut self.vars, search, replace) {
+                (&mut VarState::Unassigned(Candidates::Set(ref mut rc1)),
+                 &mut VarState::Unassigned(Candidates::Set(ref mut rc2))) => {
+                    *rc2 = Rc::new(rc2.intersection(rc1).cloned().collect());
+                    if rc2.is_empty() {
+                        return Err(());
+                    }
+                },
+                (&mut VarState::Unassigned(Candidates::Set(ref rc1)),
+                 &mut VarState::Unassigned(Candidates::Value(val))) => {
+                    if !rc1.contains(&val) {
+                        return Err(());
+                    }
+                },
+                _ => (),
+            },
+        }
+
+        self.vars[search] = VarState::Unified(to);
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for PuzzleSearch<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "PuzzleSearch={{")?;
+        for (idx, var) in self.vars.iter().enumerate() {
+            writeln!(f, "")?;
+            match var {
+                &VarState::Assigned(val) => {
+                    write!(f, "  var {}: {}", idx, val)?;
+                },
+                &VarState::Unassigned(ref cs) => {
+                    write!(f, "  var {}:", idx)?;
+                    for val in cs.iter() {
+                        write!(f, " {}", val)?;
+                    }
+                },
+                &VarState::Unified(VarToken(other)) => {
+                    write!(f, "  var {}: var {}", idx, other)?;
+                },
+            }
+        }
+        write!(f, "}}")?;
+        Ok(())
+    }
+}
+
+impl<'a> ops::Index<VarToken> for PuzzleSearch<'a> {
+    type Output = Val;
+
+    /// Get the value assigned to a variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable has not been assigned.
+    fn index(&self, var: VarToken) -> &Val {
+        let VarToken(idx) = var;
+        match &self.vars[idx] {
+            &VarState::Assigned(ref val) => val,
+            &VarState::Unassigned(_) => panic!("unassigned"),
+            &VarState::Unified(other) => &self[other],
+        }
+    }
+}
+
+fn bool_to_result(cond: bool) -> PsResult<()> {
+    if cond {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+fn get_two_mut<'a, T>(slice: &'a mut [T], a: usize, b: usize)
+        -> (&'a mut T, &'a mut T) {
+    assert!(a != b);
+    if a < b {
+        let (mut l, mut r) = slice.split_at_mut(b);
+        (&mut l[a], &mut r[0])
+    } else {
+        let (l, r) = slice.split_at_mut(a);
+        (&mut r[0], &mut l[b])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::iter;
+    use std::rc::Rc;
+
+    use ::{Constraint,LinExpr,PsResult,Puzzle,PuzzleSearch,Solution,
+           SolutionCount,StopReason,Val,VarToken};
+    use ::constraint::Equality;
+
+    #[test]
+    fn test_value_vs_singleton_candidates() {
+        let mut sys = Puzzle::new();
+        let singleton = sys.new_var_with_candidates(&[5]);
+        let fixed = sys.new_var();
+        sys.set_value(fixed, 5);
+
+        let search = sys.step().expect("contradiction");
+
+        // A variable with a single candidate is not yet assigned.
+        assert!(!search.is_assigned(singleton));
+        assert_eq!(search.get_assigned(singleton), None);
+        assert_eq!(search.get_unassigned(singleton).collect::<Vec<_>>(), &[5]);
+
+        // A variable set as part of the problem is assigned from the
+        // start of the search.
+        assert!(search.is_assigned(fixed));
+        assert_eq!(search.get_assigned(fixed), Some(5));
+        assert_eq!(search.get_unassigned(fixed).count(), 0);
+    }
+
+    #[test]
+    fn test_solve_with_search_unified() {
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var_with_candidates(&[1,2]);
+        let v1 = sys.new_var_with_candidates(&[2,3]);
+        sys.unify(v0, v1);
+
+        let (solution, search) = sys.solve_any_with_search().expect("solution");
+        assert_eq!(solution[v0], 2);
+        assert_eq!(solution[v1], 2);
+        assert_eq!(search.get_unified(v0), Some(v1));
+        assert_eq!(search.get_unified(v1), None);
+        assert_eq!(search.get_assigned(v0), Some(2));
+    }
+
+    #[test]
+    fn test_solve_all_streaming() {
+        let (tx, rx) = ::std::sync::mpsc::sync_channel(1);
+        ::std::thread::spawn(move || {
+            let mut sys = Puzzle::new();
+            sys.new_var_with_candidates(&[1,2]);
+            sys.new_var_with_candidates(&[3,4]);
+            sys.solve_all_streaming(tx);
+        });
+
+        assert_eq!(rx.iter().count(), 4);
+    }
+
+    #[test]
+    fn test_solve_all_streaming_receiver_dropped() {
+        let (tx, rx) = ::std::sync::mpsc::sync_channel(1);
+        let handle = ::std::thread::spawn(move || {
+            let mut sys = Puzzle::new();
+            sys.new_var_with_candidates(&[1,2]);
+            sys.new_var_with_candidates(&[3,4]);
+            sys.solve_all_streaming(tx);
+        });
+
+        let first = rx.recv().expect("solution");
+        assert!(first[::VarToken(0)] >= 1);
+        drop(rx);
+        handle.join().expect("join");
+    }
+
+    #[test]
+    fn test_get_constraint() {
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var_with_candidates(&[1,2,3]);
+        let v1 = sys.new_var_with_candidates(&[1,2,3]);
+        sys.all_different(&[v0,v1]);
+        sys.equals(v0 + v1, 4);
+        let count = sys.constraint_count();
+        assert_eq!(count, 2);
+
+        let search = sys.step().expect("contradiction");
+        for cidx in 0..count {
+            let constraint = search.get_constraint(cidx);
+            assert_eq!(constraint.vars().count(), 2);
         }
     }
 
-    /// Create a new puzzle constraint container reflecting the
-    /// substitution of "from" with "to".
-    fn substitute(&self, from: VarToken, to: VarToken) -> PsResult<Self> {
-        let VarToken(idx) = from;
-        let mut new_constraints = self.constraints.clone();
+    #[test]
+    fn test_bound_candidate_range_emptied_var() {
+        // A (buggy, but permitted) constraint that empties the
+        // variable's candidates, ignoring the resulting Err.
+        struct EatAll {
+            var: VarToken,
+        }
 
-        for cidx in self.wake[idx].iter() {
-            let rc = try!(self.constraints[cidx].substitute(from, to));
-            new_constraints[cidx] = rc;
+        impl Constraint for EatAll {
+            fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+                Box::new(iter::once(&self.var))
+            }
+
+            fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+                let vals: Vec<Val> = search.get_unassigned(self.var).collect();
+                for val in vals.into_iter() {
+                    let _ = search.remove_candidate(self.var, val);
+                }
+                Ok(())
+            }
+
+            fn substitute(&self, from: VarToken, to: VarToken)
+                    -> PsResult<Rc<Constraint>> {
+                let var = if self.var == from { to } else { self.var };
+                Ok(Rc::new(EatAll{ var: var }))
+            }
         }
 
-        let wake = Self::init_wake(&new_constraints, self.wake.len());
-        Ok(PuzzleConstraints {
-            constraints: new_constraints,
-            wake: wake,
-        })
-    }
+        // A constraint racing against EatAll in the same propagation
+        // round, hitting the already emptied candidate set.
+        struct Bound {
+            var: VarToken,
+        }
 
-    /// Determine which variables wake up which constraints.
-    fn init_wake(constraints: &Vec<Rc<Constraint>>, num_vars: usize)
-            -> Vec<BitSet> {
-        let mut wake = vec![BitSet::new(); num_vars];
-        for cidx in 0..constraints.len() {
-            for &VarToken(idx) in constraints[cidx].vars() {
-                wake[idx].insert(cidx);
+        impl Constraint for Bound {
+            fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+                Box::new(iter::once(&self.var))
+            }
+
+            fn on_updated(&self, search: &mut PuzzleSearch) -> PsResult<()> {
+                try!(search.bound_candidate_range(self.var, 0, 100));
+                Ok(())
+            }
+
+            fn substitute(&self, from: VarToken, to: VarToken)
+                    -> PsResult<Rc<Constraint>> {
+                let var = if self.var == from { to } else { self.var };
+                Ok(Rc::new(Bound{ var: var }))
             }
         }
 
-        wake
+        let mut sys = Puzzle::new();
+        let var = sys.new_var_with_candidates(&[1,2,3]);
+        sys.add_constraint(EatAll{ var: var });
+        sys.add_constraint(Bound{ var: var });
+
+        // Must report the contradiction rather than panic.
+        assert!(sys.step().is_none());
     }
-}
 
-/*--------------------------------------------------------------*/
+    #[test]
+    fn test_constraint_groups() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
+        sys.all_different(&vars);
 
-impl<'a> PuzzleSearch<'a> {
-    /// Allocate a new puzzle searcher.
-    fn new(puzzle: &'a Puzzle) -> Self {
-        let constraints = PuzzleConstraints::new(puzzle);
-        let vars = puzzle.candidates.iter().map(|cs|
-                VarState::Unassigned(cs.clone())).collect();
-        let mut wake = BitSet::new();
+        let group = sys.new_group("pin");
+        sys.add_constraint_in(group, Equality::new(vars[0] - 1));
 
-        for cidx in 0..constraints.constraints.len() {
-            wake.insert(cidx);
+        assert_eq!(sys.solve_all().len(), 1);
+
+        sys.set_group_enabled(group, false);
+        assert_eq!(sys.solve_all().len(), 2);
+
+        sys.set_group_enabled(group, true);
+        assert_eq!(sys.solve_all().len(), 1);
+    }
+
+    #[test]
+    fn test_disabled_group_not_invoked() {
+        struct Probe {
+            var: VarToken,
+            calls: Rc<Cell<usize>>,
         }
 
-        PuzzleSearch {
-            puzzle: puzzle,
-            constraints: Rc::new(constraints),
-            vars: vars,
-            wake: wake,
+        impl Constraint for Probe {
+            fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+                Box::new(iter::once(&self.var))
+            }
+
+            fn on_assigned(&self, _: &mut PuzzleSearch, _: VarToken, _: Val)
+                    -> PsResult<()> {
+                self.calls.set(self.calls.get() + 1);
+                Ok(())
+            }
+
+            fn on_updated(&self, _: &mut PuzzleSearch) -> PsResult<()> {
+                self.calls.set(self.calls.get() + 1);
+                Ok(())
+            }
+
+            fn substitute(&self, _: VarToken, _: VarToken)
+                    -> PsResult<Rc<Constraint>> {
+                unimplemented!();
+            }
         }
+
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
+        sys.all_different(&vars);
+
+        let calls = Rc::new(Cell::new(0));
+        let group = sys.new_group("probe");
+        sys.add_constraint_in(group, Probe{
+            var: vars[0],
+            calls: calls.clone(),
+        });
+
+        sys.set_group_enabled(group, false);
+        assert_eq!(sys.solve_all().len(), 2);
+        assert_eq!(calls.get(), 0);
+
+        sys.set_group_enabled(group, true);
+        assert_eq!(sys.solve_all().len(), 2);
+        assert!(calls.get() > 0);
     }
 
-    /// Check if the variable has been assigned to a value.
-    pub fn is_assigned(&self, var: VarToken) -> bool {
-        let VarToken(idx) = var;
-        match &self.vars[idx] {
-            &VarState::Assigned(_) => true,
-            &VarState::Unassigned(_) => false,
-            &VarState::Unified(other) => self.is_assigned(other),
-        }
+    #[test]
+    fn test_solve_min_penalty() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
+        sys.all_different(&vars);
+
+        // Two conflicting preferences; only one can be satisfied.
+        sys.add_soft_constraint(2, Equality::new(vars[0] - 1));
+        sys.add_soft_constraint(3, Equality::new(vars[0] - 2));
+
+        // The optimal solution violates the cheaper preference.
+        let (solution, penalty) = sys.solve_min_penalty().expect("solution");
+        assert_eq!(penalty, 2);
+        assert_eq!(solution[vars[0]], 2);
+        assert_eq!(solution[vars[1]], 1);
     }
 
-    /// Get the value assigned to a variable, or None.
-    ///
-    /// This should be used if the variable may potentially be
-    /// unassigned.  For example, when implementing constraints.
-    pub fn get_assigned(&self, var: VarToken) -> Option<Val> {
-        let VarToken(idx) = var;
-        match &self.vars[idx] {
-            &VarState::Assigned(val) => Some(val),
-            &VarState::Unassigned(_) => None,
-            &VarState::Unified(other) => self.get_assigned(other),
-        }
+    #[test]
+    fn test_solve_min_penalty_satisfiable() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
+        sys.all_different(&vars);
+        sys.add_soft_constraint(5, Equality::new(vars[0] - 2));
+
+        let (solution, penalty) = sys.solve_min_penalty().expect("solution");
+        assert_eq!(penalty, 0);
+        assert_eq!(solution[vars[0]], 2);
     }
 
-    /// Get an iterator over the candidates to an unassigned variable.
-    pub fn get_unassigned(&'a self, var: VarToken)
-            -> Box<Iterator<Item=Val> + 'a> {
-        let VarToken(idx) = var;
-        match &self.vars[idx] {
-            &VarState::Assigned(_) => Box::new(iter::empty()),
-            &VarState::Unassigned(ref cs) => cs.iter(),
-            &VarState::Unified(other) => self.get_unassigned(other),
-        }
+    #[test]
+    fn test_get_unassigned_then_mutate() {
+        let mut sys = Puzzle::new();
+        let var = sys.new_var_with_candidates(&[1,2,3]);
+        let mut search = sys.step().expect("contradiction");
+
+        let vals: Vec<Val> = search.get_unassigned(var).collect();
+        assert_eq!(vals, &[1,2,3]);
+
+        // Mutating the search after iterating requires the iterator
+        // to borrow the search for its own (shorter) lifetime, not
+        // for the lifetime of the puzzle borrow.
+        assert!(search.set_candidate(var, 2).is_ok());
     }
 
-    /// Get the minimum and maximum values for variable.
-    pub fn get_min_max(&self, var: VarToken) -> PsResult<(Val, Val)> {
-        let VarToken(idx) = var;
-        match &self.vars[idx] {
-            &VarState::Assigned(val) => Ok((val, val)),
-            &VarState::Unassigned(ref cs) => match cs {
-                &Candidates::None => Err(()),
-                &Candidates::Value(val) => Ok((val, val)),
-                &Candidates::Set(ref rc) => {
-                    rc.iter().cloned().min().into_iter()
-                        .zip(rc.iter().cloned().max()).next()
-                        .ok_or(())
-                }
-            },
-            &VarState::Unified(other) => self.get_min_max(other),
-        }
+    #[test]
+    fn test_palindrome() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(4, &[1,2,3,4]);
+        sys.palindrome(&vars);
+        sys.set_value(vars[0], 2);
+        sys.set_value(vars[1], 3);
+
+        // Fixing one half mirrors to the other.
+        let solution = sys.solve_unique().expect("solution");
+        assert_eq!(solution[vars[2]], 3);
+        assert_eq!(solution[vars[3]], 2);
     }
 
-    /// Set a variable to a value.
-    pub fn set_candidate(&mut self, var: VarToken, val: Val)
-            -> PsResult<()> {
-        let VarToken(idx) = var;
+    #[test]
+    fn test_verify() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
+        sys.all_different(&vars);
+        sys.equals(vars[0] + vars[1], 3);
 
-        match &self.vars[idx] {
-            &VarState::Assigned(v) => return bool_to_result(v == val),
-            &VarState::Unassigned(ref cs) => match cs {
-                &Candidates::None => return Err(()),
-                &Candidates::Value(v) => return bool_to_result(v == val),
-                &Candidates::Set(_) => (),
-            },
-            &VarState::Unified(_) => (),
-        }
+        assert!(sys.verify(&Solution{ vars: vec![1,2] }).is_ok());
+        assert!(sys.verify(&Solution{ vars: vec![2,1] }).is_ok());
 
-        if let &VarState::Unified(other) = &self.vars[idx] {
-            self.set_candidate(other, val)
-        } else if let &mut VarState::Unassigned(Candidates::Set(ref mut rc))
-                = &mut self.vars[idx] {
-            if rc.contains(&val) {
-                let mut set = Rc::make_mut(rc);
-                set.clear();
-                set.insert(val);
-                self.wake.union_with(&self.constraints.wake[idx]);
-                Ok(())
-            } else {
-                Err(())
-            }
-        } else {
-            unreachable!();
-        }
+        // Both constraints broken, reported by index.
+        assert_eq!(sys.verify(&Solution{ vars: vec![1,1] }),
+                Err(vec![0,1]));
     }
 
-    /// Remove a single candidate from a variable.
-    pub fn remove_candidate(&mut self, var: VarToken, val: Val)
-            -> PsResult<()> {
-        let VarToken(idx) = var;
+    #[test]
+    fn test_solve_under() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
+        sys.all_different(&vars);
 
-        match &self.vars[idx] {
-            &VarState::Assigned(v) => return bool_to_result(v != val),
-            &VarState::Unassigned(ref cs) => match cs {
-                &Candidates::None => return Err(()),
-                &Candidates::Value(v) => return bool_to_result(v != val),
-                &Candidates::Set(_) => (),
-            },
-            &VarState::Unified(_) => (),
+        // A contradictory assumption fails this solve only.
+        assert!(sys.solve_under(&[ (vars[0], 1), (vars[1], 1) ]).is_none());
+
+        let solution = sys.solve_under(&[ (vars[0], 2) ]).expect("solution");
+        assert_eq!(solution[vars[0]], 2);
+        assert_eq!(solution[vars[1]], 1);
+
+        // The assumptions do not persist.
+        assert_eq!(sys.solve_all().len(), 2);
+    }
+
+    #[test]
+    fn test_buggy_substitute() {
+        // A buggy constraint whose substitute() forgets to drop the
+        // old token.
+        struct KeepOld {
+            var: VarToken,
         }
 
-        if let &VarState::Unified(other) = &self.vars[idx] {
-            self.remove_candidate(other, val)
-        } else if let &mut VarState::Unassigned(Candidates::Set(ref mut rc))
-                = &mut self.vars[idx] {
-            if rc.contains(&val) {
-                let mut set = Rc::make_mut(rc);
-                set.remove(&val);
-                self.wake.union_with(&self.constraints.wake[idx]);
+        impl Constraint for KeepOld {
+            fn vars<'a>(&'a self) -> Box<Iterator<Item=&'a VarToken> + 'a> {
+                Box::new(iter::once(&self.var))
+            }
+
+            fn substitute(&self, _: VarToken, _: VarToken)
+                    -> PsResult<Rc<Constraint>> {
+                Ok(Rc::new(KeepOld{ var: self.var }))
             }
-            bool_to_result(!rc.is_empty())
-        } else {
-            unreachable!();
         }
+
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var_with_candidates(&[1,2]);
+        let v1 = sys.new_var_with_candidates(&[1,2]);
+        sys.add_constraint(KeepOld{ var: v0 });
+        sys.unify(v0, v1);
+
+        // Must fail the branch cleanly rather than panic.
+        assert!(sys.step().is_none());
     }
 
-    /// Bound an variable to the given range.
-    pub fn bound_candidate_range(&mut self, var: VarToken, min: Val, max: Val)
-            -> PsResult<(Val, Val)> {
-        let VarToken(idx) = var;
+    #[test]
+    fn test_ground_first() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2,3]);
+        sys.all_different(&vars);
 
-        match &self.vars[idx] {
-            &VarState::Assigned(v) =>
-                if min <= v && v <= max {
-                    return Ok((v, v))
-                } else {
-                    return Err(())
-                },
-            &VarState::Unassigned(ref cs) => match cs {
-                &Candidates::None => return Err(()),
-                &Candidates::Value(v) =>
-                    if min <= v && v <= max {
-                        return Ok((v, v))
-                    } else {
-                        return Err(())
-                    },
-                &Candidates::Set(_) => (),
-            },
-            &VarState::Unified(_) => (),
-        }
+        let solution = sys.ground_first().expect("solution");
+        assert_eq!(solution[vars[0]], 1);
+        assert_eq!(solution[vars[1]], 2);
+        assert_eq!(solution[vars[2]], 3);
+        assert_eq!(sys.num_guesses(), 0);
+    }
 
-        if let &VarState::Unified(other) = &self.vars[idx] {
-            self.bound_candidate_range(other, min, max)
-        } else if let &mut VarState::Unassigned(Candidates::Set(ref mut rc))
-                = &mut self.vars[idx] {
-            let &curr_min = rc.iter().min().expect("candidates");
-            let &curr_max = rc.iter().max().expect("candidates");
+    #[test]
+    fn test_ground_first_violation() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2]);
+        sys.all_different(&vars);
 
-            if curr_min < min || max < curr_max {
-                {
-                    let mut set = Rc::make_mut(rc);
-                    *set = set.iter()
-                        .filter(|&val| min <= *val && *val <= max)
-                        .cloned()
-                        .collect();
-                    self.wake.union_with(&self.constraints.wake[idx]);
-                }
-                rc.iter().cloned().min().into_iter()
-                    .zip(rc.iter().cloned().max()).next()
-                    .ok_or(())
-            } else {
-                Ok((curr_min, curr_max))
-            }
-        } else {
-            unreachable!();
-        }
+        assert!(sys.ground_first().is_none());
+    }
+
+    #[test]
+    fn test_unassigned_vars() {
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var();
+        let v1 = sys.new_var_with_candidates(&[5]);
+        let v2 = sys.new_var_with_candidates(&[1,2]);
+        sys.set_value(v0, 1);
+
+        let search = sys.step().expect("contradiction");
+
+        // v0 is assigned, and v1's singleton candidate is as good as
+        // decided; only v2 still needs a decision.
+        let unassigned: Vec<VarToken> = search.unassigned_vars().collect();
+        assert_eq!(unassigned, &[v2]);
+        assert!(!unassigned.contains(&v1));
+    }
+
+    #[test]
+    fn test_apply_constraint() {
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var_with_candidates(&[1,2,3]);
+        let v1 = sys.new_var_with_candidates(&[1,2,3]);
+        sys.equals(v0 + v1, 4);
+
+        let mut search = sys.step().expect("contradiction");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[1,2,3]);
+
+        // Manually place an assignment, then fire the constraint by
+        // hand to observe its propagation.
+        search.set_candidate(v0, 3).expect("candidate");
+        search.apply_constraint(0).expect("apply");
+        assert_eq!(search.get_unassigned(v1).collect::<Vec<Val>>(), &[1]);
+    }
+
+    #[test]
+    fn test_assign_singletons() {
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var_with_candidates(&[1,2,3]);
+        let v1 = sys.new_var_with_candidates(&[1,2,3]);
+        let v2 = sys.new_var_with_candidates(&[1,2,3]);
+
+        let mut search = sys.step().expect("contradiction");
+        search.set_candidate(v0, 2).expect("candidate");
+        search.set_candidate(v2, 3).expect("candidate");
+
+        assert_eq!(search.assign_singletons(), Ok(2));
+        assert!(search.is_assigned(v0));
+        assert!(!search.is_assigned(v1));
+        assert!(search.is_assigned(v2));
+        assert_eq!(search[v0], 2);
+        assert_eq!(search[v2], 3);
     }
 
-    /// Solve the puzzle, finding up to count solutions.
-    fn solve(&mut self, count: usize, solutions: &mut Vec<Solution>) {
-        if self.constrain().is_err() {
-            return;
+    #[test]
+    fn test_unify_tombstones_degenerate_constraints() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(6, &[1,2,3]);
+        for i in 0..5 {
+            sys.unify(vars[i], vars[i + 1]);
         }
 
-        let next_unassigned = self.vars.iter().enumerate().min_by_key(
-                |&(_, vs)| match vs {
-                    &VarState::Unassigned(ref cs) => cs.len(),
-                    _ => ::std::usize::MAX,
-                });
+        let count = sys.constraint_count();
+        assert_eq!(count, 5);
 
-        if let Some((idx, &VarState::Unassigned(ref cs))) = next_unassigned {
-            if cs.len() == 0 {
-                // Contradiction.
-                return;
-            }
+        let search = sys.step().expect("contradiction");
 
-            for val in cs.iter() {
-                let num_guesses = self.puzzle.num_guesses.get() + 1;
-                self.puzzle.num_guesses.set(num_guesses);
+        // Every Unify has collapsed to a self-unification, and has
+        // been dropped from the active set.  Indices remain stable.
+        assert_eq!(search.active_constraint_count(), 0);
+        for cidx in 0..count {
+            assert_eq!(search.get_constraint(cidx).vars().count(), 0);
+        }
+    }
 
-                let mut new = self.clone();
-                if new.assign(idx, val).is_err() {
-                    continue;
-                }
+    #[test]
+    fn test_canonical_hash() {
+        use std::collections::HashSet;
 
-                new.solve(count, solutions);
-                if solutions.len() >= count {
-                    // Reached desired number of solutions.
-                    return;
-                }
-            }
-        } else {
-            // No unassigned variables remaining.
-            let vars = (0..self.puzzle.num_vars).map(|idx|
-                    self[VarToken(idx)]).collect();
-            solutions.push(Solution{ vars: vars });
+        fn solve_first() -> ::Solution {
+            let mut sys = Puzzle::new();
+            let vars = sys.new_vars_with_candidates_1d(3, &[1,2,3]);
+            sys.all_different(&vars);
+            sys.solve_all().remove(0)
         }
+
+        let sol1 = solve_first();
+        let sol2 = solve_first();
+        assert_eq!(sol1.canonical_hash(), sol2.canonical_hash());
+
+        let mut set = HashSet::new();
+        set.insert(sol1);
+        set.insert(sol2);
+        assert_eq!(set.len(), 1);
     }
 
-    /// Assign a variable (given by index) to a value.
-    fn assign(&mut self, idx: usize, val: Val) -> PsResult<()> {
-        let var = VarToken(idx);
-        self.vars[idx] = VarState::Assigned(val);
-        self.wake.union_with(&self.constraints.wake[idx]);
+    #[test]
+    fn test_solve_maximize_knapsack() {
+        let weights = [4,3,2,5,3,2];
+        let values = [5,4,3,7,3,2];
 
-        for cidx in 0..self.constraints.constraints.len() {
-            if self.constraints.wake[idx].contains(cidx) {
-                let constraint = self.constraints.constraints[cidx].clone();
-                try!(constraint.on_assigned(self, var, val));
-            }
-        }
+        let make = || {
+            let mut sys = Puzzle::new();
+            let items = sys.new_vars_with_candidates_1d(6, &[0,1]);
+            let slack = sys.new_var_with_candidates(
+                    &(0..=8).collect::<Vec<Val>>());
 
-        Ok(())
+            let weight = items.iter().zip(weights.iter())
+                .fold(LinExpr::from(0), |sum, (&var, &w)| sum + var * w);
+            sys.equals(weight + slack, 8);
+
+            let objective = items.iter().zip(values.iter())
+                .fold(LinExpr::from(0), |sum, (&var, &v)| sum + var * v);
+            (sys, objective)
+        };
+
+        let (mut sys, objective) = make();
+        let outcome = sys.solve_maximize(objective, ::std::u64::MAX);
+        assert!(outcome.proven_optimal);
+        assert_eq!(outcome.stopped_by, StopReason::Exhausted);
+        let (_, value) = outcome.best.unwrap();
+        assert_eq!(value, 11.into());
+
+        // Capping the guesses returns a feasible incumbent, but
+        // cannot prove it optimal.
+        let (mut sys, objective) = make();
+        let outcome = sys.solve_maximize(objective, 10);
+        assert!(outcome.best.is_some());
+        assert!(!outcome.proven_optimal);
+        assert_eq!(outcome.stopped_by, StopReason::GuessLimit);
     }
 
-    /// Take any obvious non-choices, using the constraints to
-    /// eliminate candidates.  Stops when it must start guessing.
-    fn constrain(&mut self) -> PsResult<()> {
-        while !self.wake.is_empty() {
-            // "Gimme" phase:
-            // - abort if any variables with 0 candidates,
-            // - assign variables with only 1 candidate.
-            // - repeat until no more gimmes found.
-            let cycle = self.vars.len();
-            let mut idx = 0;
-            let mut last_gimme = cycle - 1;
-            loop {
-                let gimme = match &self.vars[idx] {
-                    &VarState::Assigned(_) => None,
-                    &VarState::Unassigned(ref cs) => match cs.len() {
-                        0 => return Err(()),
-                        1 => cs.iter().next(),
-                        _ => None,
-                    },
-                    &VarState::Unified(_) => None,
-                };
+    #[test]
+    fn test_last_stats_elapsed() {
+        use std::time::Duration;
 
-                if let Some(val) = gimme {
-                    try!(self.assign(idx, val));
-                    last_gimme = idx;
-                } else if idx == last_gimme {
-                    break;
-                }
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(6, &[1,2,3,4,5,6]);
+        sys.all_different(&vars);
+        sys.solve_all();
 
-                idx = if idx + 1 >= cycle { 0 } else { idx + 1 };
-            }
+        let stats = sys.last_stats();
+        assert!(stats.elapsed > Duration::new(0, 0));
+        assert_eq!(stats.decisions, sys.num_decisions());
+        assert_eq!(stats.guesses, sys.num_guesses());
+        assert_eq!(stats.backtracks, sys.num_backtracks());
+    }
 
-            // Apply constraints.
-            if !self.wake.is_empty() {
-                let wake = mem::replace(&mut self.wake, BitSet::new());
-                for cidx in wake.iter() {
-                    let constraint = self.constraints.constraints[cidx].clone();
-                    try!(constraint.on_updated(self));
-                }
-            }
-        }
+    #[test]
+    fn test_debug_wipeout() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1]);
+        sys.all_different(&vars);
+        sys.set_debug(true);
 
-        Ok(())
+        assert!(sys.step().is_none());
+        assert_eq!(sys.last_wipeout(), Some(vars[1]));
     }
 
-    /// Unify the "from" variable with the "to" variable.
-    pub fn unify(&mut self, from: VarToken, to: VarToken)
-            -> PsResult<()> {
-        if from == to {
-            return Ok(());
+    #[test]
+    fn test_metrics_sink() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize,Ordering};
+        use super::Metric;
+
+        let guesses = Arc::new(AtomicUsize::new(0));
+        let found = Arc::new(AtomicUsize::new(0));
+        let propagations = Arc::new(AtomicUsize::new(0));
+
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2,3]);
+        sys.all_different(&vars);
+
+        {
+            let (guesses, found, propagations)
+                = (guesses.clone(), found.clone(), propagations.clone());
+            sys.set_metrics_sink(Box::new(move |metric| match metric {
+                Metric::GuessTaken =>
+                    { guesses.fetch_add(1, Ordering::SeqCst); },
+                Metric::SolutionFound =>
+                    { found.fetch_add(1, Ordering::SeqCst); },
+                Metric::PropagationRun{..} =>
+                    { propagations.fetch_add(1, Ordering::SeqCst); },
+                Metric::Backtrack => (),
+            }));
         }
 
-        // Some preliminary checks, and maybe swap "from" and "to" in
-        // order to simplify our logic.
-        let (from, to) = {
-            let VarToken(search) = from;
-            let VarToken(replace) = to;
-            match (&self.vars[search], &self.vars[replace]) {
-                (&VarState::Assigned(val1), &VarState::Assigned(val2)) =>
-                    return bool_to_result(val1 == val2),
+        let solutions = sys.solve_all();
+        assert_eq!(guesses.load(Ordering::SeqCst), sys.num_guesses() as usize);
+        assert_eq!(found.load(Ordering::SeqCst), solutions.len());
+        assert!(propagations.load(Ordering::SeqCst) > 0);
+    }
 
-                // Unified variables should have been substituted out previously.
-                (&VarState::Unified(_), _) | (_, &VarState::Unified(_)) =>
-                    panic!("internal representation corrupt"),
+    #[test]
+    fn test_step_resets_guesses() {
+        let mut sys = Puzzle::new();
+        sys.new_var_with_candidates(&[1,2]);
+        sys.new_var_with_candidates(&[1,2]);
 
-                (&VarState::Unassigned(_), &VarState::Assigned(_)) =>
-                    (to, from),
+        sys.solve_all();
+        assert!(sys.num_guesses() > 0);
 
-                (&VarState::Assigned(_), &VarState::Unassigned(_))
-                | (&VarState::Unassigned(_), &VarState::Unassigned(_)) =>
-                    (from, to),
-            }
-        };
+        sys.step().expect("contradiction");
+        assert_eq!(sys.num_guesses(), 0);
+    }
 
-        let VarToken(search) = from;
-        let VarToken(replace) = to;
+    #[test]
+    fn test_arrow() {
+        let mut sys = Puzzle::new();
+        let bulb = sys.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+        let path = sys.new_vars_with_candidates_1d(2, &[1,2,3,4,5,6,7,8,9]);
+        sys.arrow(bulb, &path);
+        sys.set_value(path[0], 2);
+        sys.set_value(path[1], 3);
 
-        // Create new constraints to reflect the unification.
-        let new_constraints = try!(self.constraints.substitute(from, to));
-        self.constraints = Rc::new(new_constraints);
-        self.wake.union_with(&self.constraints.wake[replace]);
-        assert!(self.constraints.wake[search].is_empty());
+        let search = sys.step().expect("contradiction");
+        assert_eq!(search[bulb], 5);
+    }
 
-        // Take intersection of the candidates.
-        if let &VarState::Assigned(val) = &self.vars[search] {
-            try!(self.set_candidate(to, val));
-        } else {
-            if let (&mut VarState::Unassigned(Candidates::Set(ref mut rc1)),
-                    &mut VarState::Unassigned(Candidates::Set(ref mut rc2)))
-                    = get_two_mut(&mut self.vars, search, replace) {
-                *rc2 = Rc::new(rc2.intersection(rc1).cloned().collect());
-                if rc2.is_empty() {
-                    return Err(());
-                }
-            }
-        }
+    #[test]
+    fn test_mutation_with_retained_candidates() {
+        let mut sys = Puzzle::new();
+        let var = sys.new_var_with_candidates(&[1,2,3]);
 
-        self.vars[search] = VarState::Unified(to);
-        Ok(())
+        // Simulate a retained search holding the candidate Rc.
+        let snapshot = sys.candidates[0].clone();
+
+        sys.remove_candidates(var, &[3]);
+        sys.insert_candidates(var, &[4]);
+
+        // Copy-on-write: the snapshot is isolated from the mutations.
+        assert_eq!(snapshot.iter().collect::<Vec<Val>>(), &[1,2,3]);
+        assert_eq!(sys.candidates[0].iter().collect::<Vec<Val>>(), &[1,2,4]);
     }
-}
 
-impl<'a> fmt::Debug for PuzzleSearch<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "PuzzleSearch={{")?;
-        for (idx, var) in self.vars.iter().enumerate() {
-            writeln!(f, "")?;
-            match var {
-                &VarState::Assigned(val) => {
-                    write!(f, "  var {}: {}", idx, val)?;
-                },
-                &VarState::Unassigned(ref cs) => {
-                    write!(f, "  var {}:", idx)?;
-                    for val in cs.iter() {
-                        write!(f, " {}", val)?;
-                    }
-                },
-                &VarState::Unified(VarToken(other)) => {
-                    write!(f, "  var {}: var {}", idx, other)?;
-                },
-            }
+    #[test]
+    fn test_deep_model() {
+        // A few thousand chained guesses: the search must not
+        // overflow the thread stack.
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(5000, &[0,1,2]);
+        for pair in vars.chunks(2) {
+            sys.equals(pair[0] + pair[1], 2);
         }
-        write!(f, "}}")?;
-        Ok(())
+
+        let solution = sys.solve_any().expect("solution");
+        assert_eq!(solution[vars[0]] + solution[vars[1]], 2);
+        assert_eq!(sys.num_guesses(), 2500);
     }
-}
 
-impl<'a> ops::Index<VarToken> for PuzzleSearch<'a> {
-    type Output = Val;
+    #[test]
+    fn test_statistics() {
+        let mut sys = Puzzle::new();
+        let _vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
 
-    /// Get the value assigned to a variable.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the variable has not been assigned.
-    fn index(&self, var: VarToken) -> &Val {
-        let VarToken(idx) = var;
-        match &self.vars[idx] {
-            &VarState::Assigned(ref val) => val,
-            &VarState::Unassigned(_) => panic!("unassigned"),
-            &VarState::Unified(other) => &self[other],
+        // Unconstrained: every combination is enumerated without any
+        // dead ends.
+        let solutions = sys.solve_all();
+        assert_eq!(solutions.len(), 4);
+        assert_eq!(sys.num_decisions(), 3);
+        assert_eq!(sys.num_guesses(), 6);
+        assert_eq!(sys.num_backtracks(), 0);
+    }
+
+    #[test]
+    fn test_fluent_construction() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2,3]);
+        sys.all_different(&vars)
+            .equals(vars[0] + 2 * vars[1], 5)
+            .sum_odd(vars[2]);
+
+        let solution = sys.solve_unique().expect("solution");
+        assert_eq!(solution[vars[2]], 3);
+    }
+
+    #[test]
+    fn test_validate_equals() {
+        let mut sys = Puzzle::new();
+        sys.equals(3, 5);
+
+        let violations = sys.validate().unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("equals"));
+    }
+
+    #[test]
+    fn test_validate_unify() {
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var_with_candidates(&[1,2]);
+        let v1 = sys.new_var_with_candidates(&[1,2]);
+        sys.set_value(v0, 1);
+        sys.set_value(v1, 2);
+        sys.unify(v0, v1);
+
+        let violations = sys.validate().unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("unify"));
+    }
+
+    #[test]
+    fn test_validate_all_different() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2]);
+        sys.all_different(&vars);
+
+        let violations = sys.validate().unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("all_different"));
+    }
+
+    #[test]
+    fn test_validate_feasible() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(2, &[1,2]);
+        sys.all_different(&vars);
+        sys.unify(vars[0], vars[1]);
+        sys.equals(vars[0] + vars[1], 3);
+
+        assert!(sys.validate().is_ok());
+    }
+
+    #[test]
+    fn test_solve_with_lookahead() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2,3]);
+        sys.all_different(&vars);
+        sys.remove_candidates(vars[0], &[1]);
+        sys.remove_candidates(vars[1], &[2]);
+
+        let solution = sys.solve_with_lookahead(2).expect("solution");
+        assert!(solution[vars[0]] != 1);
+        assert!(solution[vars[1]] != 2);
+        assert!(solution[vars[0]] != solution[vars[1]]);
+    }
+
+    #[test]
+    fn test_solution_count() {
+        let mut sys = Puzzle::new();
+        sys.new_vars_with_candidates_1d(4, &[0,1]);
+        assert_eq!(sys.solution_count(10), SolutionCount::AtLeast(10));
+        assert_eq!(sys.solution_count(100), SolutionCount::Exact(16));
+
+        let mut sys = Puzzle::new();
+        let var = sys.new_var_with_candidates(&[1,2,3]);
+        sys.set_value(var, 2);
+        assert_eq!(sys.solution_count(10), SolutionCount::Exact(1));
+    }
+
+    #[test]
+    fn test_solution_index_usize() {
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2,3]);
+        sys.all_different(&vars);
+
+        let solution = sys.solve_any().expect("solution");
+        for (idx, &var) in vars.iter().enumerate() {
+            assert_eq!(solution[idx], solution[var]);
         }
     }
-}
 
-fn bool_to_result(cond: bool) -> PsResult<()> {
-    if cond {
-        Ok(())
-    } else {
-        Err(())
+    #[test]
+    fn test_invalidate_between_solves() {
+        let mut sys = Puzzle::new();
+        let v0 = sys.new_var_with_candidates(&[1,2]);
+        let v1 = sys.new_var_with_candidates(&[3,4]);
+        assert_eq!(sys.solve_all().len(), 4);
+
+        sys.remove_candidates(v0, &[2]);
+        sys.intersect_candidates(v1, &[4]);
+        sys.invalidate();
+        assert_eq!(sys.num_guesses(), 0);
+
+        let solutions = sys.solve_all();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0][v0], 1);
+        assert_eq!(solutions[0][v1], 4);
     }
-}
 
-fn get_two_mut<'a, T>(slice: &'a mut [T], a: usize, b: usize)
-        -> (&'a mut T, &'a mut T) {
-    assert!(a != b);
-    if a < b {
-        let (mut l, mut r) = slice.split_at_mut(b);
-        (&mut l[a], &mut r[0])
-    } else {
-        let (l, r) = slice.split_at_mut(a);
-        (&mut r[0], &mut l[b])
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_solve_random_restart() {
+        let mut rng = ::rand::thread_rng();
+
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(4, &[1,2,3,4]);
+        sys.all_different(&vars);
+
+        let solution = sys.solve_random_restart(&mut rng, 8);
+        assert!(solution.is_some());
+
+        let mut sys = Puzzle::new();
+        let vars = sys.new_vars_with_candidates_1d(3, &[1,2]);
+        sys.all_different(&vars);
+
+        let solution = sys.solve_random_restart(&mut rng, 4);
+        assert!(solution.is_none());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use ::Puzzle;
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_solutions() {
+        use std::collections::HashSet;
+
+        let mut sys = Puzzle::new();
+        sys.new_var_with_candidates(&[1,2]);
+        sys.new_var_with_candidates(&[1,2]);
+
+        // All 4 symmetric solutions show up in a large enough sample.
+        let samples = sys.sample_solutions(100, 12345);
+        assert_eq!(samples.len(), 100);
+
+        let distinct: HashSet<Solution> = samples.into_iter().collect();
+        assert_eq!(distinct.len(), 4);
+    }
 
     #[test]
     fn test_no_vars() {
@@ -0,0 +1,103 @@
+//! Proptest strategies for generating random puzzles.
+//!
+//! These are intended for property testing constraints and the
+//! solver together, e.g. comparing the results of `Puzzle::solve_all`
+//! against the reference `Puzzle::brute_force`.  Only available with
+//! the "proptest" feature.
+
+use proptest::prelude::*;
+use proptest::sample::subsequence;
+
+use ::{LinExpr,Puzzle,Val,VarToken};
+
+/// A plain-data description of a puzzle constraint.  The variables
+/// are identified by index.
+#[derive(Clone,Debug)]
+pub enum ConstraintSpec {
+    /// The variables take distinct values.
+    AllDifferent(Vec<usize>),
+    /// The sum of the variables equals the total.
+    Equality(Vec<usize>, Val),
+    /// The two variables take the same value.
+    Unify(usize, usize),
+}
+
+/// A plain-data description of a puzzle, as generated by the
+/// `puzzles` strategy.
+#[derive(Clone,Debug)]
+pub struct PuzzleSpec {
+    /// The candidates for each variable.
+    pub domains: Vec<Vec<Val>>,
+
+    /// The puzzle constraints.
+    pub constraints: Vec<ConstraintSpec>,
+}
+
+impl PuzzleSpec {
+    /// Build the described puzzle.
+    pub fn build(&self) -> (Puzzle, Vec<VarToken>) {
+        let mut sys = Puzzle::new();
+        let vars: Vec<VarToken> = self.domains.iter()
+            .map(|domain| sys.new_var_with_candidates(domain))
+            .collect();
+
+        for spec in self.constraints.iter() {
+            match spec {
+                &ConstraintSpec::AllDifferent(ref idxs) => {
+                    sys.all_different(idxs.iter().map(|&idx| &vars[idx]));
+                },
+                &ConstraintSpec::Equality(ref idxs, total) => {
+                    let mut expr = LinExpr::from(-total);
+                    for &idx in idxs.iter() {
+                        expr = expr + vars[idx];
+                    }
+                    sys.equals(expr, 0);
+                },
+                &ConstraintSpec::Unify(a, b) => {
+                    sys.unify(vars[a], vars[b]);
+                },
+            }
+        }
+
+        (sys, vars)
+    }
+}
+
+/// Strategy generating random small puzzles with up to the given
+/// number of variables, candidates drawn from 1..=max_val, and a mix
+/// of the built-in constraints.
+pub fn puzzles(max_vars: usize, max_val: Val, max_constraints: usize)
+        -> BoxedStrategy<PuzzleSpec> {
+    (1..=max_vars).prop_flat_map(move |num_vars| {
+        let domain_vals: Vec<Val> = (1..=max_val).collect();
+        let domains = proptest::collection::vec(
+                subsequence(domain_vals, 1..=(max_val as usize)),
+                num_vars);
+        let constraints = proptest::collection::vec(
+                constraint_specs(num_vars, max_val),
+                0..=max_constraints);
+
+        (domains, constraints).prop_map(|(domains, constraints)|
+                PuzzleSpec {
+                    domains: domains,
+                    constraints: constraints,
+                })
+    }).boxed()
+}
+
+/// Strategy generating a single random constraint over the given
+/// number of variables.
+fn constraint_specs(num_vars: usize, max_val: Val)
+        -> BoxedStrategy<ConstraintSpec> {
+    let indices: Vec<usize> = (0..num_vars).collect();
+    let max_total = max_val * (num_vars as Val);
+
+    prop_oneof![
+        subsequence(indices.clone(), 1..=num_vars)
+            .prop_map(ConstraintSpec::AllDifferent),
+        (subsequence(indices, 1..=num_vars), 0..=max_total)
+            .prop_map(|(idxs, total)| ConstraintSpec::Equality(idxs, total)),
+        (0..num_vars, 0..num_vars)
+            .prop_map(|(a, b)| ConstraintSpec::Unify(a, b)),
+    ].boxed()
+}
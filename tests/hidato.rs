@@ -107,5 +107,6 @@ fn hidato_wikipedia() {
     let dict = sys.solve_any().expect("solution");
     print_hidato(&dict, &vars);
     verify_hidato(&dict, &vars, &expected);
-    println!("hidato_wikipedia: {} guesses", sys.num_guesses());
+    println!("hidato_wikipedia: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
@@ -101,5 +101,6 @@ fn kakuro_wikipedia() {
     let dict = sys.solve_any().expect("solution");
     print_kakuro(&dict, &vars);
     verify_kakuro(&dict, &vars, &expected);
-    println!("kakuro_wikipedia: {} guesses", sys.num_guesses());
+    println!("kakuro_wikipedia: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
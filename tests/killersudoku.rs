@@ -60,6 +60,26 @@ fn verify_sudoku(dict: &Solution, vars: &Vec<Vec<VarToken>>, expected: &Board) {
     }
 }
 
+#[test]
+fn killersudoku_cage_var() {
+    let mut sys = Puzzle::new();
+    let total = sys.new_var_with_candidates(&[1,2,3,4,5,6,7,8,9]);
+    let cells = sys.new_vars_with_candidates_1d(2, &[1,2,3,4]);
+    sys.cage_var(total, &cells);
+
+    // The cells take 3 and 4 in some order: the total is deduced,
+    // even though there are two cell arrangements.
+    sys.intersect_candidates(cells[0], &[3,4]);
+    sys.intersect_candidates(cells[1], &[3,4]);
+
+    let solutions = sys.solve_all();
+    assert_eq!(solutions.len(), 2);
+    for dict in solutions.iter() {
+        assert_eq!(dict[total], 7);
+        assert_eq!(dict[cells[0]] + dict[cells[1]], 7);
+    }
+}
+
 #[test]
 fn killersudoku_wikipedia() {
     let puzzle = [
@@ -111,5 +131,6 @@ fn killersudoku_wikipedia() {
     let dict = sys.solve_any().expect("solution");
     print_sudoku(&dict, &vars);
     verify_sudoku(&dict, &vars, &expected);
-    println!("killersudoku_wikipedia: {} guesses", sys.num_guesses());
+    println!("killersudoku_wikipedia: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
@@ -5,6 +5,7 @@
 extern crate puzzle_solver;
 
 use puzzle_solver::{LinExpr,Puzzle,Solution,Val,VarToken};
+use puzzle_solver::constraint::MagicConstant;
 
 fn make_magic_square(n: usize) -> (Puzzle, Vec<Vec<VarToken>>, VarToken) {
     let mut sys = Puzzle::new();
@@ -31,8 +32,8 @@ fn make_magic_square(n: usize) -> (Puzzle, Vec<Vec<VarToken>>, VarToken) {
         sys.equals(total, (0..n).fold(LinExpr::from(0), |sum, i| sum + vars[i][n - i - 1]));
     }
 
-    // Sum of all digits = sum of all rows (columns) = total * n.
-    sys.equals(total * (n as Val), digits.iter().sum::<Val>());
+    // Sum of all variables = total * n.
+    sys.add_constraint(MagicConstant::new(vars.clone(), total));
 
     (sys, vars, total)
 }
@@ -56,7 +57,25 @@ fn magicsquare_3x3() {
     for dict in solutions.iter() {
         assert_eq!(dict[total], 15);
     }
-    println!("magicsquare_3x3: {} guesses", sys.num_guesses());
+    println!("magicsquare_3x3: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+
+    // solve_all returns the solutions in lexicographic order.
+    let squares: Vec<Vec<Val>> = solutions.iter()
+        .map(|dict| vars.iter()
+             .flat_map(|row| row.iter())
+             .map(|&var| dict[var])
+             .collect())
+        .collect();
+    assert_eq!(squares, [
+        [ 2,7,6, 9,5,1, 4,3,8 ],
+        [ 2,9,4, 7,5,3, 6,1,8 ],
+        [ 4,3,8, 9,5,1, 2,7,6 ],
+        [ 4,9,2, 3,5,7, 8,1,6 ],
+        [ 6,1,8, 7,5,3, 2,9,4 ],
+        [ 6,7,2, 1,5,9, 8,3,4 ],
+        [ 8,1,6, 3,5,7, 4,9,2 ],
+        [ 8,3,4, 1,5,9, 6,7,2 ] ]);
 }
 
 #[test]
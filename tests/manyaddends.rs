@@ -0,0 +1,64 @@
+//! Cryptarithms with many addends sharing common sub-sums.
+
+extern crate puzzle_solver;
+
+use puzzle_solver::{Puzzle,VarToken};
+
+/// Several addition equations all containing the same two unknown
+/// words.  Each equation has many terms, so its bound propagation
+/// is weak until the shared words are factored into auxiliaries.
+fn make_shared_words(factor: bool) -> (Puzzle, Vec<VarToken>) {
+    let mut sys = Puzzle::new();
+    let digits = sys.new_vars_with_candidates_1d(10,
+            &[0,1,2,3,4,5,6,7,8,9]);
+    sys.all_different(&digits);
+
+    let word1 = 100 * digits[0] + 10 * digits[1] + digits[2];
+    let word2 = 100 * digits[3] + 10 * digits[4] + digits[5];
+
+    // One solution: 974 + 352 = 1326, with 8, 6, 0, 1 left over.
+    let perturb = [ (digits[6], 1, 1334), (digits[7], 10, 1386),
+                    (digits[8], 100, 1326), (digits[9], 1000, 2326) ];
+    for &(var, scale, target) in perturb.iter() {
+        sys.equals(word1.clone() + word2.clone() + scale * var, target);
+    }
+
+    if factor {
+        sys.factor_common_subexpressions(2);
+    }
+
+    (sys, digits)
+}
+
+#[test]
+fn manyaddends_solutions() {
+    let (mut sys, digits) = make_shared_words(false);
+    let expected = sys.solve_all();
+    assert!(!expected.is_empty());
+
+    // Factoring must not change the solutions over the original
+    // variables.
+    let (mut sys2, digits2) = make_shared_words(true);
+    let actual = sys2.solve_all();
+    assert_eq!(actual.len(), expected.len());
+    for (sol1, sol2) in expected.iter().zip(actual.iter()) {
+        for (&v1, &v2) in digits.iter().zip(digits2.iter()) {
+            assert_eq!(sol1[v1], sol2[v2]);
+        }
+    }
+}
+
+#[test]
+fn manyaddends_factoring_reduces_guesses() {
+    let (mut sys, _) = make_shared_words(false);
+    sys.solve_all();
+    let plain_guesses = sys.num_guesses();
+
+    let (mut sys, _) = make_shared_words(true);
+    sys.solve_all();
+    let factored_guesses = sys.num_guesses();
+
+    println!("manyaddends: {} vs {} guesses",
+             factored_guesses, plain_guesses);
+    assert!(factored_guesses * 2 < plain_guesses);
+}
@@ -293,5 +293,6 @@ fn nonogram_wikipedia() {
     let dict = sys.solve_unique().expect("solution");
     print_nonogram(&dict, &vars);
     verify_nonogram(&dict, &vars, &expected);
-    println!("nonogram_wikipedia: {} guesses.", sys.num_guesses());
+    println!("nonogram_wikipedia: {} decisions, {} guesses, {} backtracks.",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
@@ -0,0 +1,33 @@
+//! Property tests comparing the solver against brute force.
+//!
+//! Run with: cargo test --features proptest
+
+#![cfg(feature = "proptest")]
+
+extern crate proptest;
+extern crate puzzle_solver;
+
+use proptest::prelude::*;
+use puzzle_solver::{Val,strategy};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn solve_all_matches_brute_force(spec in strategy::puzzles(4, 4, 3)) {
+        let (mut sys, vars) = spec.build();
+        let expected = sys.brute_force();
+        let actual = sys.solve_all();
+
+        let mut expected: Vec<Vec<Val>> = expected.iter()
+            .map(|dict| vars.iter().map(|&var| dict[var]).collect())
+            .collect();
+        let mut actual: Vec<Vec<Val>> = actual.iter()
+            .map(|dict| vars.iter().map(|&var| dict[var]).collect())
+            .collect();
+
+        expected.sort();
+        actual.sort();
+        prop_assert_eq!(expected, actual);
+    }
+}
@@ -4,6 +4,7 @@
 
 extern crate puzzle_solver;
 
+use std::collections::HashSet;
 use std::rc::Rc;
 use puzzle_solver::*;
 
@@ -63,7 +64,28 @@ fn queens_4x4() {
     let dict = sys.solve_all();
     assert_eq!(dict.len(), 2);
     print_queens(&dict[0], &vars);
-    println!("queens_4x4: {} guesses", sys.num_guesses());
+    println!("queens_4x4: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+
+    // solve_all returns the solutions in lexicographic order.
+    let rows: Vec<Vec<Val>> = dict.iter()
+        .map(|dict| vars.iter().map(|&var| dict[var]).collect())
+        .collect();
+    assert_eq!(rows, [ [1,3,0,2], [2,0,3,1] ]);
+
+    // Eager singleton promotion keeps the search cost bounded.
+    assert!(sys.num_guesses() <= 8);
+}
+
+#[test]
+fn queens_4x4_csv() {
+    let (mut sys, vars) = make_queens(4);
+    let dict = sys.solve_all();
+    assert_eq!(dict.len(), 2);
+
+    let mut out = Vec::new();
+    write_csv(&dict, &vars, &["r0","r1","r2","r3"], &mut out).unwrap();
+    assert_eq!(out, b"r0,r1,r2,r3\n1,3,0,2\n2,0,3,1\n");
 }
 
 #[test]
@@ -72,7 +94,20 @@ fn queens_5x5() {
     let dict = sys.solve_all();
     assert_eq!(dict.len(), 10);
     print_queens(&dict[0], &vars);
-    println!("queens_5x5: {} guesses", sys.num_guesses());
+    println!("queens_5x5: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[test]
+fn queens_5x5_count_by_value() {
+    let (mut sys, vars) = make_queens(5);
+    let counts = sys.count_by_value(vars[0]);
+
+    // By symmetry, each column appears in 2 of the 10 solutions.
+    assert_eq!(counts.len(), 5);
+    for (_, &count) in counts.iter() {
+        assert_eq!(count, 2);
+    }
 }
 
 #[test]
@@ -81,7 +116,27 @@ fn queens_6x6() {
     let dict = sys.solve_all();
     assert_eq!(dict.len(), 4);
     print_queens(&dict[0], &vars);
-    println!("queens_6x6: {} guesses", sys.num_guesses());
+    println!("queens_6x6: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[test]
+fn queens_6x6_frontier() {
+    let (mut sys, _) = make_queens(6);
+    let expected: HashSet<Solution> = sys.solve_all().into_iter().collect();
+
+    // Split into depth-2 subproblems and solve each independently.
+    let mut total = 0;
+    let mut actual = HashSet::new();
+    for search in sys.frontier(2).iter() {
+        let solutions = sys.solve_all_from(search);
+        total = total + solutions.len();
+        actual.extend(solutions);
+    }
+
+    // The subproblems are disjoint and cover the search space.
+    assert_eq!(total, expected.len());
+    assert_eq!(actual, expected);
 }
 
 #[test]
@@ -90,7 +145,45 @@ fn queens_7x7() {
     let dict = sys.solve_all();
     assert_eq!(dict.len(), 40);
     print_queens(&dict[0], &vars);
-    println!("queens_7x7: {} guesses", sys.num_guesses());
+    println!("queens_7x7: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[test]
+fn queens_8x8_checkpoint() {
+    let (mut sys, vars) = make_queens(8);
+    let mut solutions = Vec::new();
+
+    // Find the first half of the solutions, then checkpoint.
+    let checkpoint = {
+        let mut solver = sys.solver();
+        while solutions.len() < 46 {
+            match solver.run_for(100) {
+                SolverStatus::Running => (),
+                SolverStatus::Solved(sol) => solutions.push(sol),
+                SolverStatus::Unsat => panic!("search space exhausted"),
+            }
+        }
+        solver.checkpoint()
+    };
+
+    // Restore into a fresh puzzle built the same way, and finish.
+    let (sys, _) = make_queens(8);
+    let mut solver = Solver::restore(&sys, &checkpoint);
+    loop {
+        match solver.run_for(100) {
+            SolverStatus::Running => (),
+            SolverStatus::Solved(sol) => solutions.push(sol),
+            SolverStatus::Unsat => break,
+        }
+    }
+
+    let mut rows: Vec<Vec<Val>> = solutions.iter()
+        .map(|sol| vars.iter().map(|&var| sol[var]).collect())
+        .collect();
+    rows.sort();
+    rows.dedup();
+    assert_eq!(rows.len(), 92);
 }
 
 #[test]
@@ -99,5 +192,6 @@ fn queens_8x8() {
     let dict = sys.solve_all();
     assert_eq!(dict.len(), 92);
     print_queens(&dict[0], &vars);
-    println!("queens_8x8: {} guesses", sys.num_guesses());
+    println!("queens_8x8: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
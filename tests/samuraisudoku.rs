@@ -116,9 +116,7 @@ fn verify_samurai_sudoku(dict: &Solution, vars: &SamuraiVars, expected: &Board)
     }
 }
 
-#[test]
-fn samuraisudoku_easy() {
-    let puzzle = [
+const EASY_PUZZLE: Board = [
         [ 0,0,3,  0,0,0,  2,0,0,  X,X,X,  0,0,6,  0,0,0,  2,0,0 ],
         [ 0,2,0,  4,0,8,  0,3,0,  X,X,X,  0,3,0,  4,0,2,  0,8,0 ],
         [ 8,0,0,  0,9,0,  0,0,4,  X,X,X,  8,0,0,  0,1,0,  0,0,4 ],
@@ -147,7 +145,7 @@ fn samuraisudoku_easy() {
         [ 0,2,0,  3,0,5,  0,1,0,  X,X,X,  0,9,0,  6,0,4,  0,3,0 ],
         [ 0,0,6,  0,0,0,  2,0,0,  X,X,X,  0,0,4,  0,0,0,  1,0,0 ] ];
 
-    let expected = [
+const EASY_SOLUTION: Board = [
         [ 4,9,3,  7,1,5,  2,6,8,  X,X,X,  9,4,6,  8,3,5,  2,1,7 ],
         [ 5,2,7,  4,6,8,  9,3,1,  X,X,X,  1,3,7,  4,9,2,  5,8,6 ],
         [ 8,6,1,  2,9,3,  5,7,4,  X,X,X,  8,5,2,  7,1,6,  9,3,4 ],
@@ -176,9 +174,26 @@ fn samuraisudoku_easy() {
         [ 9,2,4,  3,6,5,  7,1,8,  X,X,X,  2,9,5,  6,1,4,  7,3,8 ],
         [ 7,3,6,  8,1,4,  2,5,9,  X,X,X,  3,8,4,  7,5,9,  1,6,2 ] ];
 
-    let (mut sys, vars) = make_samurai_sudoku(&puzzle);
+#[test]
+fn samuraisudoku_easy() {
+    let (mut sys, vars) = make_samurai_sudoku(&EASY_PUZZLE);
     let dict = sys.solve_any().expect("solution");
     print_samurai_sudoku(&dict, &vars);
-    verify_samurai_sudoku(&dict, &vars, &expected);
-    println!("samuraisudoku_easy: {} guesses", sys.num_guesses());
+    verify_samurai_sudoku(&dict, &vars, &EASY_SOLUTION);
+    println!("samuraisudoku_easy: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[cfg(feature = "grouped")]
+#[test]
+fn samuraisudoku_easy_grouped() {
+    let (mut sys, vars) = make_samurai_sudoku(&EASY_PUZZLE);
+    sys.set_grouped(true);
+    let dict = sys.solve_any().expect("solution");
+    verify_samurai_sudoku(&dict, &vars, &EASY_SOLUTION);
+    let grouped_guesses = sys.num_guesses();
+
+    let (mut sys, _) = make_samurai_sudoku(&EASY_PUZZLE);
+    sys.solve_any().expect("solution");
+    assert_eq!(grouped_guesses, sys.num_guesses());
 }
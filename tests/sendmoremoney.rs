@@ -69,7 +69,47 @@ fn sendmoremoney_carry() {
     let dict = sys.solve_unique().expect("solution");
     print_send_more_money(&dict, &vars);
     verify_send_more_money(&dict, &vars);
-    println!("sendmoremoney_carry: {} guesses", sys.num_guesses());
+    println!("sendmoremoney_carry: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[test]
+fn sendmoremoney_find_redundant() {
+    let carry = [0,1];
+
+    let (mut sys, vars) = make_send_more_money();
+    let (s, e, n, d) = (vars[0], vars[1], vars[2], vars[3]);
+    let (m, o, r, y) = (vars[4], vars[5], vars[6], vars[7]);
+    let c1 = sys.new_var_with_candidates(&carry);
+    let c2 = sys.new_var_with_candidates(&carry);
+    let c3 = sys.new_var_with_candidates(&carry);
+    sys.intersect_candidates(m, &carry); // c4 == m.
+
+    sys.equals(     d + e, 10 * c1 + y);
+    sys.equals(c1 + n + r, 10 * c2 + e);
+    sys.equals(c2 + e + o, 10 * c3 + n);
+    sys.equals(c3 + s + m, 10 *  m + o);
+
+    let redundant = sys.find_redundant(4);
+    println!("sendmoremoney_find_redundant: {:?}", redundant);
+
+    // The whole-number equation (constraint 1, after the
+    // all-different at 0) is implied by the column equations.
+    assert!(redundant.contains(&1));
+    assert!(!redundant.contains(&0));
+}
+
+#[test]
+fn sendmoremoney_format() {
+    let (mut sys, vars) = make_send_more_money();
+    let names = ["s", "e", "n", "d", "m", "o", "r", "y"];
+    for (&var, name) in vars.iter().zip(names.iter()) {
+        sys.name_var(name, var);
+    }
+
+    let dict = sys.solve_unique().expect("solution");
+    let text = dict.format("{s}{e}{n}{d} + {m}{o}{r}{e} = {m}{o}{n}{e}{y}", &sys);
+    assert_eq!(text, "9567 + 1085 = 10652");
 }
 
 #[test]
@@ -78,5 +118,6 @@ fn sendmoremoney_naive() {
     let dict = sys.solve_unique().expect("solution");
     print_send_more_money(&dict, &vars);
     verify_send_more_money(&dict, &vars);
-    println!("sendmoremoney_naive: {} guesses", sys.num_guesses());
+    println!("sendmoremoney_naive: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
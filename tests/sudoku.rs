@@ -4,7 +4,7 @@
 
 extern crate puzzle_solver;
 
-use puzzle_solver::{Puzzle,Solution,Val,VarToken};
+use puzzle_solver::{ProbeResult,Puzzle,Solution,SolverStatus,Val,VarToken};
 
 const SQRT_SIZE: usize = 3;
 const SIZE: usize = 9;
@@ -12,7 +12,7 @@ type Board = [[Val; SIZE]; SIZE];
 
 fn make_sudoku(board: &Board) -> (Puzzle, Vec<Vec<VarToken>>) {
     let mut sys = Puzzle::new();
-    let vars = sys.new_vars_with_candidates_2d(SIZE, SIZE, &[1,2,3,4,5,6,7,8,9]);
+    let vars = sys.new_vars_with_range_2d(SIZE, SIZE, 1..=9);
 
     for y in 0..SIZE {
         sys.all_different(&vars[y]);
@@ -96,7 +96,245 @@ fn sudoku_hardest() {
     let solution = sys.solve_any().expect("solution");
     print_sudoku(&solution, &vars);
     verify_sudoku(&solution, &vars, &expected);
-    println!("sudoku_hardest: {} guesses", sys.num_guesses());
+    println!("sudoku_hardest: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[test]
+fn sudoku_hardest_sliced() {
+    let puzzle = [
+        [ 8,0,0,  0,0,0,  0,0,0 ],
+        [ 0,0,3,  6,0,0,  0,0,0 ],
+        [ 0,7,0,  0,9,0,  2,0,0 ],
+
+        [ 0,5,0,  0,0,7,  0,0,0 ],
+        [ 0,0,0,  0,4,5,  7,0,0 ],
+        [ 0,0,0,  1,0,0,  0,3,0 ],
+
+        [ 0,0,1,  0,0,0,  0,6,8 ],
+        [ 0,0,8,  5,0,0,  0,1,0 ],
+        [ 0,9,0,  0,0,0,  4,0,0 ] ];
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+    let expected = sys.solve_any().expect("solution");
+
+    let (mut sys, _) = make_sudoku(&puzzle);
+    let mut solver = sys.solver();
+    let mut slices = 0;
+    let solution = loop {
+        match solver.run_for(5) {
+            SolverStatus::Running => slices = slices + 1,
+            SolverStatus::Solved(solution) => break solution,
+            SolverStatus::Unsat => panic!("unsat"),
+        }
+    };
+
+    println!("sudoku_hardest_sliced: {} slices", slices);
+    assert!(slices > 1);
+
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            assert_eq!(solution[vars[y][x]], expected[vars[y][x]]);
+        }
+    }
+}
+
+#[test]
+fn sudoku_gimme_passes() {
+    let puzzle = [
+        [ 5,3,0,  0,7,0,  0,0,0 ],
+        [ 6,0,0,  1,9,5,  0,0,0 ],
+        [ 0,9,8,  0,0,0,  0,6,0 ],
+
+        [ 8,0,0,  0,6,0,  0,0,3 ],
+        [ 4,0,0,  8,0,3,  0,0,1 ],
+        [ 7,0,0,  0,2,0,  0,0,6 ],
+
+        [ 0,6,0,  0,0,0,  2,8,0 ],
+        [ 0,0,0,  4,1,9,  0,0,5 ],
+        [ 0,0,0,  0,8,0,  0,7,9 ] ];
+
+    let (mut sys, _) = make_sudoku(&puzzle);
+    sys.solve_any().expect("solution");
+
+    // The gimme scans are driven by the changed variables, rather
+    // than cycling over all of the variables.
+    assert!(sys.num_gimme_passes() > 0);
+    println!("sudoku_gimme_passes: {} passes, {} guesses",
+             sys.num_gimme_passes(), sys.num_guesses());
+}
+
+#[test]
+fn sudoku_unassigned_vars() {
+    let puzzle = [
+        [ 8,0,0,  0,0,0,  0,0,0 ],
+        [ 0,0,3,  6,0,0,  0,0,0 ],
+        [ 0,7,0,  0,9,0,  2,0,0 ],
+
+        [ 0,5,0,  0,0,7,  0,0,0 ],
+        [ 0,0,0,  0,4,5,  7,0,0 ],
+        [ 0,0,0,  1,0,0,  0,3,0 ],
+
+        [ 0,0,1,  0,0,0,  0,6,8 ],
+        [ 0,0,8,  5,0,0,  0,1,0 ],
+        [ 0,9,0,  0,0,0,  4,0,0 ] ];
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+    let search = sys.step().expect("contradiction");
+
+    let expected = vars.iter()
+        .flat_map(|row| row.iter())
+        .filter(|&&var| !search.is_assigned(var))
+        .count();
+
+    assert!(expected > 0);
+    assert_eq!(search.unassigned_vars().count(), expected);
+}
+
+#[test]
+fn sudoku_disjoint_groups() {
+    // A disjoint-groups compatible solution, from which the first row
+    // is taken as the givens.
+    let mut puzzle = [[0; SIZE]; SIZE];
+    for x in 0..SIZE {
+        puzzle[0][x] = ((x % SIZE) + 1) as Val;
+    }
+
+    let (mut sys, _) = make_sudoku(&puzzle);
+    assert!(sys.solve_any().is_some());
+    let plain_guesses = sys.num_guesses();
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+    sys.disjoint_groups(&vars);
+    assert!(sys.solve_any().is_some());
+    let disjoint_guesses = sys.num_guesses();
+
+    // The extra constraints prune the search.
+    println!("sudoku_disjoint_groups: {} vs {} guesses",
+             disjoint_guesses, plain_guesses);
+    assert!(disjoint_guesses < plain_guesses);
+}
+
+#[test]
+fn sudoku_line_box_reduction() {
+    let puzzle = [
+        [ 8,0,0,  0,0,0,  0,0,0 ],
+        [ 0,0,3,  6,0,0,  0,0,0 ],
+        [ 0,7,0,  0,9,0,  2,0,0 ],
+
+        [ 0,5,0,  0,0,7,  0,0,0 ],
+        [ 0,0,0,  0,4,5,  7,0,0 ],
+        [ 0,0,0,  1,0,0,  0,3,0 ],
+
+        [ 0,0,1,  0,0,0,  0,6,8 ],
+        [ 0,0,8,  5,0,0,  0,1,0 ],
+        [ 0,9,0,  0,0,0,  4,0,0 ] ];
+
+    let (mut sys, _) = make_sudoku(&puzzle);
+    assert!(sys.solve_any().is_some());
+    let plain_guesses = sys.num_guesses();
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+    sys.line_box_reduction(&vars);
+    assert!(sys.solve_any().is_some());
+    let reduced_guesses = sys.num_guesses();
+
+    // The redundant propagators prune the search.
+    println!("sudoku_line_box_reduction: {} vs {} guesses",
+             reduced_guesses, plain_guesses);
+    assert!(reduced_guesses < plain_guesses);
+}
+
+#[test]
+fn sudoku_probe() {
+    let puzzle = [
+        [ 8,0,0,  0,0,0,  0,0,0 ],
+        [ 0,0,3,  6,0,0,  0,0,0 ],
+        [ 0,7,0,  0,9,0,  2,0,0 ],
+
+        [ 0,5,0,  0,0,7,  0,0,0 ],
+        [ 0,0,0,  0,4,5,  7,0,0 ],
+        [ 0,0,0,  1,0,0,  0,3,0 ],
+
+        [ 0,0,1,  0,0,0,  0,6,8 ],
+        [ 0,0,8,  5,0,0,  0,1,0 ],
+        [ 0,9,0,  0,0,0,  4,0,0 ] ];
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+
+    // The top-left block already contains an 8.
+    assert_eq!(sys.probe(&[ (vars[0][2], 8) ]), ProbeResult::Contradiction);
+
+    // The correct digit (the cell solves to 2) propagates cleanly.
+    match sys.probe(&[ (vars[0][2], 2) ]) {
+        ProbeResult::Consistent{ forced, reduced } => {
+            println!("sudoku_probe: {} forced, {} reduced",
+                     forced.len(), reduced.len());
+            assert!(!forced.is_empty());
+            assert!(!reduced.is_empty());
+        },
+        ProbeResult::Contradiction => panic!("expected consistent"),
+    }
+}
+
+#[test]
+fn sudoku_constraint_groups() {
+    let puzzle = [
+        [ 5,3,0,  0,7,0,  0,0,0 ],
+        [ 6,0,0,  1,9,5,  0,0,0 ],
+        [ 0,9,8,  0,0,0,  0,6,0 ],
+
+        [ 8,0,0,  0,6,0,  0,0,3 ],
+        [ 4,0,0,  8,0,3,  0,0,1 ],
+        [ 7,0,0,  0,2,0,  0,0,6 ],
+
+        [ 0,6,0,  0,0,0,  2,8,0 ],
+        [ 0,0,0,  4,1,9,  0,0,5 ],
+        [ 0,0,0,  0,8,0,  0,7,9 ] ];
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+
+    // An extra rule contradicting the solution (the cell is 4).
+    let group = sys.new_group("variant");
+    sys.add_constraint_in(group,
+            puzzle_solver::constraint::Equality::new(vars[0][2] - 1));
+
+    assert_eq!(sys.solve_all().len(), 0);
+
+    sys.set_group_enabled(group, false);
+    assert_eq!(sys.solve_all().len(), 1);
+}
+
+#[test]
+fn sudoku_parse_wikipedia() {
+    let src = "53..7....6..195....98....6.8...6...34..8.3..17...2...6\
+               .6....28....419..5....8..79";
+
+    let grid = puzzle_solver::formats::parse_grid(src,
+            &puzzle_solver::formats::GridSpec::sudoku()).expect("parse");
+    assert_eq!(grid[0].len(), SIZE * SIZE);
+    let values: Vec<Vec<Option<Val>>> = grid[0].chunks(SIZE)
+        .map(|row| row.to_vec())
+        .collect();
+
+    let (mut sys, vars) = make_sudoku(&[[0; SIZE]; SIZE]);
+    sys.set_values_2d(&vars, &values);
+
+    let expected = [
+        [ 5,3,4,  6,7,8,  9,1,2 ],
+        [ 6,7,2,  1,9,5,  3,4,8 ],
+        [ 1,9,8,  3,4,2,  5,6,7 ],
+
+        [ 8,5,9,  7,6,1,  4,2,3 ],
+        [ 4,2,6,  8,5,3,  7,9,1 ],
+        [ 7,1,3,  9,2,4,  8,5,6 ],
+
+        [ 9,6,1,  5,3,7,  2,8,4 ],
+        [ 2,8,7,  4,1,9,  6,3,5 ],
+        [ 3,4,5,  2,8,6,  1,7,9 ] ];
+
+    let solution = sys.solve_any().expect("solution");
+    verify_sudoku(&solution, &vars, &expected);
 }
 
 #[test]
@@ -131,5 +369,130 @@ fn sudoku_wikipedia() {
     let solution = sys.solve_any().expect("solution");
     print_sudoku(&solution, &vars);
     verify_sudoku(&solution, &vars, &expected);
-    println!("sudoku_wikipedia: {} guesses", sys.num_guesses());
+    println!("sudoku_wikipedia: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[test]
+fn sudoku_next_hint() {
+    let puzzle = [
+        [ 5,3,0,  0,7,0,  0,0,0 ],
+        [ 6,0,0,  1,9,5,  0,0,0 ],
+        [ 0,9,8,  0,0,0,  0,6,0 ],
+
+        [ 8,0,0,  0,6,0,  0,0,3 ],
+        [ 4,0,0,  8,0,3,  0,0,1 ],
+        [ 7,0,0,  0,2,0,  0,0,6 ],
+
+        [ 0,6,0,  0,0,0,  2,8,0 ],
+        [ 0,0,0,  4,1,9,  0,0,5 ],
+        [ 0,0,0,  0,8,0,  0,7,9 ] ];
+
+    let expected = [
+        [ 5,3,4,  6,7,8,  9,1,2 ],
+        [ 6,7,2,  1,9,5,  3,4,8 ],
+        [ 1,9,8,  3,4,2,  5,6,7 ],
+
+        [ 8,5,9,  7,6,1,  4,2,3 ],
+        [ 4,2,6,  8,5,3,  7,9,1 ],
+        [ 7,1,3,  9,2,4,  8,5,6 ],
+
+        [ 9,6,1,  5,3,7,  2,8,4 ],
+        [ 2,8,7,  4,1,9,  6,3,5 ],
+        [ 3,4,5,  2,8,6,  1,7,9 ] ];
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+
+    // Each hint must be a deduction that the known solution agrees
+    // with; apply it and ask again.
+    for _ in 0..5 {
+        let hint = sys.next_hint().expect("hint");
+        let (y, x) = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| (y, x)))
+            .find(|&(y, x)| vars[y][x] == hint.var)
+            .expect("unknown variable");
+        assert_eq!(hint.val, expected[y][x]);
+        assert!(hint.because.is_some());
+        sys.set_value(hint.var, hint.val);
+    }
+}
+
+#[test]
+fn sudoku_difficulty() {
+    let easy = [
+        [ 5,3,0,  0,7,0,  0,0,0 ],
+        [ 6,0,0,  1,9,5,  0,0,0 ],
+        [ 0,9,8,  0,0,0,  0,6,0 ],
+
+        [ 8,0,0,  0,6,0,  0,0,3 ],
+        [ 4,0,0,  8,0,3,  0,0,1 ],
+        [ 7,0,0,  0,2,0,  0,0,6 ],
+
+        [ 0,6,0,  0,0,0,  2,8,0 ],
+        [ 0,0,0,  4,1,9,  0,0,5 ],
+        [ 0,0,0,  0,8,0,  0,7,9 ] ];
+
+    let hard = [
+        [ 8,0,0,  0,0,0,  0,0,0 ],
+        [ 0,0,3,  6,0,0,  0,0,0 ],
+        [ 0,7,0,  0,9,0,  2,0,0 ],
+
+        [ 0,5,0,  0,0,7,  0,0,0 ],
+        [ 0,0,0,  0,4,5,  7,0,0 ],
+        [ 0,0,0,  1,0,0,  0,3,0 ],
+
+        [ 0,0,1,  0,0,0,  0,6,8 ],
+        [ 0,0,8,  5,0,0,  0,1,0 ],
+        [ 0,9,0,  0,0,0,  4,0,0 ] ];
+
+    let (mut sys, _) = make_sudoku(&easy);
+    let easy_report = sys.rate_difficulty();
+
+    let (mut sys, _) = make_sudoku(&hard);
+    let hard_report = sys.rate_difficulty();
+
+    println!("sudoku_difficulty: easy {} vs hard {}",
+             easy_report.score, hard_report.score);
+    assert!(easy_report.score < hard_report.score);
+    assert!(!hard_report.propagation_only);
+}
+
+#[test]
+fn sudoku_backbone() {
+    let puzzle = [
+        [ 5,3,0,  0,7,0,  0,0,0 ],
+        [ 6,0,0,  1,9,5,  0,0,0 ],
+        [ 0,9,8,  0,0,0,  0,6,0 ],
+
+        [ 8,0,0,  0,6,0,  0,0,3 ],
+        [ 4,0,0,  8,0,3,  0,0,1 ],
+        [ 7,0,0,  0,2,0,  0,0,6 ],
+
+        [ 0,6,0,  0,0,0,  2,8,0 ],
+        [ 0,0,0,  4,1,9,  0,0,5 ],
+        [ 0,0,0,  0,8,0,  0,7,9 ] ];
+
+    let expected = [
+        [ 5,3,4,  6,7,8,  9,1,2 ],
+        [ 6,7,2,  1,9,5,  3,4,8 ],
+        [ 1,9,8,  3,4,2,  5,6,7 ],
+
+        [ 8,5,9,  7,6,1,  4,2,3 ],
+        [ 4,2,6,  8,5,3,  7,9,1 ],
+        [ 7,1,3,  9,2,4,  8,5,6 ],
+
+        [ 9,6,1,  5,3,7,  2,8,4 ],
+        [ 2,8,7,  4,1,9,  6,3,5 ],
+        [ 3,4,5,  2,8,6,  1,7,9 ] ];
+
+    let (mut sys, vars) = make_sudoku(&puzzle);
+    let backbone = sys.backbone();
+
+    // The solution is unique, so every cell is forced.
+    assert_eq!(backbone.len(), SIZE * SIZE);
+    for &(var, val) in backbone.iter() {
+        let (y, x) = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| (y, x)))
+            .find(|&(y, x)| vars[y][x] == var)
+            .expect("unknown variable");
+        assert_eq!(val, expected[y][x]);
+    }
 }
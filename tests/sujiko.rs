@@ -63,5 +63,6 @@ fn sujiko_simetric() {
     let dict = sys.solve_unique().expect("solution");
     print_sujiko(&dict, &vars);
     verify_sujiko(&dict, &vars, &expected);
-    println!("sujiko_simetric: {} guesses", sys.num_guesses());
+    println!("sujiko_simetric: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
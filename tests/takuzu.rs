@@ -166,7 +166,37 @@ fn takuzu_grid1() {
     assert_eq!(solutions.len(), 6);
 
     print_takuzu(&solutions[0], &vars);
-    println!("takuzu_grid1: {} guesses", sys.num_guesses());
+    println!("takuzu_grid1: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+
+    // Eager singleton promotion keeps the search cost bounded.
+    assert!(sys.num_guesses() <= 12);
+}
+
+#[test]
+fn takuzu_deterministic() {
+    let puzzle = vec![
+        vec![ X,1,0,X,X,X ],
+        vec![ 1,X,X,X,0,X ],
+        vec![ X,X,0,X,X,X ],
+        vec![ 1,1,X,X,1,0 ],
+        vec![ X,X,X,X,0,X ],
+        vec![ X,X,X,X,X,X ] ];
+
+    let (mut sys1, vars1) = make_takuzu(&puzzle);
+    let first1 = sys1.solve_any().expect("solution");
+
+    let (mut sys2, vars2) = make_takuzu(&puzzle);
+    let first2 = sys2.solve_any().expect("solution");
+
+    // Propagation order is deterministic, so the same model solved
+    // twice takes the same guesses and finds the same solution first.
+    assert_eq!(sys1.num_guesses(), sys2.num_guesses());
+    for (row1, row2) in vars1.iter().zip(vars2.iter()) {
+        for (&var1, &var2) in row1.iter().zip(row2.iter()) {
+            assert_eq!(first1[var1], first2[var2]);
+        }
+    }
 }
 
 #[test]
@@ -203,7 +233,8 @@ fn takuzu_grid2() {
     let dict = sys.solve_unique().expect("solution");
     print_takuzu(&dict, &vars);
     verify_takuzu(&dict, &vars, &expected);
-    println!("takuzu_grid2: {} guesses", sys.num_guesses());
+    println!("takuzu_grid2: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
 
 #[test]
@@ -240,7 +271,8 @@ fn takuzu_grid3() {
     let dict = sys.solve_unique().expect("solution");
     print_takuzu(&dict, &vars);
     verify_takuzu(&dict, &vars, &expected);
-    println!("takuzu_grid3: {} guesses", sys.num_guesses());
+    println!("takuzu_grid3: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
 
 #[test]
@@ -262,5 +294,75 @@ fn takuzu_grid4() {
     let (mut sys, vars) = make_takuzu(&puzzle);
     let dict = &sys.solve_any().expect("solution");
     print_takuzu(&dict, &vars);
-    println!("takuzu_grid4: {} guesses", sys.num_guesses());
+    println!("takuzu_grid4: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
+}
+
+#[test]
+fn takuzu_grid1_solution_domains() {
+    let puzzle = vec![
+        vec![ X,1,0,X,X,X ],
+        vec![ 1,X,X,X,0,X ],
+        vec![ X,X,0,X,X,X ],
+        vec![ 1,1,X,X,1,0 ],
+        vec![ X,X,X,X,0,X ],
+        vec![ X,X,X,X,X,X ] ];
+
+    // The union of the values each cell takes over the 6 solutions:
+    // 0/1 = the same value in every solution, 2 = both observed.
+    let expected = [
+        [ 2,1,0,2,2,2 ],
+        [ 1,0,1,2,0,2 ],
+        [ 0,1,0,2,2,2 ],
+        [ 1,1,0,0,1,0 ],
+        [ 2,0,1,1,0,2 ],
+        [ 2,0,1,0,1,2 ] ];
+
+    let (mut sys, _) = make_takuzu(&puzzle);
+    let domains = sys.solution_domains();
+
+    // The grid variables are the first ones created, in row-major
+    // order.
+    for y in 0..6 {
+        for x in 0..6 {
+            let cell: Vec<Val> = domains[6 * y + x].iter().cloned().collect();
+            match expected[y][x] {
+                2 => assert_eq!(cell, &[0,1]),
+                val => assert_eq!(cell, &[val]),
+            }
+        }
+    }
+}
+
+#[test]
+fn takuzu_grid1_backbone() {
+    let puzzle = vec![
+        vec![ X,1,0,X,X,X ],
+        vec![ 1,X,X,X,0,X ],
+        vec![ X,X,0,X,X,X ],
+        vec![ 1,1,X,X,1,0 ],
+        vec![ X,X,X,X,0,X ],
+        vec![ X,X,X,X,X,X ] ];
+
+    // The cells taking the same value in all 6 solutions; 2 = free.
+    let expected = [
+        [ 2,1,0,2,2,2 ],
+        [ 1,0,1,2,0,2 ],
+        [ 0,1,0,2,2,2 ],
+        [ 1,1,0,0,1,0 ],
+        [ 2,0,1,1,0,2 ],
+        [ 2,0,1,0,1,2 ] ];
+
+    let (mut sys, vars) = make_takuzu(&puzzle);
+    let backbone = sys.backbone();
+
+    for y in 0..6 {
+        for x in 0..6 {
+            let entry = backbone.iter().find(|&&(var, _)| var == vars[y][x]);
+            match expected[y][x] {
+                2 => assert!(entry.is_none()),
+                val => assert_eq!(entry, Some(&(vars[y][x], val))),
+            }
+        }
+    }
 }
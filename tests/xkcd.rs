@@ -47,5 +47,6 @@ fn xkcd_knapsack() {
         }
     }
 
-    println!("xkcd_knapsack: {} guesses", sys.num_guesses());
+    println!("xkcd_knapsack: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }
@@ -113,5 +113,6 @@ fn zebra() {
         assert_eq!(dict[nat(n)], dict[pet(p)]);
     }
 
-    println!("zebra: {} guesses", sys.num_guesses());
+    println!("zebra: {} decisions, {} guesses, {} backtracks",
+             sys.num_decisions(), sys.num_guesses(), sys.num_backtracks());
 }